@@ -0,0 +1,408 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    ImageFormat,
+};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PackingAlgorithm {
+    /// Bottom-left skyline, fast and good for rectangles of similar height like glyphs.
+    Skyline,
+    /// Max-rects with best short side fit, slower but packs mixed sizes tighter.
+    MaxRects,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct PackedRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PackedRect {
+    fn right(&self) -> u32 {
+        return self.x + self.width;
+    }
+
+    fn bottom(&self) -> u32 {
+        return self.y + self.height;
+    }
+
+    fn contains(&self, other: &PackedRect) -> bool {
+        return other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom();
+    }
+
+    fn intersects(&self, other: &PackedRect) -> bool {
+        return self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom();
+    }
+}
+
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+enum PackerState {
+    Skyline(Vec<SkylineSegment>),
+    MaxRects(Vec<PackedRect>),
+}
+
+/// Online rectangle packer over a fixed size area, rectangles are never rotated.
+pub struct RectPacker {
+    width: u32,
+    height: u32,
+    state: PackerState,
+}
+
+impl RectPacker {
+    pub fn new(width: u32, height: u32, algorithm: PackingAlgorithm) -> RectPacker {
+        let state = match algorithm {
+            PackingAlgorithm::Skyline => {
+                PackerState::Skyline(vec![SkylineSegment { x: 0, y: 0, width }])
+            }
+            PackingAlgorithm::MaxRects => PackerState::MaxRects(vec![PackedRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }]),
+        };
+        return RectPacker {
+            width,
+            height,
+            state,
+        };
+    }
+
+    /// Finds room for a `width` x `height` rectangle, `None` when it no longer fits.
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<PackedRect> {
+        if width == 0 || height == 0 || width > self.width || height > self.height {
+            return None;
+        }
+        let (area_width, area_height) = (self.width, self.height);
+        match &mut self.state {
+            PackerState::Skyline(segments) => {
+                skyline_insert(segments, area_width, area_height, width, height)
+            }
+            PackerState::MaxRects(free) => max_rects_insert(free, width, height),
+        }
+    }
+}
+
+/// top of a `width` wide rectangle resting on the skyline starting at segment `index`
+fn skyline_fit(
+    segments: &[SkylineSegment],
+    index: usize,
+    area_width: u32,
+    width: u32,
+) -> Option<u32> {
+    let x = segments[index].x;
+    if x + width > area_width {
+        return None;
+    }
+    let mut y = 0;
+    let mut remaining = width as i64;
+    for segment in &segments[index..] {
+        if remaining <= 0 {
+            break;
+        }
+        y = y.max(segment.y);
+        remaining -= segment.width as i64;
+    }
+    return Some(y);
+}
+
+fn skyline_insert(
+    segments: &mut Vec<SkylineSegment>,
+    area_width: u32,
+    area_height: u32,
+    width: u32,
+    height: u32,
+) -> Option<PackedRect> {
+    let mut best: Option<(usize, u32)> = None;
+    for index in 0..segments.len() {
+        if let Some(y) = skyline_fit(segments, index, area_width, width) {
+            if y + height > area_height {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((best_index, best_y)) => {
+                    y < best_y || (y == best_y && segments[index].x < segments[best_index].x)
+                }
+            };
+            if better {
+                best = Some((index, y));
+            }
+        }
+    }
+
+    let (index, y) = best?;
+    let rect = PackedRect {
+        x: segments[index].x,
+        y,
+        width,
+        height,
+    };
+    segments.insert(
+        index,
+        SkylineSegment {
+            x: rect.x,
+            y: rect.bottom(),
+            width,
+        },
+    );
+
+    // shrink or drop the segments now hidden below the new one
+    let next = index + 1;
+    while next < segments.len() {
+        let covered_to = rect.right();
+        if segments[next].x >= covered_to {
+            break;
+        }
+        let overlap = covered_to - segments[next].x;
+        if overlap >= segments[next].width {
+            segments.remove(next);
+        } else {
+            segments[next].x += overlap;
+            segments[next].width -= overlap;
+            break;
+        }
+    }
+
+    // merge neighbours at the same height
+    let mut index = 0;
+    while index + 1 < segments.len() {
+        if segments[index].y == segments[index + 1].y {
+            segments[index].width += segments[index + 1].width;
+            segments.remove(index + 1);
+        } else {
+            index += 1;
+        }
+    }
+    return Some(rect);
+}
+
+fn max_rects_insert(free: &mut Vec<PackedRect>, width: u32, height: u32) -> Option<PackedRect> {
+    let mut best: Option<(PackedRect, u32, u32)> = None;
+    for candidate in free.iter() {
+        if candidate.width < width || candidate.height < height {
+            continue;
+        }
+        let leftover_x = candidate.width - width;
+        let leftover_y = candidate.height - height;
+        let short_side = leftover_x.min(leftover_y);
+        let long_side = leftover_x.max(leftover_y);
+        let better = match best {
+            None => true,
+            Some((_, best_short, best_long)) => {
+                short_side < best_short || (short_side == best_short && long_side < best_long)
+            }
+        };
+        if better {
+            best = Some((
+                PackedRect {
+                    x: candidate.x,
+                    y: candidate.y,
+                    width,
+                    height,
+                },
+                short_side,
+                long_side,
+            ));
+        }
+    }
+
+    let (rect, _, _) = best?;
+    let mut split = vec![];
+    free.retain(|candidate| {
+        if !candidate.intersects(&rect) {
+            return true;
+        }
+        if rect.x > candidate.x {
+            split.push(PackedRect {
+                width: rect.x - candidate.x,
+                ..*candidate
+            });
+        }
+        if rect.right() < candidate.right() {
+            split.push(PackedRect {
+                x: rect.right(),
+                width: candidate.right() - rect.right(),
+                ..*candidate
+            });
+        }
+        if rect.y > candidate.y {
+            split.push(PackedRect {
+                height: rect.y - candidate.y,
+                ..*candidate
+            });
+        }
+        if rect.bottom() < candidate.bottom() {
+            split.push(PackedRect {
+                y: rect.bottom(),
+                height: candidate.bottom() - rect.bottom(),
+                ..*candidate
+            });
+        }
+        return false;
+    });
+    free.append(&mut split);
+
+    // drop free rectangles that are fully inside another one
+    let mut index = 0;
+    while index < free.len() {
+        let redundant = free.iter().enumerate().any(|(other, candidate)| {
+            other != index
+                && candidate.contains(&free[index])
+                && (candidate != &free[index] || other < index)
+        });
+        if redundant {
+            free.swap_remove(index);
+        } else {
+            index += 1;
+        }
+    }
+    return Some(rect);
+}
+
+pub struct AtlasDesc {
+    /// size of the atlas, compressed formats need a multiple of the block size
+    pub width: u32,
+    pub height: u32,
+    pub algorithm: PackingAlgorithm,
+    /// Gutter in texels around every image, filled by repeating the edge of the image so
+    /// filtering and lower mips don't pull in neighbours. Rounded up to whole blocks.
+    pub padding: u32,
+    /// Image positions are aligned to this many texels (rounded up to whole blocks), use
+    /// `1 << (mip_levels - 1)` to keep every image on whole texels of the lower mips.
+    pub alignment: u32,
+}
+
+/// Placement of one image in the atlas, `uv_min`/`uv_max` exclude the gutter.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct AtlasEntry {
+    pub rect: PackedRect,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+pub struct Atlas {
+    pub image: Image,
+    /// one entry per input image, in input order
+    pub entries: Vec<AtlasEntry>,
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    return value.div_ceil(alignment) * alignment;
+}
+
+/// Packs the first mip of `images` into one atlas image. All images have to share a format,
+/// block compressed images are copied block by block and their gutters repeat the edge blocks.
+pub fn build_atlas(images: &[&Image], desc: &AtlasDesc) -> ImageResult<Atlas> {
+    let format = match images.first() {
+        Some(image) => image.format,
+        None => return Err(ImageError::InvalidDimensions),
+    };
+    if format.bit_size_of_block() == 0 || !format.bit_size_of_block().is_multiple_of(8) {
+        return Err(ImageError::UnsupportedFormat(format));
+    }
+    if images.iter().any(|image| image.format != format) {
+        return Err(ImageError::UnsupportedFormat(format));
+    }
+    let block_width = format.width_of_block();
+    let block_height = format.height_of_block();
+    if !desc.width.is_multiple_of(block_width)
+        || !desc.height.is_multiple_of(block_height)
+        || desc.alignment == 0
+    {
+        return Err(ImageError::InvalidDimensions);
+    }
+
+    let padding_x = align(desc.padding, block_width);
+    let padding_y = align(desc.padding, block_height);
+    let alignment_x = align(desc.alignment, block_width);
+    let alignment_y = align(desc.alignment, block_height);
+
+    // place large images first, the packers do noticeably better that way
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse(images[*index].width.max(images[*index].height)));
+
+    let mut packer = RectPacker::new(desc.width, desc.height, desc.algorithm);
+    let mut placements = vec![None; images.len()];
+    for index in order {
+        let image = images[index];
+        let width = align(align(image.width, block_width) + padding_x * 2, alignment_x);
+        let height = align(
+            align(image.height, block_height) + padding_y * 2,
+            alignment_y,
+        );
+        let slot = packer.insert(width, height).ok_or(ImageError::AtlasFull)?;
+        placements[index] = Some(PackedRect {
+            x: slot.x + padding_x,
+            y: slot.y + padding_y,
+            width: image.width,
+            height: image.height,
+        });
+    }
+
+    let mut atlas = Image::new(format, desc.width, desc.height, 1, 1, 1);
+    let block_size = format.bit_size_of_block() as usize / 8;
+    let atlas_pitch = atlas.row_pitch(0);
+    let mut entries = Vec::with_capacity(images.len());
+    for (image, rect) in images.iter().zip(placements) {
+        let rect = rect.unwrap();
+        let source = image.subresource(0, 0);
+        let source_pitch = image.row_pitch(0);
+        let blocks_x = image.width.div_ceil(block_width) as i64;
+        let blocks_y = image.height.div_ceil(block_height) as i64;
+        let gutter_x = (padding_x / block_width) as i64;
+        let gutter_y = (padding_y / block_height) as i64;
+        let origin_x = (rect.x / block_width) as i64;
+        let origin_y = (rect.y / block_height) as i64;
+
+        for block_y in -gutter_y..blocks_y + gutter_y {
+            let source_y = block_y.clamp(0, blocks_y - 1) as usize;
+            for block_x in -gutter_x..blocks_x + gutter_x {
+                let source_x = block_x.clamp(0, blocks_x - 1) as usize;
+                let from = source_y * source_pitch + source_x * block_size;
+                let to = (origin_y + block_y) as usize * atlas_pitch
+                    + (origin_x + block_x) as usize * block_size;
+                atlas.data[to..to + block_size].copy_from_slice(&source[from..from + block_size]);
+            }
+        }
+
+        entries.push(AtlasEntry {
+            rect,
+            uv_min: [
+                rect.x as f32 / desc.width as f32,
+                rect.y as f32 / desc.height as f32,
+            ],
+            uv_max: [
+                rect.right() as f32 / desc.width as f32,
+                rect.bottom() as f32 / desc.height as f32,
+            ],
+        });
+    }
+    return Ok(Atlas {
+        image: atlas,
+        entries,
+    });
+}
+
+/// Smallest texel alignment that keeps an image on whole texels down to `mip_levels` mips.
+pub fn mip_alignment(format: ImageFormat, mip_levels: u32) -> u32 {
+    let alignment = 1 << mip_levels.saturating_sub(1);
+    return align(
+        alignment,
+        format.width_of_block().max(format.height_of_block()),
+    );
+}
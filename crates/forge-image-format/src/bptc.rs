@@ -0,0 +1,1581 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    ImageFormat,
+};
+use half::f16;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CompressionEffort {
+    /// a few common modes, first partition guess and no rotations
+    Fast,
+    /// every mode with the best few partitions of each
+    Normal,
+    /// every mode, partition, rotation and p-bit combination
+    Thorough,
+}
+
+pub struct BptcDesc {
+    /// `DXBC7_UNORM`, `DXBC7_SRGB`, `DXBC6H_UFLOAT` or `DXBC6H_SFLOAT`
+    pub format: ImageFormat,
+    pub effort: CompressionEffort,
+}
+
+struct EffortSettings {
+    /// partitions tried per mode, taken from the best line fits
+    partitions: usize,
+    refine_iterations: u32,
+    exhaustive_pbits: bool,
+    all_rotations: bool,
+    bc7_modes: &'static [usize],
+    bc6h_modes: &'static [usize],
+}
+
+fn effort_settings(effort: CompressionEffort) -> EffortSettings {
+    return match effort {
+        CompressionEffort::Fast => EffortSettings {
+            partitions: 1,
+            refine_iterations: 1,
+            exhaustive_pbits: false,
+            all_rotations: false,
+            bc7_modes: &[1, 5, 6],
+            bc6h_modes: &[0, 10, 11, 13],
+        },
+        CompressionEffort::Normal => EffortSettings {
+            partitions: 4,
+            refine_iterations: 2,
+            exhaustive_pbits: false,
+            all_rotations: true,
+            bc7_modes: &[0, 1, 2, 3, 4, 5, 6, 7],
+            bc6h_modes: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13],
+        },
+        CompressionEffort::Thorough => EffortSettings {
+            partitions: 64,
+            refine_iterations: 4,
+            exhaustive_pbits: true,
+            all_rotations: true,
+            bc7_modes: &[0, 1, 2, 3, 4, 5, 6, 7],
+            bc6h_modes: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13],
+        },
+    };
+}
+
+/// Compresses every mip, layer and slice of `image` to BC7 or BC6H.
+///
+/// BC7 reads the image as 8 bit rgba, in srgb for `DXBC7_SRGB`, BC6H reads it as 32 bit float
+/// and ignores alpha. Partial blocks at the edges repeat the last row and column.
+pub fn compress_bptc(image: &Image, desc: &BptcDesc) -> ImageResult<Image> {
+    let (source_format, bc6h, signed) = match desc.format {
+        ImageFormat::DXBC7_UNORM => (ImageFormat::R8G8B8A8_UNORM, false, false),
+        ImageFormat::DXBC7_SRGB => (ImageFormat::R8G8B8A8_SRGB, false, false),
+        ImageFormat::DXBC6H_UFLOAT => (ImageFormat::R32G32B32A32_SFLOAT, true, false),
+        ImageFormat::DXBC6H_SFLOAT => (ImageFormat::R32G32B32A32_SFLOAT, true, true),
+        _ => return Err(ImageError::UnsupportedFormat(desc.format)),
+    };
+    let source = image.convert(source_format)?;
+    let texel_size = source_format.bit_size_of_block() as usize / 8;
+
+    let mut result = Image::new(
+        desc.format,
+        image.width,
+        image.height,
+        image.depth,
+        image.array_size,
+        image.mip_levels,
+    );
+    for mip in 0..image.mip_levels {
+        let (width, height, depth) = source.mip_extent(mip);
+        let source_row_pitch = source.row_pitch(mip);
+        let source_slice_pitch = source.slice_pitch(mip);
+        let row_pitch = result.row_pitch(mip);
+        let slice_pitch = result.slice_pitch(mip);
+        for layer in 0..image.array_size {
+            let texels = source.subresource(mip, layer);
+            let blocks = result.subresource_mut(mip, layer);
+            for z in 0..depth as usize {
+                for block_y in 0..height.div_ceil(4) as usize {
+                    for block_x in 0..width.div_ceil(4) as usize {
+                        let texel_offset = |pixel: usize| {
+                            let x = (block_x * 4 + pixel % 4).min(width as usize - 1);
+                            let y = (block_y * 4 + pixel / 4).min(height as usize - 1);
+                            return z * source_slice_pitch + y * source_row_pitch + x * texel_size;
+                        };
+                        let block = if bc6h {
+                            let mut pixels = [[0.0f32; 3]; 16];
+                            for (pixel, rgb) in pixels.iter_mut().enumerate() {
+                                let offset = texel_offset(pixel);
+                                for (channel, value) in rgb.iter_mut().enumerate() {
+                                    let at = offset + channel * 4;
+                                    *value =
+                                        f32::from_le_bytes(texels[at..at + 4].try_into().unwrap());
+                                }
+                            }
+                            encode_bc6h_block(&pixels, signed, desc.effort)
+                        } else {
+                            let mut pixels = [[0u8; 4]; 16];
+                            for (pixel, rgba) in pixels.iter_mut().enumerate() {
+                                let offset = texel_offset(pixel);
+                                rgba.copy_from_slice(&texels[offset..offset + 4]);
+                            }
+                            encode_bc7_block(&pixels, desc.effort)
+                        };
+                        let offset = z * slice_pitch + block_y * row_pitch + block_x * 16;
+                        blocks[offset..offset + 16].copy_from_slice(&block);
+                    }
+                }
+            }
+        }
+    }
+    return Ok(result);
+}
+
+/// subset of each pixel for the two subset partitions, two bits per pixel
+const PARTITIONS_2: [u32; 64] = [
+    0x50505050, 0x40404040, 0x54545454, 0x54505040, 0x50404000, 0x55545450, 0x55545040, 0x54504000,
+    0x50400000, 0x55555450, 0x55544000, 0x54400000, 0x55555440, 0x55550000, 0x55555500, 0x55000000,
+    0x55150100, 0x00004054, 0x15010000, 0x00405054, 0x00004050, 0x15050100, 0x05010000, 0x40505054,
+    0x00404050, 0x05010100, 0x14141414, 0x05141450, 0x01155440, 0x00555500, 0x15014054, 0x05414150,
+    0x44444444, 0x55005500, 0x11441144, 0x05055050, 0x05500550, 0x11114444, 0x41144114, 0x44111144,
+    0x15055054, 0x01055040, 0x05041050, 0x05455150, 0x14414114, 0x50050550, 0x41411414, 0x00141400,
+    0x00041504, 0x00105410, 0x10541000, 0x04150400, 0x50410514, 0x41051450, 0x05415014, 0x14054150,
+    0x41050514, 0x41505014, 0x40011554, 0x54150140, 0x50505500, 0x00555050, 0x15151010, 0x54540404,
+];
+
+const PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// anchor pixel of the second subset, the first subset always anchors on pixel 0
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// anchor pixels of the second and third subset
+#[rustfmt::skip]
+const ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weights(index_bits: u32) -> &'static [u32] {
+    return match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    };
+}
+
+fn subset_of(subsets: usize, partition: usize, pixel: usize) -> usize {
+    let table = match subsets {
+        2 => PARTITIONS_2[partition],
+        3 => PARTITIONS_3[partition],
+        _ => return 0,
+    };
+    return ((table >> (2 * pixel)) & 3) as usize;
+}
+
+fn anchor_of(subsets: usize, partition: usize, subset: usize) -> usize {
+    return match (subsets, subset) {
+        (_, 0) => 0,
+        (2, _) => ANCHORS_2[partition] as usize,
+        _ => ANCHORS_3[partition][subset - 1] as usize,
+    };
+}
+
+fn is_anchor(subsets: usize, partition: usize, pixel: usize) -> bool {
+    return (0..subsets).any(|subset| anchor_of(subsets, partition, subset) == pixel);
+}
+
+struct BitWriter {
+    bits: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        return BitWriter {
+            bits: 0,
+            position: 0,
+        };
+    }
+
+    fn write(&mut self, value: u32, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let mask = (1u64 << count) - 1;
+        self.bits |= ((value as u64 & mask) as u128) << self.position;
+        self.position += count;
+    }
+
+    fn finish(self) -> [u8; 16] {
+        debug_assert_eq!(self.position, 128);
+        return self.bits.to_le_bytes();
+    }
+}
+
+/// mean and principal axis of the points over `channels`
+fn principal_axis(points: &[[f32; 4]], channels: &[usize]) -> ([f32; 4], [f32; 4]) {
+    let mut mean = [0.0f32; 4];
+    for point in points {
+        for &channel in channels {
+            mean[channel] += point[channel];
+        }
+    }
+    for &channel in channels {
+        mean[channel] /= points.len() as f32;
+    }
+
+    let mut covariance = [[0.0f32; 4]; 4];
+    for point in points {
+        for &i in channels {
+            for &j in channels {
+                covariance[i][j] += (point[i] - mean[i]) * (point[j] - mean[j]);
+            }
+        }
+    }
+
+    // power iteration, seeded with the channel of largest variance
+    let seed = channels
+        .iter()
+        .copied()
+        .max_by(|a, b| covariance[*a][*a].total_cmp(&covariance[*b][*b]))
+        .unwrap();
+    let mut axis = [0.0f32; 4];
+    axis[seed] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0f32; 4];
+        for &i in channels {
+            for &j in channels {
+                next[i] += covariance[i][j] * axis[j];
+            }
+        }
+        let length = channels
+            .iter()
+            .map(|&channel| next[channel] * next[channel])
+            .sum::<f32>()
+            .sqrt();
+        if length < 1e-12 {
+            break;
+        }
+        for &channel in channels {
+            axis[channel] = next[channel] / length;
+        }
+    }
+    return (mean, axis);
+}
+
+fn project(point: &[f32; 4], mean: &[f32; 4], axis: &[f32; 4], channels: &[usize]) -> f32 {
+    return channels
+        .iter()
+        .map(|&channel| (point[channel] - mean[channel]) * axis[channel])
+        .sum();
+}
+
+/// endpoints spanning the points along their principal axis
+fn fit_endpoints(points: &[[f32; 4]], channels: &[usize]) -> [[f32; 4]; 2] {
+    let (mean, axis) = principal_axis(points, channels);
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+    for point in points {
+        let t = project(point, &mean, &axis, channels);
+        min = min.min(t);
+        max = max.max(t);
+    }
+    let mut endpoints = [[0.0f32; 4]; 2];
+    for &channel in channels {
+        endpoints[0][channel] = mean[channel] + axis[channel] * min;
+        endpoints[1][channel] = mean[channel] + axis[channel] * max;
+    }
+    return endpoints;
+}
+
+/// squared distance of the points to their principal axis, used to rank partitions
+fn line_fit_error(points: &[[f32; 4]], channels: &[usize]) -> f32 {
+    if points.is_empty() {
+        return 0.0;
+    }
+    let (mean, axis) = principal_axis(points, channels);
+    let mut error = 0.0;
+    for point in points {
+        let t = project(point, &mean, &axis, channels);
+        let distance: f32 = channels
+            .iter()
+            .map(|&channel| (point[channel] - mean[channel]).powi(2))
+            .sum();
+        error += (distance - t * t).max(0.0);
+    }
+    return error;
+}
+
+/// Least squares endpoints for fixed interpolation factors, `None` when all factors are equal.
+fn refine_endpoints(
+    points: &[[f32; 4]],
+    factors: &[f32],
+    channels: &[usize],
+) -> Option<[[f32; 4]; 2]> {
+    let mut aa = 0.0f32;
+    let mut ab = 0.0f32;
+    let mut bb = 0.0f32;
+    let mut ax = [0.0f32; 4];
+    let mut bx = [0.0f32; 4];
+    for (point, &t) in points.iter().zip(factors) {
+        let a = 1.0 - t;
+        aa += a * a;
+        ab += a * t;
+        bb += t * t;
+        for &channel in channels {
+            ax[channel] += a * point[channel];
+            bx[channel] += t * point[channel];
+        }
+    }
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let mut endpoints = [[0.0f32; 4]; 2];
+    for &channel in channels {
+        endpoints[0][channel] = (bb * ax[channel] - ab * bx[channel]) / determinant;
+        endpoints[1][channel] = (aa * bx[channel] - ab * ax[channel]) / determinant;
+    }
+    return Some(endpoints);
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum PBits {
+    None,
+    /// one p-bit per endpoint
+    Unique,
+    /// one p-bit per subset, shared by both endpoints
+    Shared,
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    pbits: PBits,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 4,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 4,
+        alpha_bits: 0,
+        pbits: PBits::Unique,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 6,
+        alpha_bits: 0,
+        pbits: PBits::Shared,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 0,
+        pbits: PBits::None,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 0,
+        pbits: PBits::Unique,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 1,
+        color_bits: 5,
+        alpha_bits: 6,
+        pbits: PBits::None,
+        index_bits: 2,
+        secondary_index_bits: 3,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 8,
+        pbits: PBits::None,
+        index_bits: 2,
+        secondary_index_bits: 2,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 7,
+        pbits: PBits::Unique,
+        index_bits: 4,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 5,
+        pbits: PBits::Unique,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+];
+
+fn bc7_unquantize(stored: u32, pbit: Option<u32>, bits: u32) -> u32 {
+    let (value, bits) = match pbit {
+        Some(pbit) => ((stored << 1) | pbit, bits + 1),
+        None => (stored, bits),
+    };
+    let value = value << (8 - bits);
+    return value | (value >> bits);
+}
+
+/// closest stored value for `value`, returns it with its unquantized value
+fn bc7_quantize(value: f32, bits: u32, pbit: Option<u32>) -> (u32, u32) {
+    let max = (1i32 << bits) - 1;
+    let guess = match pbit {
+        Some(pbit) => {
+            ((value / 255.0 * ((1 << (bits + 1)) - 1) as f32 - pbit as f32) / 2.0).round() as i32
+        }
+        None => (value / 255.0 * max as f32).round() as i32,
+    };
+    let mut best = (0, 0);
+    let mut best_error = f32::MAX;
+    for candidate in guess - 1..=guess + 1 {
+        let stored = candidate.clamp(0, max) as u32;
+        let unquantized = bc7_unquantize(stored, pbit, bits);
+        let error = (unquantized as f32 - value).abs();
+        if error < best_error {
+            best_error = error;
+            best = (stored, unquantized);
+        }
+    }
+    return best;
+}
+
+/// one subset of a BC7 block, `indices` are in the order of the subset's pixels
+struct Bc7SubsetFit {
+    stored: [[u32; 4]; 2],
+    pbits: [u32; 2],
+    indices: [u8; 16],
+    error: f32,
+}
+
+impl Bc7SubsetFit {
+    /// Swaps the endpoints if needed so the anchor index has its top bit clear.
+    fn fix_anchor(&mut self, anchor: usize, count: usize, index_bits: u32) {
+        let max = (1u8 << index_bits) - 1;
+        if self.indices[anchor] <= max / 2 {
+            return;
+        }
+        self.stored.swap(0, 1);
+        self.pbits.swap(0, 1);
+        for index in &mut self.indices[..count] {
+            *index = max - *index;
+        }
+    }
+}
+
+/// bits per channel and p-bit layout of the endpoints of one subset
+struct Bc7EndpointFormat {
+    bits: [u32; 4],
+    pbits: PBits,
+    index_bits: u32,
+}
+
+fn bc7_quantize_endpoints(
+    endpoints: &[[f32; 4]; 2],
+    channels: &[usize],
+    format: &Bc7EndpointFormat,
+    pbits: [u32; 2],
+) -> ([[u32; 4]; 2], [[u32; 4]; 2], f32) {
+    let mut stored = [[0u32; 4]; 2];
+    let mut unquantized = [[0u32; 4]; 2];
+    let mut error = 0.0;
+    for endpoint in 0..2 {
+        let pbit = match format.pbits {
+            PBits::None => None,
+            _ => Some(pbits[endpoint]),
+        };
+        for &channel in channels {
+            let value = endpoints[endpoint][channel].clamp(0.0, 255.0);
+            let (quantized, unquantized_value) = bc7_quantize(value, format.bits[channel], pbit);
+            stored[endpoint][channel] = quantized;
+            unquantized[endpoint][channel] = unquantized_value;
+            error += (unquantized_value as f32 - value).powi(2);
+        }
+    }
+    return (stored, unquantized, error);
+}
+
+/// p-bit combinations worth trying for the given endpoints
+fn bc7_pbit_candidates(
+    endpoints: &[[f32; 4]; 2],
+    channels: &[usize],
+    format: &Bc7EndpointFormat,
+    exhaustive: bool,
+) -> Vec<[u32; 2]> {
+    let all = match format.pbits {
+        PBits::None => return vec![[0, 0]],
+        PBits::Unique => vec![[0, 0], [0, 1], [1, 0], [1, 1]],
+        PBits::Shared => vec![[0, 0], [1, 1]],
+    };
+    if exhaustive {
+        return all;
+    }
+    let best = all
+        .into_iter()
+        .min_by(|a, b| {
+            let error_a = bc7_quantize_endpoints(endpoints, channels, format, *a).2;
+            let error_b = bc7_quantize_endpoints(endpoints, channels, format, *b).2;
+            error_a.total_cmp(&error_b)
+        })
+        .unwrap();
+    return vec![best];
+}
+
+/// nearest palette entry for every point, returns the indices and the total squared error
+fn bc7_assign_indices(
+    points: &[[f32; 4]],
+    channels: &[usize],
+    unquantized: &[[u32; 4]; 2],
+    index_bits: u32,
+) -> ([u8; 16], f32) {
+    let weights = weights(index_bits);
+    let mut palette = [[0.0f32; 4]; 16];
+    for (entry, &weight) in palette.iter_mut().zip(weights) {
+        for &channel in channels {
+            entry[channel] =
+                ((unquantized[0][channel] * (64 - weight) + unquantized[1][channel] * weight + 32)
+                    >> 6) as f32;
+        }
+    }
+
+    let mut indices = [0u8; 16];
+    let mut total = 0.0;
+    for (point, index) in points.iter().zip(indices.iter_mut()) {
+        let mut best_error = f32::MAX;
+        for (candidate, entry) in palette[..weights.len()].iter().enumerate() {
+            let error: f32 = channels
+                .iter()
+                .map(|&channel| (entry[channel] - point[channel]).powi(2))
+                .sum();
+            if error < best_error {
+                best_error = error;
+                *index = candidate as u8;
+            }
+        }
+        total += best_error;
+    }
+    return (indices, total);
+}
+
+fn bc7_encode_subset(
+    points: &[[f32; 4]],
+    channels: &[usize],
+    format: &Bc7EndpointFormat,
+    settings: &EffortSettings,
+) -> Bc7SubsetFit {
+    let weights = weights(format.index_bits);
+    let mut endpoints = fit_endpoints(points, channels);
+    let mut best: Option<Bc7SubsetFit> = None;
+    for iteration in 0..=settings.refine_iterations {
+        for pbits in bc7_pbit_candidates(&endpoints, channels, format, settings.exhaustive_pbits) {
+            let (stored, unquantized, _) =
+                bc7_quantize_endpoints(&endpoints, channels, format, pbits);
+            let (indices, error) =
+                bc7_assign_indices(points, channels, &unquantized, format.index_bits);
+            if best.as_ref().is_none_or(|best| error < best.error) {
+                best = Some(Bc7SubsetFit {
+                    stored,
+                    pbits,
+                    indices,
+                    error,
+                });
+            }
+        }
+        let fit = best.as_ref().unwrap();
+        if iteration == settings.refine_iterations || fit.error == 0.0 {
+            break;
+        }
+        let factors: Vec<f32> = fit.indices[..points.len()]
+            .iter()
+            .map(|&index| weights[index as usize] as f32 / 64.0)
+            .collect();
+        match refine_endpoints(points, &factors, channels) {
+            Some(refined) => endpoints = refined,
+            None => break,
+        }
+    }
+    return best.unwrap();
+}
+
+pub(crate) struct Bc7Block {
+    pub(crate) mode: usize,
+    pub(crate) partition: usize,
+    pub(crate) rotation: u32,
+    pub(crate) index_selection: u32,
+    /// endpoint values without their p-bits, per subset, endpoint and channel
+    pub(crate) stored: [[[u32; 4]; 2]; 3],
+    pub(crate) pbits: [[u32; 2]; 3],
+    pub(crate) indices: [u8; 16],
+    pub(crate) secondary_indices: [u8; 16],
+    pub(crate) error: f32,
+}
+
+impl Bc7Block {
+    /// Swaps the endpoints of every subset whose anchor index has its top bit set, for blocks
+    /// built from endpoints and indices that didn't come from this encoder.
+    pub(crate) fn fix_anchors(&mut self) {
+        let mode = &BC7_MODES[self.mode];
+        // the channels each index set interpolates
+        let (primary, secondary) = if self.index_selection == 1 {
+            (3..4, 0..3)
+        } else if mode.secondary_index_bits > 0 {
+            (0..3, 3..4)
+        } else {
+            (0..4, 0..0)
+        };
+        for subset in 0..mode.subsets {
+            let anchor = anchor_of(mode.subsets, self.partition, subset);
+            let max = (1u8 << mode.index_bits) - 1;
+            if self.indices[anchor] > max / 2 {
+                for pixel in 0..16 {
+                    if subset_of(mode.subsets, self.partition, pixel) == subset {
+                        self.indices[pixel] = max - self.indices[pixel];
+                    }
+                }
+                let [low, high] = &mut self.stored[subset];
+                for channel in primary.clone() {
+                    std::mem::swap(&mut low[channel], &mut high[channel]);
+                }
+                if mode.pbits == PBits::Unique {
+                    self.pbits[subset].swap(0, 1);
+                }
+            }
+        }
+        if mode.secondary_index_bits > 0 {
+            let max = (1u8 << mode.secondary_index_bits) - 1;
+            if self.secondary_indices[0] > max / 2 {
+                for index in self.secondary_indices.iter_mut() {
+                    *index = max - *index;
+                }
+                let [low, high] = &mut self.stored[0];
+                for channel in secondary {
+                    std::mem::swap(&mut low[channel], &mut high[channel]);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn pack(&self) -> [u8; 16] {
+        let mode = &BC7_MODES[self.mode];
+        let mut writer = BitWriter::new();
+        writer.write(1 << self.mode, self.mode as u32 + 1);
+        writer.write(self.partition as u32, mode.partition_bits);
+        writer.write(self.rotation, mode.rotation_bits);
+        writer.write(self.index_selection, mode.index_selection_bits);
+        for channel in 0..3 {
+            for subset in 0..mode.subsets {
+                for endpoint in 0..2 {
+                    writer.write(self.stored[subset][endpoint][channel], mode.color_bits);
+                }
+            }
+        }
+        for subset in 0..mode.subsets {
+            for endpoint in 0..2 {
+                writer.write(self.stored[subset][endpoint][3], mode.alpha_bits);
+            }
+        }
+        for subset in 0..mode.subsets {
+            match mode.pbits {
+                PBits::None => {}
+                PBits::Unique => {
+                    writer.write(self.pbits[subset][0], 1);
+                    writer.write(self.pbits[subset][1], 1);
+                }
+                PBits::Shared => writer.write(self.pbits[subset][0], 1),
+            }
+        }
+        for (pixel, &index) in self.indices.iter().enumerate() {
+            let anchor = is_anchor(mode.subsets, self.partition, pixel) as u32;
+            writer.write(index as u32, mode.index_bits - anchor);
+        }
+        if mode.secondary_index_bits > 0 {
+            for (pixel, &index) in self.secondary_indices.iter().enumerate() {
+                let anchor = (pixel == 0) as u32;
+                writer.write(index as u32, mode.secondary_index_bits - anchor);
+            }
+        }
+        return writer.finish();
+    }
+}
+
+/// Encodes one 4x4 block of rgba8 pixels in row order to BC7.
+pub fn encode_bc7_block(pixels: &[[u8; 4]; 16], effort: CompressionEffort) -> [u8; 16] {
+    let settings = effort_settings(effort);
+    let points: Vec<[f32; 4]> = pixels
+        .iter()
+        .map(|pixel| pixel.map(|channel| channel as f32))
+        .collect();
+    // modes without alpha decode to opaque
+    let opaque_error: f32 = points.iter().map(|point| (255.0 - point[3]).powi(2)).sum();
+
+    let mut best: Option<Bc7Block> = None;
+    for &mode_index in settings.bc7_modes {
+        let mode = &BC7_MODES[mode_index];
+        let base_error = if mode.alpha_bits == 0 {
+            opaque_error
+        } else {
+            0.0
+        };
+        if best.as_ref().is_some_and(|best| base_error >= best.error) {
+            continue;
+        }
+
+        if mode.subsets == 1 {
+            let candidate = bc7_encode_single(&points, mode_index, &settings);
+            if best
+                .as_ref()
+                .is_none_or(|best| candidate.error < best.error)
+            {
+                best = Some(candidate);
+            }
+            continue;
+        }
+
+        let channels: &[usize] = if mode.alpha_bits > 0 {
+            &[0, 1, 2, 3]
+        } else {
+            &[0, 1, 2]
+        };
+        let format = Bc7EndpointFormat {
+            bits: [
+                mode.color_bits,
+                mode.color_bits,
+                mode.color_bits,
+                mode.alpha_bits,
+            ],
+            pbits: mode.pbits,
+            index_bits: mode.index_bits,
+        };
+        let partition_count = 1usize << mode.partition_bits;
+        let mut ranked: Vec<(usize, f32)> = (0..partition_count)
+            .map(|partition| {
+                let error = (0..mode.subsets)
+                    .map(|subset| {
+                        let members: Vec<[f32; 4]> = (0..16)
+                            .filter(|&pixel| subset_of(mode.subsets, partition, pixel) == subset)
+                            .map(|pixel| points[pixel])
+                            .collect();
+                        line_fit_error(&members, channels)
+                    })
+                    .sum();
+                (partition, error)
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        for &(partition, _) in ranked.iter().take(settings.partitions) {
+            let mut candidate = Bc7Block {
+                mode: mode_index,
+                partition,
+                rotation: 0,
+                index_selection: 0,
+                stored: [[[0; 4]; 2]; 3],
+                pbits: [[0; 2]; 3],
+                indices: [0; 16],
+                secondary_indices: [0; 16],
+                error: base_error,
+            };
+            for subset in 0..mode.subsets {
+                let members: Vec<usize> = (0..16)
+                    .filter(|&pixel| subset_of(mode.subsets, partition, pixel) == subset)
+                    .collect();
+                let subset_points: Vec<[f32; 4]> =
+                    members.iter().map(|&pixel| points[pixel]).collect();
+                let mut fit = bc7_encode_subset(&subset_points, channels, &format, &settings);
+                let anchor = anchor_of(mode.subsets, partition, subset);
+                let local_anchor = members.iter().position(|&pixel| pixel == anchor).unwrap();
+                fit.fix_anchor(local_anchor, members.len(), mode.index_bits);
+                for (local, &pixel) in members.iter().enumerate() {
+                    candidate.indices[pixel] = fit.indices[local];
+                }
+                candidate.stored[subset] = fit.stored;
+                candidate.pbits[subset] = fit.pbits;
+                candidate.error += fit.error;
+            }
+            if best
+                .as_ref()
+                .is_none_or(|best| candidate.error < best.error)
+            {
+                best = Some(candidate);
+            }
+        }
+    }
+    return best.unwrap().pack();
+}
+
+/// modes 4, 5 and 6, trying the rotations and index selections the effort allows
+fn bc7_encode_single(
+    points: &[[f32; 4]],
+    mode_index: usize,
+    settings: &EffortSettings,
+) -> Bc7Block {
+    let mode = &BC7_MODES[mode_index];
+    let rotations = if mode.rotation_bits > 0 && settings.all_rotations {
+        4
+    } else {
+        1
+    };
+    let mut best: Option<Bc7Block> = None;
+    for rotation in 0..rotations {
+        for index_selection in 0..(1 << mode.index_selection_bits) {
+            // the decoder swaps alpha back into the rotated channel
+            let rotated: Vec<[f32; 4]> = points
+                .iter()
+                .map(|point| {
+                    let mut point = *point;
+                    if rotation > 0 {
+                        point.swap(3, rotation - 1);
+                    }
+                    point
+                })
+                .collect();
+            let mut candidate = Bc7Block {
+                mode: mode_index,
+                partition: 0,
+                rotation: rotation as u32,
+                index_selection,
+                stored: [[[0; 4]; 2]; 3],
+                pbits: [[0; 2]; 3],
+                indices: [0; 16],
+                secondary_indices: [0; 16],
+                error: 0.0,
+            };
+
+            if mode.secondary_index_bits == 0 {
+                let format = Bc7EndpointFormat {
+                    bits: [
+                        mode.color_bits,
+                        mode.color_bits,
+                        mode.color_bits,
+                        mode.alpha_bits,
+                    ],
+                    pbits: mode.pbits,
+                    index_bits: mode.index_bits,
+                };
+                let mut fit = bc7_encode_subset(&rotated, &[0, 1, 2, 3], &format, settings);
+                fit.fix_anchor(0, 16, mode.index_bits);
+                candidate.stored[0] = fit.stored;
+                candidate.pbits[0] = fit.pbits;
+                candidate.indices = fit.indices;
+                candidate.error = fit.error;
+            } else {
+                // separate color and alpha indices, the selection bit swaps their precision
+                let (color_index_bits, alpha_index_bits) = if index_selection == 1 {
+                    (mode.secondary_index_bits, mode.index_bits)
+                } else {
+                    (mode.index_bits, mode.secondary_index_bits)
+                };
+                let bits = [
+                    mode.color_bits,
+                    mode.color_bits,
+                    mode.color_bits,
+                    mode.alpha_bits,
+                ];
+                let color_format = Bc7EndpointFormat {
+                    bits,
+                    pbits: PBits::None,
+                    index_bits: color_index_bits,
+                };
+                let alpha_format = Bc7EndpointFormat {
+                    bits,
+                    pbits: PBits::None,
+                    index_bits: alpha_index_bits,
+                };
+                let mut color = bc7_encode_subset(&rotated, &[0, 1, 2], &color_format, settings);
+                let mut alpha = bc7_encode_subset(&rotated, &[3], &alpha_format, settings);
+                color.fix_anchor(0, 16, color_index_bits);
+                alpha.fix_anchor(0, 16, alpha_index_bits);
+                for endpoint in 0..2 {
+                    candidate.stored[0][endpoint] = color.stored[endpoint];
+                    candidate.stored[0][endpoint][3] = alpha.stored[endpoint][3];
+                }
+                if index_selection == 1 {
+                    candidate.indices = alpha.indices;
+                    candidate.secondary_indices = color.indices;
+                } else {
+                    candidate.indices = color.indices;
+                    candidate.secondary_indices = alpha.indices;
+                }
+                candidate.error = color.error + alpha.error;
+            }
+            if best
+                .as_ref()
+                .is_none_or(|best| candidate.error < best.error)
+            {
+                best = Some(candidate);
+            }
+        }
+    }
+    return best.unwrap();
+}
+
+// BC6H endpoint fields, `W` and `X` are the first subset, `Y` and `Z` the second
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+/// partition index
+const D: u8 = 12;
+
+struct Bc6hMode {
+    header: u32,
+    header_bits: u32,
+    subsets: usize,
+    endpoint_bits: u32,
+    delta_bits: [u32; 3],
+    transformed: bool,
+    /// (field, first bit, bit count) in the order they're stored after the header
+    layout: &'static [(u8, u8, u8)],
+}
+
+#[rustfmt::skip]
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode {
+        header: 0b00,
+        header_bits: 2,
+        subsets: 2,
+        endpoint_bits: 10,
+        delta_bits: [5, 5, 5],
+        transformed: true,
+        layout: &[
+            (GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10),
+            (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4),
+            (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5),
+            (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b01,
+        header_bits: 2,
+        subsets: 2,
+        endpoint_bits: 7,
+        delta_bits: [6, 6, 6],
+        transformed: true,
+        layout: &[
+            (GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1),
+            (BY, 4, 1), (GW, 0, 7), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7),
+            (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6),
+            (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b00010,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 11,
+        delta_bits: [5, 4, 4],
+        transformed: true,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4),
+            (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1),
+            (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+            (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b00110,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 11,
+        delta_bits: [4, 5, 4],
+        transformed: true,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1),
+            (GY, 0, 4), (GX, 0, 5), (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1),
+            (BZ, 1, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4),
+            (GY, 4, 1), (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b01010,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 11,
+        delta_bits: [4, 4, 5],
+        transformed: true,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1),
+            (GY, 0, 4), (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5),
+            (BW, 10, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1), (RZ, 0, 4),
+            (BZ, 4, 1), (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b01110,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 9,
+        delta_bits: [5, 5, 5],
+        transformed: true,
+        layout: &[
+            (RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1),
+            (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4),
+            (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5),
+            (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b10010,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 8,
+        delta_bits: [6, 5, 5],
+        transformed: true,
+        layout: &[
+            (RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1),
+            (BW, 0, 8), (BZ, 3, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5),
+            (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6),
+            (RZ, 0, 6), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b10110,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 8,
+        delta_bits: [5, 6, 5],
+        transformed: true,
+        layout: &[
+            (RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1),
+            (BW, 0, 8), (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4),
+            (GX, 0, 6), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5),
+            (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b11010,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 8,
+        delta_bits: [5, 5, 6],
+        transformed: true,
+        layout: &[
+            (RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1),
+            (BW, 0, 8), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4),
+            (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5),
+            (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b11110,
+        header_bits: 5,
+        subsets: 2,
+        endpoint_bits: 6,
+        delta_bits: [6, 6, 6],
+        transformed: false,
+        layout: &[
+            (RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6),
+            (GY, 5, 1), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1),
+            (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6),
+            (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+        ],
+    },
+    Bc6hMode {
+        header: 0b00011,
+        header_bits: 5,
+        subsets: 1,
+        endpoint_bits: 10,
+        delta_bits: [10, 10, 10],
+        transformed: false,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10),
+        ],
+    },
+    Bc6hMode {
+        header: 0b00111,
+        header_bits: 5,
+        subsets: 1,
+        endpoint_bits: 11,
+        delta_bits: [9, 9, 9],
+        transformed: true,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9),
+            (GW, 10, 1), (BX, 0, 9), (BW, 10, 1),
+        ],
+    },
+    Bc6hMode {
+        header: 0b01011,
+        header_bits: 5,
+        subsets: 1,
+        endpoint_bits: 12,
+        delta_bits: [8, 8, 8],
+        transformed: true,
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1),
+            (GX, 0, 8), (GW, 11, 1), (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+    Bc6hMode {
+        header: 0b01111,
+        header_bits: 5,
+        subsets: 1,
+        endpoint_bits: 16,
+        delta_bits: [4, 4, 4],
+        transformed: true,
+        // the high bits of the base endpoint are stored reversed
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1),
+            (RW, 13, 1), (RW, 12, 1), (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1),
+            (GW, 14, 1), (GW, 13, 1), (GW, 12, 1), (GW, 11, 1), (GW, 10, 1), (BX, 0, 4),
+            (BW, 15, 1), (BW, 14, 1), (BW, 13, 1), (BW, 12, 1), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+];
+
+fn bc6h_range(bits: u32, signed: bool) -> (i32, i32) {
+    if signed {
+        return (-(1 << (bits - 1)), (1 << (bits - 1)) - 1);
+    }
+    return (0, (1 << bits) - 1);
+}
+
+/// expands a quantized endpoint to the 16 bit interpolation range
+fn bc6h_unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if signed {
+        if bits >= 16 {
+            return value;
+        }
+        let (negative, magnitude) = (value < 0, value.abs());
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7fff
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        return if negative { -unquantized } else { unquantized };
+    }
+    if bits >= 15 {
+        return value;
+    }
+    if value == 0 {
+        return 0;
+    }
+    if value == (1 << bits) - 1 {
+        return 0xffff;
+    }
+    return ((value << 16) + 0x8000) >> bits;
+}
+
+/// scales an interpolated value to the bits of a half float
+fn bc6h_finish(value: i32, signed: bool) -> i32 {
+    if signed {
+        return if value < 0 {
+            -(((-value) * 31) >> 5)
+        } else {
+            (value * 31) >> 5
+        };
+    }
+    return (value * 31) >> 6;
+}
+
+fn bc6h_quantize(value: f32, bits: u32, signed: bool) -> i32 {
+    let (min, max) = bc6h_range(bits, signed);
+    let scale = if signed {
+        (1 << (bits - 1)) as f32 / 32768.0
+    } else {
+        (1 << bits) as f32 / 65536.0
+    };
+    let guess = (value * scale).floor() as i32;
+    let mut best = 0;
+    let mut best_error = f32::MAX;
+    for candidate in guess - 1..=guess + 1 {
+        let quantized = candidate.clamp(min, max);
+        let error = (bc6h_unquantize(quantized, bits, signed) as f32 - value).abs();
+        if error < best_error {
+            best_error = error;
+            best = quantized;
+        }
+    }
+    return best;
+}
+
+/// half float bits as an integer that orders like the value, clamped to finite values
+fn bc6h_half_integer(value: f32, signed: bool) -> i32 {
+    let value = if value.is_nan() { 0.0 } else { value };
+    let value = if signed { value } else { value.max(0.0) };
+    let bits = f16::from_f32(value).to_bits() as i32;
+    let magnitude = (bits & 0x7fff).min(0x7bff);
+    return if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    };
+}
+
+/// one subset of a BC6H block, `indices` are in the order of the subset's pixels
+struct Bc6hSubsetFit {
+    quantized: [[i32; 3]; 2],
+    indices: [u8; 16],
+    error: i64,
+}
+
+/// Nearest palette entries compared as half float integers. With `anchor` set that pixel is
+/// limited to the lower half of the palette.
+fn bc6h_assign_indices(
+    halves: &[[i32; 3]],
+    quantized: &[[i32; 3]; 2],
+    bits: u32,
+    signed: bool,
+    index_bits: u32,
+    anchor: Option<usize>,
+) -> ([u8; 16], i64) {
+    let weights = weights(index_bits);
+    let mut unquantized = [[0i32; 3]; 2];
+    for endpoint in 0..2 {
+        for channel in 0..3 {
+            unquantized[endpoint][channel] =
+                bc6h_unquantize(quantized[endpoint][channel], bits, signed);
+        }
+    }
+    let mut palette = [[0i32; 3]; 16];
+    for (entry, &weight) in palette.iter_mut().zip(weights) {
+        for channel in 0..3 {
+            let value = (unquantized[0][channel] * (64 - weight as i32)
+                + unquantized[1][channel] * weight as i32
+                + 32)
+                >> 6;
+            entry[channel] = bc6h_finish(value, signed);
+        }
+    }
+
+    let mut indices = [0u8; 16];
+    let mut total = 0;
+    for (pixel, (half, index)) in halves.iter().zip(indices.iter_mut()).enumerate() {
+        let count = if anchor == Some(pixel) {
+            weights.len() / 2
+        } else {
+            weights.len()
+        };
+        let mut best_error = i64::MAX;
+        for (candidate, entry) in palette[..count].iter().enumerate() {
+            let error: i64 = (0..3)
+                .map(|channel| ((entry[channel] - half[channel]) as i64).pow(2))
+                .sum();
+            if error < best_error {
+                best_error = error;
+                *index = candidate as u8;
+            }
+        }
+        total += best_error;
+    }
+    return (indices, total);
+}
+
+fn bc6h_encode_subset(
+    halves: &[[i32; 3]],
+    points: &[[f32; 4]],
+    mode: &Bc6hMode,
+    signed: bool,
+    index_bits: u32,
+    settings: &EffortSettings,
+) -> Bc6hSubsetFit {
+    let channels = [0, 1, 2];
+    let weights = weights(index_bits);
+    let limit = if signed { 32767.0 } else { 65535.0 };
+    let mut endpoints = fit_endpoints(points, &channels);
+    let mut best: Option<Bc6hSubsetFit> = None;
+    for iteration in 0..=settings.refine_iterations {
+        let mut quantized = [[0i32; 3]; 2];
+        for endpoint in 0..2 {
+            for channel in 0..3 {
+                let value = endpoints[endpoint][channel].clamp(-limit, limit);
+                quantized[endpoint][channel] = bc6h_quantize(value, mode.endpoint_bits, signed);
+            }
+        }
+        let (indices, error) = bc6h_assign_indices(
+            halves,
+            &quantized,
+            mode.endpoint_bits,
+            signed,
+            index_bits,
+            None,
+        );
+        if best.as_ref().is_none_or(|best| error < best.error) {
+            best = Some(Bc6hSubsetFit {
+                quantized,
+                indices,
+                error,
+            });
+        }
+        let fit = best.as_ref().unwrap();
+        if iteration == settings.refine_iterations || fit.error == 0 {
+            break;
+        }
+        let factors: Vec<f32> = fit.indices[..points.len()]
+            .iter()
+            .map(|&index| weights[index as usize] as f32 / 64.0)
+            .collect();
+        match refine_endpoints(points, &factors, &channels) {
+            Some(refined) => endpoints = refined,
+            None => break,
+        }
+    }
+    return best.unwrap();
+}
+
+struct Bc6hBlock {
+    mode: usize,
+    partition: usize,
+    /// endpoints `W`, `X`, `Y` and `Z` before the delta transform
+    endpoints: [[i32; 3]; 4],
+    indices: [u8; 16],
+    error: i64,
+}
+
+impl Bc6hBlock {
+    fn pack(&self) -> [u8; 16] {
+        let mode = &BC6H_MODES[self.mode];
+        let index_bits = if mode.subsets == 2 { 3 } else { 4 };
+        let field = |field: u8| -> u32 {
+            if field == D {
+                return self.partition as u32;
+            }
+            let endpoint = field as usize / 3;
+            let channel = field as usize % 3;
+            let value = if endpoint > 0 && mode.transformed {
+                self.endpoints[endpoint][channel] - self.endpoints[0][channel]
+            } else {
+                self.endpoints[endpoint][channel]
+            };
+            return value as u32;
+        };
+
+        let mut writer = BitWriter::new();
+        writer.write(mode.header, mode.header_bits);
+        for &(name, lsb, count) in mode.layout {
+            writer.write(field(name) >> lsb, count as u32);
+        }
+        for (pixel, &index) in self.indices.iter().enumerate() {
+            let anchor = is_anchor(mode.subsets, self.partition, pixel) as u32;
+            writer.write(index as u32, index_bits - anchor);
+        }
+        return writer.finish();
+    }
+}
+
+fn bc6h_encode_partition(
+    halves: &[[i32; 3]; 16],
+    points: &[[f32; 4]; 16],
+    mode_index: usize,
+    partition: usize,
+    signed: bool,
+    settings: &EffortSettings,
+) -> Bc6hBlock {
+    let mode = &BC6H_MODES[mode_index];
+    let index_bits = if mode.subsets == 2 { 3 } else { 4 };
+    let members: Vec<Vec<usize>> = (0..mode.subsets)
+        .map(|subset| {
+            (0..16)
+                .filter(|&pixel| subset_of(mode.subsets, partition, pixel) == subset)
+                .collect()
+        })
+        .collect();
+
+    let mut block = Bc6hBlock {
+        mode: mode_index,
+        partition,
+        endpoints: [[0; 3]; 4],
+        indices: [0; 16],
+        error: 0,
+    };
+    for (subset, pixels) in members.iter().enumerate() {
+        let subset_halves: Vec<[i32; 3]> = pixels.iter().map(|&pixel| halves[pixel]).collect();
+        let subset_points: Vec<[f32; 4]> = pixels.iter().map(|&pixel| points[pixel]).collect();
+        let mut fit = bc6h_encode_subset(
+            &subset_halves,
+            &subset_points,
+            mode,
+            signed,
+            index_bits,
+            settings,
+        );
+        let anchor = anchor_of(mode.subsets, partition, subset);
+        let local_anchor = pixels.iter().position(|&pixel| pixel == anchor).unwrap();
+        let max = (1u8 << index_bits) - 1;
+        if fit.indices[local_anchor] > max / 2 {
+            fit.quantized.swap(0, 1);
+            for index in &mut fit.indices[..pixels.len()] {
+                *index = max - *index;
+            }
+        }
+        block.endpoints[subset * 2] = fit.quantized[0];
+        block.endpoints[subset * 2 + 1] = fit.quantized[1];
+        for (local, &pixel) in pixels.iter().enumerate() {
+            block.indices[pixel] = fit.indices[local];
+        }
+        block.error += fit.error;
+    }
+
+    if !mode.transformed {
+        return block;
+    }
+    // pull endpoints whose delta to the base doesn't fit towards the base
+    let mut clamped = false;
+    for endpoint in 1..mode.subsets * 2 {
+        for channel in 0..3 {
+            let (min, max) = bc6h_range(mode.delta_bits[channel], true);
+            let base = block.endpoints[0][channel];
+            let delta = block.endpoints[endpoint][channel] - base;
+            if delta < min || delta > max {
+                block.endpoints[endpoint][channel] = base + delta.clamp(min, max);
+                clamped = true;
+            }
+        }
+    }
+    if clamped {
+        block.error = 0;
+        for (subset, pixels) in members.iter().enumerate() {
+            let subset_halves: Vec<[i32; 3]> = pixels.iter().map(|&pixel| halves[pixel]).collect();
+            let anchor = anchor_of(mode.subsets, partition, subset);
+            let local_anchor = pixels.iter().position(|&pixel| pixel == anchor);
+            let quantized = [block.endpoints[subset * 2], block.endpoints[subset * 2 + 1]];
+            let (indices, error) = bc6h_assign_indices(
+                &subset_halves,
+                &quantized,
+                mode.endpoint_bits,
+                signed,
+                index_bits,
+                local_anchor,
+            );
+            for (local, &pixel) in pixels.iter().enumerate() {
+                block.indices[pixel] = indices[local];
+            }
+            block.error += error;
+        }
+    }
+    return block;
+}
+
+/// Encodes one 4x4 block of rgb pixels in row order to BC6H, `signed` selects `DXBC6H_SFLOAT`.
+pub fn encode_bc6h_block(
+    pixels: &[[f32; 3]; 16],
+    signed: bool,
+    effort: CompressionEffort,
+) -> [u8; 16] {
+    let settings = effort_settings(effort);
+    let halves = pixels.map(|pixel| pixel.map(|channel| bc6h_half_integer(channel, signed)));
+    // endpoints are fitted in the 16 bit range the interpolation works in
+    let scale = if signed { 32.0 / 31.0 } else { 64.0 / 31.0 };
+    let points = halves.map(|half| {
+        [
+            half[0] as f32 * scale,
+            half[1] as f32 * scale,
+            half[2] as f32 * scale,
+            0.0,
+        ]
+    });
+
+    let mut best: Option<Bc6hBlock> = None;
+    for &mode_index in settings.bc6h_modes {
+        let mode = &BC6H_MODES[mode_index];
+        let partitions: Vec<usize> = if mode.subsets == 2 {
+            let mut ranked: Vec<(usize, f32)> = (0..32)
+                .map(|partition| {
+                    let error = (0..2)
+                        .map(|subset| {
+                            let members: Vec<[f32; 4]> = (0..16)
+                                .filter(|&pixel| subset_of(2, partition, pixel) == subset)
+                                .map(|pixel| points[pixel])
+                                .collect();
+                            line_fit_error(&members, &[0, 1, 2])
+                        })
+                        .sum();
+                    (partition, error)
+                })
+                .collect();
+            ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+            ranked
+                .iter()
+                .take(settings.partitions)
+                .map(|(partition, _)| *partition)
+                .collect()
+        } else {
+            vec![0]
+        };
+
+        for partition in partitions {
+            let candidate =
+                bc6h_encode_partition(&halves, &points, mode_index, partition, signed, &settings);
+            if best
+                .as_ref()
+                .is_none_or(|best| candidate.error < best.error)
+            {
+                best = Some(candidate);
+            }
+        }
+    }
+    return best.unwrap().pack();
+}
@@ -0,0 +1,307 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::{max_mip_levels, Image},
+    ImageFormat,
+};
+use std::f32::consts::PI;
+
+/// Cube faces in array layer order, this is the order vulkan selects faces in when sampling a
+/// texture created with `DESCRIPTOR_TYPE_TEXTURE_CUBE`.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CubeFace {
+    PositiveX = 0,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// Unnormalized direction through the face coordinate `u`, `v` in [0, 1].
+    pub fn direction(&self, u: f32, v: f32) -> [f32; 3] {
+        let sc = u * 2.0 - 1.0;
+        let tc = v * 2.0 - 1.0;
+        match self {
+            CubeFace::PositiveX => [1.0, -tc, -sc],
+            CubeFace::NegativeX => [-1.0, -tc, sc],
+            CubeFace::PositiveY => [sc, 1.0, tc],
+            CubeFace::NegativeY => [sc, -1.0, -tc],
+            CubeFace::PositiveZ => [sc, -tc, 1.0],
+            CubeFace::NegativeZ => [-sc, -tc, -1.0],
+        }
+    }
+
+    /// Face and face coordinate hit by `direction`, following the vulkan face selection rules.
+    pub fn from_direction(direction: [f32; 3]) -> (CubeFace, f32, f32) {
+        let [x, y, z] = direction;
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (CubeFace::PositiveX, -z, -y, ax)
+            } else {
+                (CubeFace::NegativeX, z, -y, ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (CubeFace::PositiveY, x, z, ay)
+            } else {
+                (CubeFace::NegativeY, x, -z, ay)
+            }
+        } else if z > 0.0 {
+            (CubeFace::PositiveZ, x, -y, az)
+        } else {
+            (CubeFace::NegativeZ, -x, -y, az)
+        };
+        return (face, 0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0));
+    }
+}
+
+/// How a set of directions is laid out in an image.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Projection {
+    /// Latitude/longitude panorama, +Y is the top row and the center column looks down -Z.
+    Equirectangular,
+    /// Six square array layers in `CubeFace` order.
+    Cubemap,
+    /// Octahedral map of the full sphere, +Z is the center and -Z the corners.
+    Octahedral,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum FilterType {
+    Nearest,
+    Linear,
+}
+
+pub struct ProjectionDesc {
+    pub projection: Projection,
+    /// Width of the result, for cubemaps this is the size of a single face.
+    pub width: u32,
+    /// Height of the result, cubemaps and octahedral maps are square and ignore it.
+    pub height: u32,
+    pub format: ImageFormat,
+    pub filter: FilterType,
+    /// Samples taken along each axis of a destination texel, 1 takes a single sample at the
+    /// texel center. Raise it when the result is much smaller than the source to avoid aliasing.
+    pub samples: u32,
+    /// Mip levels of the result, lower levels are box filtered per face/layer.
+    pub mip_levels: u32,
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 1.0];
+    }
+    return [v[0] / length, v[1] / length, v[2] / length];
+}
+
+fn sign(value: f32) -> f32 {
+    if value >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn equirectangular_to_direction(u: f32, v: f32) -> [f32; 3] {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    return [
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    ];
+}
+
+fn direction_to_equirectangular(direction: [f32; 3]) -> (f32, f32) {
+    let [x, y, z] = normalize(direction);
+    let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+    let v = y.clamp(-1.0, 1.0).acos() / PI;
+    return (u, v);
+}
+
+fn octahedral_to_direction(u: f32, v: f32) -> [f32; 3] {
+    let fx = u * 2.0 - 1.0;
+    let fy = v * 2.0 - 1.0;
+    let z = 1.0 - fx.abs() - fy.abs();
+    let t = (-z).max(0.0);
+    return normalize([fx - t * sign(fx), fy - t * sign(fy), z]);
+}
+
+fn direction_to_octahedral(direction: [f32; 3]) -> (f32, f32) {
+    let [x, y, z] = direction;
+    let l1 = x.abs() + y.abs() + z.abs();
+    if l1 == 0.0 {
+        return (0.5, 0.5);
+    }
+    let (mut px, mut py) = (x / l1, y / l1);
+    if z < 0.0 {
+        let (ox, oy) = (px, py);
+        px = (1.0 - oy.abs()) * sign(ox);
+        py = (1.0 - ox.abs()) * sign(oy);
+    }
+    return (px * 0.5 + 0.5, py * 0.5 + 0.5);
+}
+
+fn check_source(image: &Image, projection: Projection) -> ImageResult<()> {
+    if !Image::supports_texel_access(image.format) {
+        return Err(ImageError::UnsupportedFormat(image.format));
+    }
+    let valid = match projection {
+        Projection::Cubemap => image.array_size == 6 && image.width == image.height,
+        Projection::Equirectangular | Projection::Octahedral => image.array_size >= 1,
+    };
+    if !valid || image.depth != 1 {
+        return Err(ImageError::InvalidDimensions);
+    }
+    return Ok(());
+}
+
+fn sample_layer(
+    image: &Image,
+    mip: u32,
+    layer: u32,
+    u: f32,
+    v: f32,
+    filter: FilterType,
+    wrap_u: bool,
+) -> [f32; 4] {
+    let (width, height, _) = image.mip_extent(mip);
+    let resolve_x = |x: i64| -> u32 {
+        if wrap_u {
+            x.rem_euclid(width as i64) as u32
+        } else {
+            x.clamp(0, width as i64 - 1) as u32
+        }
+    };
+    let resolve_y = |y: i64| -> u32 { y.clamp(0, height as i64 - 1) as u32 };
+
+    match filter {
+        FilterType::Nearest => {
+            let x = (u * width as f32).floor() as i64;
+            let y = (v * height as f32).floor() as i64;
+            return image.read_texel(mip, layer, resolve_x(x), resolve_y(y), 0);
+        }
+        FilterType::Linear => {
+            let fx = u * width as f32 - 0.5;
+            let fy = v * height as f32 - 0.5;
+            let (x0, y0) = (fx.floor(), fy.floor());
+            let (tx, ty) = (fx - x0, fy - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let t00 = image.read_texel(mip, layer, resolve_x(x0), resolve_y(y0), 0);
+            let t10 = image.read_texel(mip, layer, resolve_x(x0 + 1), resolve_y(y0), 0);
+            let t01 = image.read_texel(mip, layer, resolve_x(x0), resolve_y(y0 + 1), 0);
+            let t11 = image.read_texel(mip, layer, resolve_x(x0 + 1), resolve_y(y0 + 1), 0);
+            let mut result = [0.0; 4];
+            for channel in 0..4 {
+                let top = t00[channel] + (t10[channel] - t00[channel]) * tx;
+                let bottom = t01[channel] + (t11[channel] - t01[channel]) * tx;
+                result[channel] = top + (bottom - top) * ty;
+            }
+            return result;
+        }
+    }
+}
+
+/// Samples the texel seen along `direction` from an image laid out with `projection`.
+///
+/// Cubemap lookups clamp at the edge of the selected face, equirectangular lookups wrap
+/// horizontally.
+pub fn sample_direction(
+    image: &Image,
+    projection: Projection,
+    mip: u32,
+    direction: [f32; 3],
+    filter: FilterType,
+) -> [f32; 4] {
+    match projection {
+        Projection::Equirectangular => {
+            let (u, v) = direction_to_equirectangular(direction);
+            return sample_layer(image, mip, 0, u, v, filter, true);
+        }
+        Projection::Cubemap => {
+            let (face, u, v) = CubeFace::from_direction(direction);
+            return sample_layer(image, mip, face as u32, u, v, filter, false);
+        }
+        Projection::Octahedral => {
+            let (u, v) = direction_to_octahedral(direction);
+            return sample_layer(image, mip, 0, u, v, filter, false);
+        }
+    }
+}
+
+/// Resamples `source`, laid out with the `from` projection, into a new image described by
+/// `desc`. Cubemap results have six array layers in `CubeFace` order.
+pub fn convert_projection(
+    source: &Image,
+    from: Projection,
+    desc: &ProjectionDesc,
+) -> ImageResult<Image> {
+    check_source(source, from)?;
+    if !Image::supports_texel_access(desc.format) {
+        return Err(ImageError::UnsupportedFormat(desc.format));
+    }
+    let (width, height, layers) = match desc.projection {
+        Projection::Equirectangular => (desc.width, desc.height, 1),
+        Projection::Cubemap => (desc.width, desc.width, 6),
+        Projection::Octahedral => (desc.width, desc.width, 1),
+    };
+    if width == 0
+        || height == 0
+        || desc.samples == 0
+        || desc.mip_levels == 0
+        || desc.mip_levels > max_mip_levels(width, height, 1)
+    {
+        return Err(ImageError::InvalidDimensions);
+    }
+
+    let mut result = Image::new(
+        ImageFormat::R32G32B32A32_SFLOAT,
+        width,
+        height,
+        1,
+        layers,
+        1,
+    );
+    let samples = desc.samples;
+    let weight = 1.0 / (samples * samples) as f32;
+    for layer in 0..layers {
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0f32; 4];
+                for sy in 0..samples {
+                    for sx in 0..samples {
+                        let u = (x as f32 + (sx as f32 + 0.5) / samples as f32) / width as f32;
+                        let v = (y as f32 + (sy as f32 + 0.5) / samples as f32) / height as f32;
+                        let direction = match desc.projection {
+                            Projection::Equirectangular => equirectangular_to_direction(u, v),
+                            Projection::Cubemap => CubeFace::ALL[layer as usize].direction(u, v),
+                            Projection::Octahedral => octahedral_to_direction(u, v),
+                        };
+                        let texel = sample_direction(source, from, 0, direction, desc.filter);
+                        for channel in 0..4 {
+                            sum[channel] += texel[channel] * weight;
+                        }
+                    }
+                }
+                result.write_texel(0, layer, x, y, 0, sum);
+            }
+        }
+    }
+
+    if desc.mip_levels > 1 {
+        result = result.generate_mips(desc.mip_levels)?;
+    }
+    return result.convert(desc.format);
+}
@@ -0,0 +1,17 @@
+use crate::ImageFormat;
+
+#[derive(Debug)]
+pub enum ImageError {
+    /// the operation can't read or write texels of this format
+    UnsupportedFormat(ImageFormat),
+    /// the image doesn't have the shape the operation expects (e.g. a cubemap without 6 layers)
+    InvalidDimensions,
+    /// the encoded data is truncated or doesn't follow the container format
+    InvalidData(&'static str),
+    /// the encoded data is valid but uses a feature the loader doesn't implement
+    UnsupportedFeature(&'static str),
+    /// the images don't fit into the atlas
+    AtlasFull,
+}
+
+pub type ImageResult<T> = Result<T, ImageError>;
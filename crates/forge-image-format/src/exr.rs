@@ -0,0 +1,552 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::{max_mip_levels, Image},
+    ImageFormat,
+};
+use half::f16;
+
+const EXR_MAGIC: u32 = 20000630;
+const EXR_TILED_FLAG: u32 = 0x200;
+const EXR_NON_IMAGE_FLAG: u32 = 0x800;
+const EXR_MULTI_PART_FLAG: u32 = 0x1000;
+
+#[derive(PartialEq, Copy, Clone)]
+enum PixelType {
+    UInt,
+    Half,
+    Float,
+}
+
+impl PixelType {
+    fn size(&self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::UInt | PixelType::Float => 4,
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum Compression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+}
+
+impl Compression {
+    fn lines_per_chunk(&self) -> u32 {
+        match self {
+            Compression::None | Compression::Rle | Compression::Zips => 1,
+            Compression::Zip => 16,
+        }
+    }
+}
+
+struct Channel {
+    name: String,
+    pixel_type: PixelType,
+    /// rgba channel the samples are written to, `None` for channels that are skipped
+    target: Option<usize>,
+}
+
+struct Tiles {
+    width: u32,
+    height: u32,
+    mip_mapped: bool,
+}
+
+struct Header {
+    channels: Vec<Channel>,
+    compression: Compression,
+    y_min: i32,
+    width: u32,
+    height: u32,
+    tiles: Option<Tiles>,
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, size: usize) -> ImageResult<&'a [u8]> {
+        let result = self
+            .cursor
+            .checked_add(size)
+            .and_then(|end| self.bytes.get(self.cursor..end))
+            .ok_or(ImageError::InvalidData("exr data is truncated"))?;
+        self.cursor += size;
+        return Ok(result);
+    }
+
+    fn u8(&mut self) -> ImageResult<u8> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u32(&mut self) -> ImageResult<u32> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn i32(&mut self) -> ImageResult<i32> {
+        return Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> ImageResult<u64> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    fn string(&mut self) -> ImageResult<&'a str> {
+        let rest = &self.bytes[self.cursor.min(self.bytes.len())..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(ImageError::InvalidData("exr string is not terminated"))?;
+        self.cursor += length + 1;
+        return std::str::from_utf8(&rest[..length])
+            .map_err(|_| ImageError::InvalidData("exr string isn't valid utf-8"));
+    }
+}
+
+/// maps a channel name like `R` or `diffuse.R` to the rgba channel it fills
+fn channel_target(name: &str) -> Option<usize> {
+    match name.rsplit('.').next().unwrap_or(name) {
+        "R" | "r" | "Y" => Some(0),
+        "G" | "g" => Some(1),
+        "B" | "b" => Some(2),
+        "A" | "a" => Some(3),
+        _ => None,
+    }
+}
+
+fn read_channels(reader: &mut ByteReader) -> ImageResult<Vec<Channel>> {
+    let mut channels = vec![];
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let pixel_type = match reader.i32()? {
+            0 => PixelType::UInt,
+            1 => PixelType::Half,
+            2 => PixelType::Float,
+            _ => return Err(ImageError::InvalidData("unknown exr pixel type")),
+        };
+        reader.take(4)?; // pLinear and reserved
+        let x_sampling = reader.i32()?;
+        let y_sampling = reader.i32()?;
+        if x_sampling != 1 || y_sampling != 1 {
+            return Err(ImageError::UnsupportedFeature("exr channel subsampling"));
+        }
+        channels.push(Channel {
+            name: name.to_string(),
+            pixel_type,
+            target: channel_target(name),
+        });
+    }
+
+    // when several layers fill the same channel keep the default layer, or the first one
+    for target in 0..4 {
+        let candidates: Vec<usize> = (0..channels.len())
+            .filter(|index| channels[*index].target == Some(target))
+            .collect();
+        let keep = candidates
+            .iter()
+            .find(|index| !channels[**index].name.contains('.'))
+            .or(candidates.first())
+            .copied();
+        for index in candidates {
+            if Some(index) != keep {
+                channels[index].target = None;
+            }
+        }
+    }
+    return Ok(channels);
+}
+
+fn read_header(reader: &mut ByteReader) -> ImageResult<Header> {
+    if reader.u32()? != EXR_MAGIC {
+        return Err(ImageError::InvalidData("missing exr signature"));
+    }
+    let version = reader.u32()?;
+    if version & EXR_MULTI_PART_FLAG != 0 {
+        return Err(ImageError::UnsupportedFeature("multi-part exr"));
+    }
+    if version & EXR_NON_IMAGE_FLAG != 0 {
+        return Err(ImageError::UnsupportedFeature("deep exr"));
+    }
+    let tiled = version & EXR_TILED_FLAG != 0;
+
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+    let mut tiles = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _attribute_type = reader.string()?;
+        let size = reader.i32()?;
+        if size < 0 {
+            return Err(ImageError::InvalidData("negative exr attribute size"));
+        }
+        let mut value = ByteReader {
+            bytes: reader.take(size as usize)?,
+            cursor: 0,
+        };
+        match name {
+            "channels" => channels = Some(read_channels(&mut value)?),
+            "compression" => {
+                compression = Some(match value.u8()? {
+                    0 => Compression::None,
+                    1 => Compression::Rle,
+                    2 => Compression::Zips,
+                    3 => Compression::Zip,
+                    4 => return Err(ImageError::UnsupportedFeature("exr piz compression")),
+                    5 => return Err(ImageError::UnsupportedFeature("exr pxr24 compression")),
+                    6 | 7 => return Err(ImageError::UnsupportedFeature("exr b44 compression")),
+                    8 | 9 => return Err(ImageError::UnsupportedFeature("exr dwa compression")),
+                    _ => return Err(ImageError::InvalidData("unknown exr compression")),
+                })
+            }
+            "dataWindow" => {
+                data_window = Some((value.i32()?, value.i32()?, value.i32()?, value.i32()?))
+            }
+            "tiles" => {
+                let width = value.u32()?;
+                let height = value.u32()?;
+                let mode = value.u8()?;
+                let mip_mapped = match mode & 0xf {
+                    0 => false,
+                    // levels rounded up don't line up with the mip chain of an image
+                    1 if mode >> 4 == 0 => true,
+                    1 => false,
+                    2 => return Err(ImageError::UnsupportedFeature("exr ripmap levels")),
+                    _ => return Err(ImageError::InvalidData("unknown exr level mode")),
+                };
+                tiles = Some(Tiles {
+                    width,
+                    height,
+                    mip_mapped,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    let channels = channels.ok_or(ImageError::InvalidData("exr header has no channels"))?;
+    let compression =
+        compression.ok_or(ImageError::InvalidData("exr header has no compression"))?;
+    let (x_min, y_min, x_max, y_max) =
+        data_window.ok_or(ImageError::InvalidData("exr header has no data window"))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(ImageError::InvalidDimensions);
+    }
+    if tiled
+        && tiles
+            .as_ref()
+            .is_none_or(|tiles| tiles.width == 0 || tiles.height == 0)
+    {
+        return Err(ImageError::InvalidData(
+            "tiled exr without a tile description",
+        ));
+    }
+    return Ok(Header {
+        channels,
+        compression,
+        y_min,
+        width: u32::try_from(x_max as i64 - x_min as i64 + 1)
+            .map_err(|_| ImageError::InvalidDimensions)?,
+        height: u32::try_from(y_max as i64 - y_min as i64 + 1)
+            .map_err(|_| ImageError::InvalidDimensions)?,
+        tiles: if tiled { tiles } else { None },
+    });
+}
+
+/// undoes the byte delta predictor and the split of even and odd bytes used by rle and zip
+fn reconstruct(data: &mut [u8]) -> Vec<u8> {
+    for index in 1..data.len() {
+        data[index] = data[index - 1].wrapping_add(data[index]).wrapping_sub(128);
+    }
+    let half = data.len().div_ceil(2);
+    let mut result = Vec::with_capacity(data.len());
+    for index in 0..half {
+        result.push(data[index]);
+        if half + index < data.len() {
+            result.push(data[half + index]);
+        }
+    }
+    return result;
+}
+
+fn decompress_rle(data: &[u8], expected: usize) -> ImageResult<Vec<u8>> {
+    let mut result = Vec::with_capacity(expected);
+    let mut cursor = 0;
+    while cursor < data.len() {
+        let count = data[cursor] as i8;
+        cursor += 1;
+        if count < 0 {
+            let count = -(count as i32) as usize;
+            let literal = data
+                .get(cursor..cursor + count)
+                .ok_or(ImageError::InvalidData("exr rle data is truncated"))?;
+            result.extend_from_slice(literal);
+            cursor += count;
+        } else {
+            let value = *data
+                .get(cursor)
+                .ok_or(ImageError::InvalidData("exr rle data is truncated"))?;
+            result.extend(std::iter::repeat_n(value, count as usize + 1));
+            cursor += 1;
+        }
+    }
+    return Ok(result);
+}
+
+fn decompress(compression: Compression, data: &[u8], expected: usize) -> ImageResult<Vec<u8>> {
+    if compression == Compression::None && data.len() != expected {
+        return Err(ImageError::InvalidData("exr chunk has the wrong size"));
+    }
+    // chunks that don't get smaller when compressed are stored as is
+    if compression == Compression::None || data.len() == expected {
+        return Ok(data.to_vec());
+    }
+    let mut result = match compression {
+        Compression::Rle => decompress_rle(data, expected)?,
+        Compression::Zips | Compression::Zip => {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, expected)
+                .map_err(|_| ImageError::InvalidData("exr zip data is corrupt"))?
+        }
+        Compression::None => unreachable!(),
+    };
+    if result.len() != expected {
+        return Err(ImageError::InvalidData("exr chunk has the wrong size"));
+    }
+    return Ok(reconstruct(&mut result));
+}
+
+fn read_sample(pixel_type: PixelType, bytes: &[u8]) -> f32 {
+    match pixel_type {
+        PixelType::UInt => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f32,
+        PixelType::Half => f16::from_le_bytes([bytes[0], bytes[1]]).to_f32(),
+        PixelType::Float => f32::from_le_bytes(bytes[..4].try_into().unwrap()),
+    }
+}
+
+/// Writes a block of `lines` rows of `width` pixels starting at `x`, `y` of `mip`. Inside a
+/// block every row stores all samples of the first channel, then the next channel and so on.
+#[allow(clippy::too_many_arguments)]
+fn write_block(
+    image: &mut Image,
+    header: &Header,
+    mip: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    lines: u32,
+    data: &[u8],
+) {
+    let mut cursor = 0;
+    let luminance = header
+        .channels
+        .iter()
+        .any(|channel| channel.name.rsplit('.').next() == Some("Y"))
+        && !header
+            .channels
+            .iter()
+            .any(|channel| channel.target == Some(1));
+    for line in 0..lines {
+        let mut row = vec![[0.0f32, 0.0, 0.0, 1.0]; width as usize];
+        for channel in &header.channels {
+            let size = channel.pixel_type.size();
+            if let Some(target) = channel.target {
+                for (column, texel) in row.iter_mut().enumerate() {
+                    texel[target] =
+                        read_sample(channel.pixel_type, &data[cursor + column * size..]);
+                }
+            }
+            cursor += width as usize * size;
+        }
+        for (column, texel) in row.iter_mut().enumerate() {
+            if luminance {
+                texel[1] = texel[0];
+                texel[2] = texel[0];
+            }
+            image.write_texel(mip, 0, x + column as u32, y + line, 0, *texel);
+        }
+    }
+}
+
+fn read_offsets(reader: &mut ByteReader, count: usize) -> ImageResult<Vec<u64>> {
+    return (0..count).map(|_| reader.u64()).collect();
+}
+
+fn chunk(bytes: &[u8], offset: u64) -> ImageResult<ByteReader<'_>> {
+    let cursor = usize::try_from(offset)
+        .map_err(|_| ImageError::InvalidData("exr chunk offset is too large"))?;
+    return Ok(ByteReader { bytes, cursor });
+}
+
+/// Smallest number of bytes that can hold the offset table and every chunk of the image, so a
+/// corrupt header is rejected before the image is allocated.
+fn min_chunk_bytes(header: &Header, mip_levels: u32) -> Option<u64> {
+    let (chunk_count, chunk_header_size) = match &header.tiles {
+        Some(tiles) => {
+            let mut count = 0u64;
+            for mip in 0..mip_levels {
+                let tiles_x = (header.width >> mip).max(1).div_ceil(tiles.width) as u64;
+                let tiles_y = (header.height >> mip).max(1).div_ceil(tiles.height) as u64;
+                count = count.checked_add(tiles_x.checked_mul(tiles_y)?)?;
+            }
+            // tile coordinates, level and data size
+            (count, 20)
+        }
+        // y and data size
+        None => {
+            let lines_per_chunk = header.compression.lines_per_chunk();
+            (header.height.div_ceil(lines_per_chunk) as u64, 8)
+        }
+    };
+    let bytes_per_pixel: u64 = header
+        .channels
+        .iter()
+        .map(|channel| channel.pixel_type.size() as u64)
+        .sum();
+    let pixel_bytes = (header.width as u64)
+        .checked_mul(header.height as u64)?
+        .checked_mul(bytes_per_pixel.max(1))?;
+    // rle stores at most 128 bytes in 2, deflate doesn't get past about 1032:1
+    let max_ratio = match header.compression {
+        Compression::None => 1,
+        Compression::Rle => 64,
+        Compression::Zips | Compression::Zip => 1032,
+    };
+    return chunk_count
+        .checked_mul(8 + chunk_header_size)?
+        .checked_add(pixel_bytes / max_ratio);
+}
+
+fn load_scanlines(
+    bytes: &[u8],
+    reader: &mut ByteReader,
+    header: &Header,
+    image: &mut Image,
+) -> ImageResult<()> {
+    let lines_per_chunk = header.compression.lines_per_chunk();
+    let chunk_count = header.height.div_ceil(lines_per_chunk);
+    let bytes_per_line: usize = header
+        .channels
+        .iter()
+        .map(|channel| channel.pixel_type.size() * header.width as usize)
+        .sum();
+
+    for offset in read_offsets(reader, chunk_count as usize)? {
+        let mut chunk = chunk(bytes, offset)?;
+        let y = chunk.i32()? as i64 - header.y_min as i64;
+        if y < 0 || y >= header.height as i64 || y % lines_per_chunk as i64 != 0 {
+            return Err(ImageError::InvalidData(
+                "exr chunk outside of the data window",
+            ));
+        }
+        let y = y as u32;
+        let lines = lines_per_chunk.min(header.height - y);
+        let size = chunk.i32()?;
+        let data = chunk.take(size.max(0) as usize)?;
+        let data = decompress(header.compression, data, bytes_per_line * lines as usize)?;
+        write_block(image, header, 0, 0, y, header.width, lines, &data);
+    }
+    return Ok(());
+}
+
+fn load_tiles(
+    bytes: &[u8],
+    reader: &mut ByteReader,
+    header: &Header,
+    tiles: &Tiles,
+    image: &mut Image,
+) -> ImageResult<()> {
+    let tile_counts: Vec<(u32, u32)> = (0..image.mip_levels)
+        .map(|mip| {
+            let (width, height, _) = image.mip_extent(mip);
+            (width.div_ceil(tiles.width), height.div_ceil(tiles.height))
+        })
+        .collect();
+    let offset_count = tile_counts
+        .iter()
+        .map(|(x, y)| *x as usize * *y as usize)
+        .sum();
+
+    for offset in read_offsets(reader, offset_count)? {
+        let mut chunk = chunk(bytes, offset)?;
+        let tile_x = chunk.i32()?;
+        let tile_y = chunk.i32()?;
+        let level_x = chunk.i32()?;
+        let level_y = chunk.i32()?;
+        if level_x != level_y || level_x < 0 || level_x as u32 >= image.mip_levels {
+            return Err(ImageError::InvalidData("exr tile has an invalid level"));
+        }
+        let mip = level_x as u32;
+        let (count_x, count_y) = tile_counts[mip as usize];
+        if tile_x < 0 || tile_y < 0 || tile_x as u32 >= count_x || tile_y as u32 >= count_y {
+            return Err(ImageError::InvalidData(
+                "exr tile outside of the data window",
+            ));
+        }
+
+        let (width, height, _) = image.mip_extent(mip);
+        let x = tile_x as u32 * tiles.width;
+        let y = tile_y as u32 * tiles.height;
+        let tile_width = tiles.width.min(width - x);
+        let tile_height = tiles.height.min(height - y);
+        let bytes_per_line: usize = header
+            .channels
+            .iter()
+            .map(|channel| channel.pixel_type.size() * tile_width as usize)
+            .sum();
+        let size = chunk.i32()?;
+        let data = chunk.take(size.max(0) as usize)?;
+        let data = decompress(
+            header.compression,
+            data,
+            bytes_per_line * tile_height as usize,
+        )?;
+        write_block(image, header, mip, x, y, tile_width, tile_height, &data);
+    }
+    return Ok(());
+}
+
+/// Loads a single part scanline or tiled OpenEXR file with half, float or uint channels.
+///
+/// `format` has to be `R16G16B16A16_SFLOAT` or `R32G32B32A32_SFLOAT`. The data window becomes
+/// the image, `R`, `G`, `B` and `A` (or a luminance only `Y`) fill the rgba channels and a
+/// missing alpha reads as 1. Tiled files with rounded down mip levels keep their mip chain.
+/// Uncompressed, RLE, ZIPS and ZIP compression are supported.
+pub fn load_exr(bytes: &[u8], format: ImageFormat) -> ImageResult<Image> {
+    if format != ImageFormat::R16G16B16A16_SFLOAT && format != ImageFormat::R32G32B32A32_SFLOAT {
+        return Err(ImageError::UnsupportedFormat(format));
+    }
+
+    let mut reader = ByteReader { bytes, cursor: 0 };
+    let mut header = read_header(&mut reader)?;
+    // the file stores channels sorted by name, which is also the order inside every chunk
+    header
+        .channels
+        .sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+
+    let mip_levels = match &header.tiles {
+        Some(tiles) if tiles.mip_mapped => max_mip_levels(header.width, header.height, 1),
+        _ => 1,
+    };
+    let min_size = min_chunk_bytes(&header, mip_levels).ok_or(ImageError::InvalidDimensions)?;
+    if min_size > bytes.len().saturating_sub(reader.cursor) as u64 {
+        return Err(ImageError::InvalidData("exr data is truncated"));
+    }
+    let mut image = Image::new(format, header.width, header.height, 1, 1, mip_levels);
+    match &header.tiles {
+        Some(tiles) => load_tiles(bytes, &mut reader, &header, tiles, &mut image)?,
+        None => load_scanlines(bytes, &mut reader, &header, &mut image)?,
+    }
+    return Ok(image);
+}
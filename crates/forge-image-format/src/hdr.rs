@@ -0,0 +1,201 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    ImageFormat,
+};
+
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+
+fn rgbe_to_float(rgbe: [u8; 4]) -> [f32; 4] {
+    if rgbe[3] == 0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let scale = 2.0f32.powi(rgbe[3] as i32 - (128 + 8));
+    return [
+        rgbe[0] as f32 * scale,
+        rgbe[1] as f32 * scale,
+        rgbe[2] as f32 * scale,
+        1.0,
+    ];
+}
+
+fn read_line<'a>(bytes: &'a [u8], cursor: &mut usize) -> ImageResult<&'a str> {
+    let start = *cursor;
+    let end = bytes[start..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map(|position| start + position)
+        .ok_or(ImageError::InvalidData("hdr header is truncated"))?;
+    *cursor = end + 1;
+    return std::str::from_utf8(&bytes[start..end])
+        .map(|line| line.trim_end_matches('\r'))
+        .map_err(|_| ImageError::InvalidData("hdr header isn't valid text"));
+}
+
+/// flat pixels, possibly using the original radiance run length encoding
+fn read_flat_scanline(
+    bytes: &[u8],
+    cursor: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> ImageResult<()> {
+    let mut x = 0;
+    let mut shift = 0;
+    while x < scanline.len() {
+        let pixel: [u8; 4] = bytes
+            .get(*cursor..*cursor + 4)
+            .ok_or(ImageError::InvalidData("hdr pixel data is truncated"))?
+            .try_into()
+            .unwrap();
+        *cursor += 4;
+        if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+            if x == 0 {
+                return Err(ImageError::InvalidData("hdr run without a previous pixel"));
+            }
+            // consecutive markers extend the run by the next 8 bits of its length
+            if pixel[3] == 0 || shift > 24 {
+                return Err(ImageError::InvalidData("malformed hdr run"));
+            }
+            let count = (pixel[3] as usize) << shift;
+            if x + count > scanline.len() {
+                return Err(ImageError::InvalidData("hdr run overflows the scanline"));
+            }
+            let previous = scanline[x - 1];
+            scanline[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+    }
+    return Ok(());
+}
+
+/// adaptive run length encoding where each of the four components is stored separately
+fn read_rle_scanline(
+    bytes: &[u8],
+    cursor: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> ImageResult<()> {
+    let header = bytes
+        .get(*cursor..*cursor + 4)
+        .ok_or(ImageError::InvalidData("hdr pixel data is truncated"))?;
+    if ((header[2] as usize) << 8 | header[3] as usize) != scanline.len() {
+        return Err(ImageError::InvalidData("hdr scanline width mismatch"));
+    }
+    *cursor += 4;
+
+    for component in 0..4 {
+        let mut x = 0;
+        while x < scanline.len() {
+            let count = *bytes
+                .get(*cursor)
+                .ok_or(ImageError::InvalidData("hdr pixel data is truncated"))?
+                as usize;
+            *cursor += 1;
+            if count > 128 {
+                let count = count - 128;
+                let value = *bytes
+                    .get(*cursor)
+                    .ok_or(ImageError::InvalidData("hdr pixel data is truncated"))?;
+                *cursor += 1;
+                if count == 0 || x + count > scanline.len() {
+                    return Err(ImageError::InvalidData("hdr run overflows the scanline"));
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[component] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > scanline.len() {
+                    return Err(ImageError::InvalidData("hdr run overflows the scanline"));
+                }
+                let values = bytes
+                    .get(*cursor..*cursor + count)
+                    .ok_or(ImageError::InvalidData("hdr pixel data is truncated"))?;
+                *cursor += count;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[component] = *value;
+                }
+                x += count;
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Loads a Radiance `.hdr` (RGBE) file into a single mip, single layer image.
+///
+/// `format` has to be `R16G16B16A16_SFLOAT` or `R32G32B32A32_SFLOAT`, alpha is always 1.
+pub fn load_hdr(bytes: &[u8], format: ImageFormat) -> ImageResult<Image> {
+    if format != ImageFormat::R16G16B16A16_SFLOAT && format != ImageFormat::R32G32B32A32_SFLOAT {
+        return Err(ImageError::UnsupportedFormat(format));
+    }
+
+    let mut cursor = 0;
+    let magic = read_line(bytes, &mut cursor)?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(ImageError::InvalidData("missing radiance signature"));
+    }
+    loop {
+        let line = read_line(bytes, &mut cursor)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("FORMAT=") {
+            if value != "32-bit_rle_rgbe" {
+                return Err(ImageError::UnsupportedFeature(
+                    "hdr pixel format other than rgbe",
+                ));
+            }
+        }
+    }
+
+    // only the standard orientations are supported, "-Y" stores the top row first
+    let resolution: Vec<&str> = read_line(bytes, &mut cursor)?.split_whitespace().collect();
+    let (flip_y, height, width) = match resolution.as_slice() {
+        ["-Y", height, "+X", width] => (false, *height, *width),
+        ["+Y", height, "+X", width] => (true, *height, *width),
+        [_, _, _, _] => return Err(ImageError::UnsupportedFeature("hdr scanline orientation")),
+        _ => return Err(ImageError::InvalidData("malformed hdr resolution string")),
+    };
+    let height: u32 = height
+        .parse()
+        .map_err(|_| ImageError::InvalidData("malformed hdr resolution string"))?;
+    let width: u32 = width
+        .parse()
+        .map_err(|_| ImageError::InvalidData("malformed hdr resolution string"))?;
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidDimensions);
+    }
+
+    // a scanline takes 4 bytes per pixel, or 2 bytes per component for every run of up to 127
+    // pixels, files compressed further are rejected before the image is allocated
+    let min_scanline_size = (4 * width as u64).min(8 * width.div_ceil(127) as u64);
+    let min_size = min_scanline_size.checked_mul(height as u64);
+    if min_size.is_none_or(|size| size > (bytes.len() - cursor) as u64) {
+        return Err(ImageError::InvalidDimensions);
+    }
+
+    let mut image = Image::new(format, width, height, 1, 1, 1);
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for row in 0..height {
+        let header = bytes.get(cursor..cursor + 2);
+        let rle = (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&scanline.len())
+            && header == Some(&[2, 2])
+            && bytes.get(cursor + 2).is_some_and(|byte| byte & 0x80 == 0);
+        if rle {
+            read_rle_scanline(bytes, &mut cursor, &mut scanline)?;
+        } else {
+            read_flat_scanline(bytes, &mut cursor, &mut scanline)?;
+        }
+
+        let y = if flip_y { height - 1 - row } else { row };
+        for (x, pixel) in scanline.iter().enumerate() {
+            image.write_texel(0, 0, x as u32, y, 0, rgbe_to_float(*pixel));
+        }
+    }
+    return Ok(image);
+}
@@ -0,0 +1,343 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    ImageFormat,
+};
+use half::f16;
+
+/// Tightly packed image data for one or more array layers and mip levels.
+///
+/// Subresources are stored mip major: every array layer of mip 0, then every array layer of
+/// mip 1 and so on. Inside a subresource the depth slices, rows and blocks are tightly packed,
+/// which matches what a buffer to image copy with a zero row length expects.
+pub struct Image {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub array_size: u32,
+    pub mip_levels: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum Component {
+    Unorm8,
+    Snorm8,
+    Srgb8,
+    Unorm16,
+    Sfloat16,
+    Sfloat32,
+}
+
+impl Component {
+    fn size(&self) -> usize {
+        match self {
+            Component::Unorm8 | Component::Snorm8 | Component::Srgb8 => 1,
+            Component::Unorm16 | Component::Sfloat16 => 2,
+            Component::Sfloat32 => 4,
+        }
+    }
+}
+
+/// component encoding and the rgba channel each stored component maps to
+fn texel_layout(format: ImageFormat) -> Option<(Component, &'static [usize])> {
+    match format {
+        ImageFormat::R8_UNORM => Some((Component::Unorm8, &[0])),
+        ImageFormat::R8_SNORM => Some((Component::Snorm8, &[0])),
+        ImageFormat::R8_SRGB => Some((Component::Srgb8, &[0])),
+        ImageFormat::A8_UNORM => Some((Component::Unorm8, &[3])),
+        ImageFormat::R8G8_UNORM => Some((Component::Unorm8, &[0, 1])),
+        ImageFormat::R8G8_SNORM => Some((Component::Snorm8, &[0, 1])),
+        ImageFormat::R8G8_SRGB => Some((Component::Srgb8, &[0, 1])),
+        ImageFormat::R8G8B8_UNORM => Some((Component::Unorm8, &[0, 1, 2])),
+        ImageFormat::R8G8B8_SRGB => Some((Component::Srgb8, &[0, 1, 2])),
+        ImageFormat::B8G8R8_UNORM => Some((Component::Unorm8, &[2, 1, 0])),
+        ImageFormat::B8G8R8_SRGB => Some((Component::Srgb8, &[2, 1, 0])),
+        ImageFormat::R8G8B8A8_UNORM => Some((Component::Unorm8, &[0, 1, 2, 3])),
+        ImageFormat::R8G8B8A8_SNORM => Some((Component::Snorm8, &[0, 1, 2, 3])),
+        ImageFormat::R8G8B8A8_SRGB => Some((Component::Srgb8, &[0, 1, 2, 3])),
+        ImageFormat::B8G8R8A8_UNORM => Some((Component::Unorm8, &[2, 1, 0, 3])),
+        ImageFormat::B8G8R8A8_SRGB => Some((Component::Srgb8, &[2, 1, 0, 3])),
+        ImageFormat::R16_UNORM => Some((Component::Unorm16, &[0])),
+        ImageFormat::R16G16_UNORM => Some((Component::Unorm16, &[0, 1])),
+        ImageFormat::R16G16B16_UNORM => Some((Component::Unorm16, &[0, 1, 2])),
+        ImageFormat::R16G16B16A16_UNORM => Some((Component::Unorm16, &[0, 1, 2, 3])),
+        ImageFormat::R16_SFLOAT => Some((Component::Sfloat16, &[0])),
+        ImageFormat::R16G16_SFLOAT => Some((Component::Sfloat16, &[0, 1])),
+        ImageFormat::R16G16B16_SFLOAT => Some((Component::Sfloat16, &[0, 1, 2])),
+        ImageFormat::R16G16B16A16_SFLOAT => Some((Component::Sfloat16, &[0, 1, 2, 3])),
+        ImageFormat::R32_SFLOAT => Some((Component::Sfloat32, &[0])),
+        ImageFormat::R32G32_SFLOAT => Some((Component::Sfloat32, &[0, 1])),
+        ImageFormat::R32G32B32_SFLOAT => Some((Component::Sfloat32, &[0, 1, 2])),
+        ImageFormat::R32G32B32A32_SFLOAT => Some((Component::Sfloat32, &[0, 1, 2, 3])),
+        _ => None,
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+    return ((value + 0.055) / 1.055).powf(2.4);
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        return value * 12.92;
+    }
+    return 1.055 * value.powf(1.0 / 2.4) - 0.055;
+}
+
+fn decode_component(component: Component, bytes: &[u8], alpha: bool) -> f32 {
+    match component {
+        Component::Unorm8 => bytes[0] as f32 / 255.0,
+        Component::Snorm8 => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+        Component::Srgb8 => {
+            let value = bytes[0] as f32 / 255.0;
+            if alpha {
+                value
+            } else {
+                srgb_to_linear(value)
+            }
+        }
+        Component::Unorm16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+        Component::Sfloat16 => f16::from_le_bytes([bytes[0], bytes[1]]).to_f32(),
+        Component::Sfloat32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
+
+fn encode_component(component: Component, value: f32, alpha: bool, out: &mut [u8]) {
+    match component {
+        Component::Unorm8 => out[0] = (value.clamp(0.0, 1.0) * 255.0).round() as u8,
+        Component::Snorm8 => out[0] = (value.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8,
+        Component::Srgb8 => {
+            let value = value.clamp(0.0, 1.0);
+            let value = if alpha { value } else { linear_to_srgb(value) };
+            out[0] = (value * 255.0).round() as u8;
+        }
+        Component::Unorm16 => out[..2]
+            .copy_from_slice(&((value.clamp(0.0, 1.0) * 65535.0).round() as u16).to_le_bytes()),
+        Component::Sfloat16 => out[..2].copy_from_slice(&f16::from_f32(value).to_le_bytes()),
+        Component::Sfloat32 => out[..4].copy_from_slice(&value.to_le_bytes()),
+    }
+}
+
+/// number of mip levels in a full chain down to 1x1x1
+pub fn max_mip_levels(width: u32, height: u32, depth: u32) -> u32 {
+    return 32 - width.max(height).max(depth).max(1).leading_zeros();
+}
+
+impl Image {
+    pub fn new(
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        depth: u32,
+        array_size: u32,
+        mip_levels: u32,
+    ) -> Image {
+        assert!(
+            format.bit_size_of_block() > 0,
+            "format has no fixed block size"
+        );
+        assert!(width > 0 && height > 0 && depth > 0 && array_size > 0 && mip_levels > 0);
+        let mut image = Image {
+            format,
+            width,
+            height,
+            depth,
+            array_size,
+            mip_levels,
+            data: vec![],
+        };
+        let size = (0..mip_levels)
+            .map(|mip| image.layer_size(mip) * array_size as usize)
+            .sum();
+        image.data = vec![0; size];
+        return image;
+    }
+
+    /// true if `read_texel` and `write_texel` can be used with this format
+    pub fn supports_texel_access(format: ImageFormat) -> bool {
+        return texel_layout(format).is_some();
+    }
+
+    pub fn mip_extent(&self, mip: u32) -> (u32, u32, u32) {
+        return (
+            (self.width >> mip).max(1),
+            (self.height >> mip).max(1),
+            (self.depth >> mip).max(1),
+        );
+    }
+
+    pub fn row_pitch(&self, mip: u32) -> usize {
+        let (width, _, _) = self.mip_extent(mip);
+        let blocks = width.div_ceil(self.format.width_of_block());
+        return blocks as usize * self.format.bit_size_of_block() as usize / 8;
+    }
+
+    pub fn slice_pitch(&self, mip: u32) -> usize {
+        let (_, height, _) = self.mip_extent(mip);
+        let rows = height.div_ceil(self.format.height_of_block());
+        return rows as usize * self.row_pitch(mip);
+    }
+
+    /// size in bytes of a single array layer of the given mip
+    pub fn layer_size(&self, mip: u32) -> usize {
+        let (_, _, depth) = self.mip_extent(mip);
+        return depth as usize * self.slice_pitch(mip);
+    }
+
+    pub fn subresource_offset(&self, mip: u32, layer: u32) -> usize {
+        assert!(mip < self.mip_levels && layer < self.array_size);
+        let mip_offset: usize = (0..mip)
+            .map(|level| self.layer_size(level) * self.array_size as usize)
+            .sum();
+        return mip_offset + layer as usize * self.layer_size(mip);
+    }
+
+    pub fn subresource(&self, mip: u32, layer: u32) -> &[u8] {
+        let offset = self.subresource_offset(mip, layer);
+        return &self.data[offset..offset + self.layer_size(mip)];
+    }
+
+    pub fn subresource_mut(&mut self, mip: u32, layer: u32) -> &mut [u8] {
+        let offset = self.subresource_offset(mip, layer);
+        let size = self.layer_size(mip);
+        return &mut self.data[offset..offset + size];
+    }
+
+    fn texel_offset(&self, mip: u32, layer: u32, x: u32, y: u32, z: u32) -> usize {
+        let texel_size = self.format.bit_size_of_block() as usize / 8;
+        return self.subresource_offset(mip, layer)
+            + z as usize * self.slice_pitch(mip)
+            + y as usize * self.row_pitch(mip)
+            + x as usize * texel_size;
+    }
+
+    /// Reads a texel as linear rgba, missing channels read as 0 and alpha as 1.
+    ///
+    /// Panics if the format isn't supported, see `supports_texel_access`.
+    pub fn read_texel(&self, mip: u32, layer: u32, x: u32, y: u32, z: u32) -> [f32; 4] {
+        let (component, channels) =
+            texel_layout(self.format).expect("format doesn't support texel access");
+        let mut offset = self.texel_offset(mip, layer, x, y, z);
+        let mut result = [0.0, 0.0, 0.0, 1.0];
+        for channel in channels {
+            result[*channel] = decode_component(component, &self.data[offset..], *channel == 3);
+            offset += component.size();
+        }
+        return result;
+    }
+
+    /// Writes a linear rgba texel, channels the format doesn't store are dropped.
+    ///
+    /// Panics if the format isn't supported, see `supports_texel_access`.
+    pub fn write_texel(&mut self, mip: u32, layer: u32, x: u32, y: u32, z: u32, texel: [f32; 4]) {
+        let (component, channels) =
+            texel_layout(self.format).expect("format doesn't support texel access");
+        let mut offset = self.texel_offset(mip, layer, x, y, z);
+        for channel in channels {
+            encode_component(
+                component,
+                texel[*channel],
+                *channel == 3,
+                &mut self.data[offset..],
+            );
+            offset += component.size();
+        }
+    }
+
+    /// Copies the image into a new format, both formats have to support texel access.
+    pub fn convert(&self, format: ImageFormat) -> ImageResult<Image> {
+        if !Image::supports_texel_access(self.format) {
+            return Err(ImageError::UnsupportedFormat(self.format));
+        }
+        if !Image::supports_texel_access(format) {
+            return Err(ImageError::UnsupportedFormat(format));
+        }
+        if format == self.format {
+            return Ok(Image {
+                data: self.data.clone(),
+                ..*self
+            });
+        }
+
+        let mut result = Image::new(
+            format,
+            self.width,
+            self.height,
+            self.depth,
+            self.array_size,
+            self.mip_levels,
+        );
+        for mip in 0..self.mip_levels {
+            let (width, height, depth) = self.mip_extent(mip);
+            for layer in 0..self.array_size {
+                for z in 0..depth {
+                    for y in 0..height {
+                        for x in 0..width {
+                            let texel = self.read_texel(mip, layer, x, y, z);
+                            result.write_texel(mip, layer, x, y, z, texel);
+                        }
+                    }
+                }
+            }
+        }
+        return Ok(result);
+    }
+
+    /// Builds a copy with `mip_levels` levels where every level past mip 0 is a box filtered
+    /// downsample of the level above it. Each array layer is filtered on its own, so cubemap
+    /// faces never bleed into each other.
+    pub fn generate_mips(&self, mip_levels: u32) -> ImageResult<Image> {
+        if !Image::supports_texel_access(self.format) {
+            return Err(ImageError::UnsupportedFormat(self.format));
+        }
+        if mip_levels == 0 || mip_levels > max_mip_levels(self.width, self.height, self.depth) {
+            return Err(ImageError::InvalidDimensions);
+        }
+
+        let mut result = Image::new(
+            self.format,
+            self.width,
+            self.height,
+            self.depth,
+            self.array_size,
+            mip_levels,
+        );
+        for layer in 0..self.array_size {
+            result
+                .subresource_mut(0, layer)
+                .copy_from_slice(self.subresource(0, layer));
+        }
+
+        for mip in 1..mip_levels {
+            let (src_width, src_height, src_depth) = result.mip_extent(mip - 1);
+            let (width, height, depth) = result.mip_extent(mip);
+            for layer in 0..self.array_size {
+                for z in 0..depth {
+                    for y in 0..height {
+                        for x in 0..width {
+                            let mut sum = [0.0f32; 4];
+                            let mut count = 0.0;
+                            for sz in (z * 2)..((z * 2 + 2).min(src_depth)) {
+                                for sy in (y * 2)..((y * 2 + 2).min(src_height)) {
+                                    for sx in (x * 2)..((x * 2 + 2).min(src_width)) {
+                                        let texel = result.read_texel(mip - 1, layer, sx, sy, sz);
+                                        for channel in 0..4 {
+                                            sum[channel] += texel[channel];
+                                        }
+                                        count += 1.0;
+                                    }
+                                }
+                            }
+                            result.write_texel(mip, layer, x, y, z, sum.map(|value| value / count));
+                        }
+                    }
+                }
+            }
+        }
+        return Ok(result);
+    }
+}
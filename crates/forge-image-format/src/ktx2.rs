@@ -0,0 +1,339 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    uastc::{decode_uastc_block, is_uastc_transcode_target, transcode_uastc_block},
+    ImageFormat,
+};
+use std::io::Read;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+const COLOR_MODEL_ETC1S: u8 = 163;
+const COLOR_MODEL_UASTC: u8 = 166;
+const TRANSFER_FUNCTION_SRGB: u8 = 2;
+
+/// UASTC channel ids of the first sample that carry data in alpha
+const UASTC_CHANNEL_RGBA: u8 = 3;
+const UASTC_CHANNEL_RRRG: u8 = 5;
+const UASTC_CHANNEL_RG: u8 = 6;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum BasisCodec {
+    /// 16 byte blocks that map directly to ASTC 4x4 and carry BC7/ETC hints
+    Uastc,
+    /// BasisLZ supercompressed ETC1S
+    Etc1s,
+}
+
+/// Shape and encoding of a Basis Universal KTX2 file, enough to pick what to transcode it to.
+#[derive(Copy, Clone, Debug)]
+pub struct Ktx2Info {
+    pub codec: BasisCodec,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// array layers times faces, the faces of a cubemap are consecutive layers
+    pub array_size: u32,
+    pub mip_levels: u32,
+    pub srgb: bool,
+    pub has_alpha: bool,
+}
+
+struct Level {
+    offset: usize,
+    length: usize,
+    uncompressed_length: usize,
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, size: usize) -> ImageResult<&'a [u8]> {
+        let result = self
+            .cursor
+            .checked_add(size)
+            .and_then(|end| self.bytes.get(self.cursor..end))
+            .ok_or(ImageError::InvalidData("ktx2 data is truncated"))?;
+        self.cursor += size;
+        return Ok(result);
+    }
+
+    fn u8(&mut self) -> ImageResult<u8> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u16(&mut self) -> ImageResult<u16> {
+        return Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()));
+    }
+
+    fn u32(&mut self) -> ImageResult<u32> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> ImageResult<usize> {
+        let value = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        return usize::try_from(value)
+            .map_err(|_| ImageError::InvalidData("ktx2 offset is too large"));
+    }
+}
+
+fn parse_ktx2(bytes: &[u8]) -> ImageResult<(Ktx2Info, u32, Vec<Level>)> {
+    let mut reader = ByteReader { bytes, cursor: 0 };
+    if reader.take(12)? != KTX2_IDENTIFIER {
+        return Err(ImageError::InvalidData("ktx2 identifier is missing"));
+    }
+    let vk_format = reader.u32()?;
+    let _type_size = reader.u32()?;
+    let width = reader.u32()?;
+    let height = reader.u32()?.max(1);
+    let depth = reader.u32()?.max(1);
+    let layers = reader.u32()?.max(1);
+    let faces = reader.u32()?;
+    let level_count = reader.u32()?.max(1);
+    let supercompression = reader.u32()?;
+    let dfd_offset = reader.u32()? as usize;
+    let _dfd_length = reader.u32()?;
+    let _kvd_offset = reader.u32()?;
+    let _kvd_length = reader.u32()?;
+    let _sgd_offset = reader.u64()?;
+    let _sgd_length = reader.u64()?;
+    if width == 0 || (faces != 1 && faces != 6) {
+        return Err(ImageError::InvalidDimensions);
+    }
+    let array_size = layers
+        .checked_mul(faces)
+        .ok_or(ImageError::InvalidDimensions)?;
+    if vk_format != 0 {
+        return Err(ImageError::UnsupportedFeature(
+            "ktx2 file isn't basis universal encoded",
+        ));
+    }
+
+    let mut levels = Vec::new();
+    for _ in 0..level_count {
+        levels.push(Level {
+            offset: reader.u64()?,
+            length: reader.u64()?,
+            uncompressed_length: reader.u64()?,
+        });
+    }
+
+    // basic data format descriptor block, the codec is its color model
+    reader.cursor = dfd_offset;
+    let _total_size = reader.u32()?;
+    let _descriptor_type = reader.u32()?;
+    let _version = reader.u16()?;
+    let block_size = reader.u16()? as usize;
+    let color_model = reader.u8()?;
+    let _color_primaries = reader.u8()?;
+    let transfer_function = reader.u8()?;
+    let _flags = reader.u8()?;
+    let _block_dimensions = reader.take(12)?;
+    let sample_count = block_size.saturating_sub(24) / 16;
+    let mut channels = Vec::new();
+    for _ in 0..sample_count {
+        let sample = reader.take(16)?;
+        channels.push(sample[3] & 0xf);
+    }
+
+    let (codec, has_alpha) = match color_model {
+        COLOR_MODEL_UASTC => {
+            let channel = *channels.first().unwrap_or(&0);
+            let has_alpha = matches!(
+                channel,
+                UASTC_CHANNEL_RGBA | UASTC_CHANNEL_RRRG | UASTC_CHANNEL_RG
+            );
+            (BasisCodec::Uastc, has_alpha)
+        }
+        // a second slice holds the alpha (or green) channel
+        COLOR_MODEL_ETC1S => (BasisCodec::Etc1s, channels.len() > 1),
+        _ => {
+            return Err(ImageError::UnsupportedFeature(
+                "ktx2 file isn't basis universal encoded",
+            ))
+        }
+    };
+    let info = Ktx2Info {
+        codec,
+        width,
+        height,
+        depth,
+        array_size,
+        mip_levels: level_count,
+        srgb: transfer_function == TRANSFER_FUNCTION_SRGB,
+        has_alpha,
+    };
+    return Ok((info, supercompression, levels));
+}
+
+/// Reads the header and data format descriptor of a Basis Universal KTX2 file.
+pub fn read_ktx2_info(bytes: &[u8]) -> ImageResult<Ktx2Info> {
+    return Ok(parse_ktx2(bytes)?.0);
+}
+
+/// Picks the format `transcode_ktx2` should produce for a device with the given features.
+///
+/// ASTC keeps the UASTC blocks as they are and is preferred, then BC7 and ETC2. Devices without
+/// any of them get uncompressed rgba8.
+pub fn select_transcode_format(
+    info: &Ktx2Info,
+    features: &ash::vk::PhysicalDeviceFeatures,
+) -> ImageFormat {
+    let pick = |srgb: ImageFormat, unorm: ImageFormat| if info.srgb { srgb } else { unorm };
+    if features.texture_compression_astc_ldr != 0 {
+        return pick(ImageFormat::ASTC_4x4_SRGB, ImageFormat::ASTC_4x4_UNORM);
+    }
+    if features.texture_compression_bc != 0 {
+        return pick(ImageFormat::DXBC7_SRGB, ImageFormat::DXBC7_UNORM);
+    }
+    if features.texture_compression_etc2 != 0 {
+        if info.has_alpha {
+            return pick(
+                ImageFormat::ETC2_R8G8B8A8_SRGB,
+                ImageFormat::ETC2_R8G8B8A8_UNORM,
+            );
+        }
+        return pick(
+            ImageFormat::ETC2_R8G8B8_SRGB,
+            ImageFormat::ETC2_R8G8B8_UNORM,
+        );
+    }
+    return pick(ImageFormat::R8G8B8A8_SRGB, ImageFormat::R8G8B8A8_UNORM);
+}
+
+/// size of the UASTC blocks of every layer and face of `mip`
+fn level_block_size(info: &Ktx2Info, mip: u32) -> Option<usize> {
+    let blocks_x = (info.width >> mip).max(1).div_ceil(4) as usize;
+    let blocks_y = (info.height >> mip).max(1).div_ceil(4) as usize;
+    let depth = (info.depth >> mip).max(1) as usize;
+    return blocks_x
+        .checked_mul(blocks_y)?
+        .checked_mul(depth)?
+        .checked_mul(info.array_size as usize)?
+        .checked_mul(16);
+}
+
+fn level_data(bytes: &[u8], level: &Level, supercompression: u32) -> ImageResult<Vec<u8>> {
+    let data = level
+        .offset
+        .checked_add(level.length)
+        .and_then(|end| bytes.get(level.offset..end))
+        .ok_or(ImageError::InvalidData("ktx2 level is out of bounds"))?;
+    let result = match supercompression {
+        SUPERCOMPRESSION_NONE => data.to_vec(),
+        SUPERCOMPRESSION_ZSTD => {
+            let mut result = Vec::new();
+            ruzstd::StreamingDecoder::new(data)
+                .map_err(|_| ImageError::InvalidData("ktx2 zstd data is corrupt"))?
+                .take(level.uncompressed_length as u64)
+                .read_to_end(&mut result)
+                .map_err(|_| ImageError::InvalidData("ktx2 zstd data is corrupt"))?;
+            result
+        }
+        SUPERCOMPRESSION_ZLIB => {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, level.uncompressed_length)
+                .map_err(|_| ImageError::InvalidData("ktx2 zlib data is corrupt"))?
+        }
+        _ => {
+            return Err(ImageError::UnsupportedFeature(
+                "ktx2 supercompression scheme isn't supported",
+            ))
+        }
+    };
+    return Ok(result);
+}
+
+/// Transcodes a UASTC KTX2 file with all its mips, layers and faces to `format`.
+///
+/// `format` can be ASTC 4x4, BC7, ETC2 or `R8G8B8A8`, see `select_transcode_format`. Level data
+/// may be zstd or zlib supercompressed. BasisLZ/ETC1S files aren't supported.
+pub fn transcode_ktx2(bytes: &[u8], format: ImageFormat) -> ImageResult<Image> {
+    let (info, supercompression, levels) = parse_ktx2(bytes)?;
+    if info.codec == BasisCodec::Etc1s || supercompression == SUPERCOMPRESSION_BASIS_LZ {
+        return Err(ImageError::UnsupportedFeature(
+            "BasisLZ/ETC1S payloads can't be transcoded, only UASTC",
+        ));
+    }
+    let uncompressed = matches!(
+        format,
+        ImageFormat::R8G8B8A8_UNORM | ImageFormat::R8G8B8A8_SRGB
+    );
+    if !uncompressed && !is_uastc_transcode_target(format) {
+        return Err(ImageError::UnsupportedFormat(format));
+    }
+
+    // every level has to be inside the file and big enough for its blocks before the image
+    // sized from the header is allocated
+    for (mip, level) in (0..).zip(levels.iter()) {
+        let end = level.offset.checked_add(level.length);
+        if end.is_none_or(|end| end > bytes.len()) {
+            return Err(ImageError::InvalidData("ktx2 level is out of bounds"));
+        }
+        let block_size = level_block_size(&info, mip).ok_or(ImageError::InvalidDimensions)?;
+        let stored_size = match supercompression {
+            SUPERCOMPRESSION_NONE => level.length,
+            _ => level.uncompressed_length,
+        };
+        if stored_size < block_size {
+            return Err(ImageError::InvalidData(
+                "ktx2 level is smaller than its blocks",
+            ));
+        }
+    }
+
+    let mut image = Image::new(
+        format,
+        info.width,
+        info.height,
+        info.depth,
+        info.array_size,
+        info.mip_levels,
+    );
+    let block_size = format.bit_size_of_block() as usize / 8;
+    for (mip, level) in (0..).zip(levels.iter()) {
+        let data = level_data(bytes, level, supercompression)?;
+        let (width, height, depth) = image.mip_extent(mip);
+        let (blocks_x, blocks_y) = (width.div_ceil(4) as usize, height.div_ceil(4) as usize);
+        let layer_blocks = blocks_x * blocks_y * depth as usize;
+        if data.len() < layer_blocks * 16 * info.array_size as usize {
+            return Err(ImageError::InvalidData(
+                "ktx2 level is smaller than its blocks",
+            ));
+        }
+
+        // levels store every slice of a face, every face of a layer and then the next layer
+        for (layer, source) in (0..info.array_size).zip(data.chunks_exact(layer_blocks * 16)) {
+            let out = image.subresource_mut(mip, layer);
+            for (index, block) in source.chunks_exact(16).enumerate() {
+                let block: &[u8; 16] = block.try_into().unwrap();
+                if !uncompressed {
+                    transcode_uastc_block(block, format, &mut out[index * block_size..])?;
+                    continue;
+                }
+                let pixels = decode_uastc_block(block)?;
+                let block_x = index % blocks_x * 4;
+                let block_y = index / blocks_x % blocks_y * 4;
+                let z = index / (blocks_x * blocks_y);
+                for (pixel_index, pixel) in pixels.iter().enumerate() {
+                    let x = block_x + pixel_index % 4;
+                    let y = block_y + pixel_index / 4;
+                    if x < width as usize && y < height as usize {
+                        let offset = ((z * height as usize + y) * width as usize + x) * 4;
+                        out[offset..offset + 4].copy_from_slice(pixel);
+                    }
+                }
+            }
+        }
+    }
+    return Ok(image);
+}
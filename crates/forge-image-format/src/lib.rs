@@ -1,7 +1,21 @@
 
 
+#![allow(clippy::needless_return, clippy::match_like_matches_macro)]
+
+pub mod atlas;
+pub mod bptc;
+pub mod cubemap;
+pub mod error;
+pub mod exr;
+pub mod hdr;
+pub mod image;
+pub mod ktx2;
+pub mod lut;
+pub mod pvrtc;
+pub mod uastc;
+
 #[allow(non_camel_case_types)]
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 #[repr(u32)]
 pub enum ImageFormat {
     UNDEFINED,
@@ -333,6 +347,423 @@ impl ImageFormat {
         }
     }
 
+    /// block compressed formats (BC, PVRTC, ETC2/EAC, ASTC) all span more than one row
+    pub fn is_compressed(&self) -> bool {
+        return self.height_of_block() > 1;
+    }
+
+    /// number of texels covered by a single block in the x direction
+    pub fn width_of_block(&self) -> u32 {
+        match self {
+            ImageFormat::R1_UNORM => 8,
+            ImageFormat::R2_UNORM => 4,
+            ImageFormat::R4_UNORM => 2,
+            ImageFormat::G8B8G8R8_422_UNORM
+            | ImageFormat::B8G8R8G8_422_UNORM
+            | ImageFormat::G16B16G16R16_422_UNORM
+            | ImageFormat::B16G16R16G16_422_UNORM
+            | ImageFormat::G12X4B12X4G12X4R12X4_422_UNORM_4PACK16
+            | ImageFormat::B12X4G12X4R12X4G12X4_422_UNORM_4PACK16
+            | ImageFormat::G10X6B10X6G10X6R10X6_422_UNORM_4PACK16
+            | ImageFormat::B10X6G10X6R10X6G10X6_422_UNORM_4PACK16 => 2,
+            ImageFormat::DXBC1_RGB_UNORM
+            | ImageFormat::DXBC1_RGB_SRGB
+            | ImageFormat::DXBC1_RGBA_UNORM
+            | ImageFormat::DXBC1_RGBA_SRGB
+            | ImageFormat::DXBC2_UNORM
+            | ImageFormat::DXBC2_SRGB
+            | ImageFormat::DXBC3_UNORM
+            | ImageFormat::DXBC3_SRGB
+            | ImageFormat::DXBC4_UNORM
+            | ImageFormat::DXBC4_SNORM
+            | ImageFormat::DXBC5_UNORM
+            | ImageFormat::DXBC5_SNORM
+            | ImageFormat::DXBC6H_UFLOAT
+            | ImageFormat::DXBC6H_SFLOAT
+            | ImageFormat::DXBC7_UNORM
+            | ImageFormat::DXBC7_SRGB
+            | ImageFormat::PVRTC1_4BPP_UNORM
+            | ImageFormat::PVRTC2_4BPP_UNORM
+            | ImageFormat::PVRTC1_4BPP_SRGB
+            | ImageFormat::PVRTC2_4BPP_SRGB
+            | ImageFormat::ETC2_R8G8B8_UNORM
+            | ImageFormat::ETC2_R8G8B8_SRGB
+            | ImageFormat::ETC2_R8G8B8A1_UNORM
+            | ImageFormat::ETC2_R8G8B8A1_SRGB
+            | ImageFormat::ETC2_R8G8B8A8_UNORM
+            | ImageFormat::ETC2_R8G8B8A8_SRGB
+            | ImageFormat::ETC2_EAC_R11_UNORM
+            | ImageFormat::ETC2_EAC_R11_SNORM
+            | ImageFormat::ETC2_EAC_R11G11_UNORM
+            | ImageFormat::ETC2_EAC_R11G11_SNORM
+            | ImageFormat::ASTC_4x4_UNORM
+            | ImageFormat::ASTC_4x4_SRGB => 4,
+            ImageFormat::PVRTC1_2BPP_UNORM
+            | ImageFormat::PVRTC2_2BPP_UNORM
+            | ImageFormat::PVRTC1_2BPP_SRGB
+            | ImageFormat::PVRTC2_2BPP_SRGB => 8,
+            ImageFormat::ASTC_5x4_UNORM
+            | ImageFormat::ASTC_5x4_SRGB
+            | ImageFormat::ASTC_5x5_UNORM
+            | ImageFormat::ASTC_5x5_SRGB => 5,
+            ImageFormat::ASTC_6x5_UNORM
+            | ImageFormat::ASTC_6x5_SRGB
+            | ImageFormat::ASTC_6x6_UNORM
+            | ImageFormat::ASTC_6x6_SRGB => 6,
+            ImageFormat::ASTC_8x5_UNORM
+            | ImageFormat::ASTC_8x5_SRGB
+            | ImageFormat::ASTC_8x6_UNORM
+            | ImageFormat::ASTC_8x6_SRGB
+            | ImageFormat::ASTC_8x8_UNORM
+            | ImageFormat::ASTC_8x8_SRGB => 8,
+            ImageFormat::ASTC_10x5_UNORM
+            | ImageFormat::ASTC_10x5_SRGB
+            | ImageFormat::ASTC_10x6_UNORM
+            | ImageFormat::ASTC_10x6_SRGB
+            | ImageFormat::ASTC_10x8_UNORM
+            | ImageFormat::ASTC_10x8_SRGB
+            | ImageFormat::ASTC_10x10_UNORM
+            | ImageFormat::ASTC_10x10_SRGB => 10,
+            ImageFormat::ASTC_12x10_UNORM
+            | ImageFormat::ASTC_12x10_SRGB
+            | ImageFormat::ASTC_12x12_UNORM
+            | ImageFormat::ASTC_12x12_SRGB => 12,
+            _ => 1,
+        }
+    }
+
+    /// number of texels covered by a single block in the y direction
+    pub fn height_of_block(&self) -> u32 {
+        match self {
+            ImageFormat::DXBC1_RGB_UNORM
+            | ImageFormat::DXBC1_RGB_SRGB
+            | ImageFormat::DXBC1_RGBA_UNORM
+            | ImageFormat::DXBC1_RGBA_SRGB
+            | ImageFormat::DXBC2_UNORM
+            | ImageFormat::DXBC2_SRGB
+            | ImageFormat::DXBC3_UNORM
+            | ImageFormat::DXBC3_SRGB
+            | ImageFormat::DXBC4_UNORM
+            | ImageFormat::DXBC4_SNORM
+            | ImageFormat::DXBC5_UNORM
+            | ImageFormat::DXBC5_SNORM
+            | ImageFormat::DXBC6H_UFLOAT
+            | ImageFormat::DXBC6H_SFLOAT
+            | ImageFormat::DXBC7_UNORM
+            | ImageFormat::DXBC7_SRGB
+            | ImageFormat::PVRTC1_2BPP_UNORM
+            | ImageFormat::PVRTC1_4BPP_UNORM
+            | ImageFormat::PVRTC2_2BPP_UNORM
+            | ImageFormat::PVRTC2_4BPP_UNORM
+            | ImageFormat::PVRTC1_2BPP_SRGB
+            | ImageFormat::PVRTC1_4BPP_SRGB
+            | ImageFormat::PVRTC2_2BPP_SRGB
+            | ImageFormat::PVRTC2_4BPP_SRGB
+            | ImageFormat::ETC2_R8G8B8_UNORM
+            | ImageFormat::ETC2_R8G8B8_SRGB
+            | ImageFormat::ETC2_R8G8B8A1_UNORM
+            | ImageFormat::ETC2_R8G8B8A1_SRGB
+            | ImageFormat::ETC2_R8G8B8A8_UNORM
+            | ImageFormat::ETC2_R8G8B8A8_SRGB
+            | ImageFormat::ETC2_EAC_R11_UNORM
+            | ImageFormat::ETC2_EAC_R11_SNORM
+            | ImageFormat::ETC2_EAC_R11G11_UNORM
+            | ImageFormat::ETC2_EAC_R11G11_SNORM
+            | ImageFormat::ASTC_4x4_UNORM
+            | ImageFormat::ASTC_4x4_SRGB
+            | ImageFormat::ASTC_5x4_UNORM
+            | ImageFormat::ASTC_5x4_SRGB => 4,
+            ImageFormat::ASTC_5x5_UNORM
+            | ImageFormat::ASTC_5x5_SRGB
+            | ImageFormat::ASTC_6x5_UNORM
+            | ImageFormat::ASTC_6x5_SRGB
+            | ImageFormat::ASTC_8x5_UNORM
+            | ImageFormat::ASTC_8x5_SRGB
+            | ImageFormat::ASTC_10x5_UNORM
+            | ImageFormat::ASTC_10x5_SRGB => 5,
+            ImageFormat::ASTC_6x6_UNORM
+            | ImageFormat::ASTC_6x6_SRGB
+            | ImageFormat::ASTC_8x6_UNORM
+            | ImageFormat::ASTC_8x6_SRGB
+            | ImageFormat::ASTC_10x6_UNORM
+            | ImageFormat::ASTC_10x6_SRGB => 6,
+            ImageFormat::ASTC_8x8_UNORM
+            | ImageFormat::ASTC_8x8_SRGB
+            | ImageFormat::ASTC_10x8_UNORM
+            | ImageFormat::ASTC_10x8_SRGB => 8,
+            ImageFormat::ASTC_10x10_UNORM
+            | ImageFormat::ASTC_10x10_SRGB
+            | ImageFormat::ASTC_12x10_UNORM
+            | ImageFormat::ASTC_12x10_SRGB => 10,
+            ImageFormat::ASTC_12x12_UNORM | ImageFormat::ASTC_12x12_SRGB => 12,
+            _ => 1,
+        }
+    }
+
+    /// number of texels covered by a single block in the z direction
+    pub fn depth_of_block(&self) -> u32 {
+        return 1;
+    }
+
+    /// size of a single block in bits, planar and palette formats report 0
+    pub fn bit_size_of_block(&self) -> u32 {
+        match self {
+            ImageFormat::R1_UNORM
+            | ImageFormat::R2_UNORM
+            | ImageFormat::R4_UNORM
+            | ImageFormat::R4G4_UNORM
+            | ImageFormat::G4R4_UNORM
+            | ImageFormat::A8_UNORM
+            | ImageFormat::R8_UNORM
+            | ImageFormat::R8_SNORM
+            | ImageFormat::R8_UINT
+            | ImageFormat::R8_SINT
+            | ImageFormat::R8_SRGB
+            | ImageFormat::B2G3R3_UNORM
+            | ImageFormat::S8_UINT => 8,
+            ImageFormat::R4G4B4A4_UNORM
+            | ImageFormat::R4G4B4X4_UNORM
+            | ImageFormat::B4G4R4A4_UNORM
+            | ImageFormat::B4G4R4X4_UNORM
+            | ImageFormat::A4R4G4B4_UNORM
+            | ImageFormat::X4R4G4B4_UNORM
+            | ImageFormat::A4B4G4R4_UNORM
+            | ImageFormat::X4B4G4R4_UNORM
+            | ImageFormat::R5G6B5_UNORM
+            | ImageFormat::B5G6R5_UNORM
+            | ImageFormat::R5G5B5A1_UNORM
+            | ImageFormat::B5G5R5A1_UNORM
+            | ImageFormat::A1B5G5R5_UNORM
+            | ImageFormat::A1R5G5B5_UNORM
+            | ImageFormat::R5G5B5X1_UNORM
+            | ImageFormat::B5G5R5X1_UNORM
+            | ImageFormat::X1R5G5B5_UNORM
+            | ImageFormat::X1B5G5R5_UNORM
+            | ImageFormat::B2G3R3A8_UNORM
+            | ImageFormat::R8G8_UNORM
+            | ImageFormat::R8G8_SNORM
+            | ImageFormat::G8R8_UNORM
+            | ImageFormat::G8R8_SNORM
+            | ImageFormat::R8G8_UINT
+            | ImageFormat::R8G8_SINT
+            | ImageFormat::R8G8_SRGB
+            | ImageFormat::R16_UNORM
+            | ImageFormat::R16_SNORM
+            | ImageFormat::R16_UINT
+            | ImageFormat::R16_SINT
+            | ImageFormat::R16_SFLOAT
+            | ImageFormat::R16_SBFLOAT
+            | ImageFormat::D16_UNORM
+            | ImageFormat::R4G4B4A4_UNORM_PACK16
+            | ImageFormat::B4G4R4A4_UNORM_PACK16
+            | ImageFormat::R5G6B5_UNORM_PACK16
+            | ImageFormat::B5G6R5_UNORM_PACK16
+            | ImageFormat::R5G5B5A1_UNORM_PACK16
+            | ImageFormat::B5G5R5A1_UNORM_PACK16
+            | ImageFormat::A1R5G5B5_UNORM_PACK16 => 16,
+            ImageFormat::R8G8B8_UNORM
+            | ImageFormat::R8G8B8_SNORM
+            | ImageFormat::R8G8B8_UINT
+            | ImageFormat::R8G8B8_SINT
+            | ImageFormat::R8G8B8_SRGB
+            | ImageFormat::B8G8R8_UNORM
+            | ImageFormat::B8G8R8_SNORM
+            | ImageFormat::B8G8R8_UINT
+            | ImageFormat::B8G8R8_SINT
+            | ImageFormat::B8G8R8_SRGB
+            | ImageFormat::D16_UNORM_S8_UINT => 24,
+            ImageFormat::R8G8B8A8_UNORM
+            | ImageFormat::R8G8B8A8_SNORM
+            | ImageFormat::R8G8B8A8_UINT
+            | ImageFormat::R8G8B8A8_SINT
+            | ImageFormat::R8G8B8A8_SRGB
+            | ImageFormat::B8G8R8A8_UNORM
+            | ImageFormat::B8G8R8A8_SNORM
+            | ImageFormat::B8G8R8A8_UINT
+            | ImageFormat::B8G8R8A8_SINT
+            | ImageFormat::B8G8R8A8_SRGB
+            | ImageFormat::R8G8B8X8_UNORM
+            | ImageFormat::B8G8R8X8_UNORM
+            | ImageFormat::R16G16_UNORM
+            | ImageFormat::G16R16_UNORM
+            | ImageFormat::R16G16_SNORM
+            | ImageFormat::G16R16_SNORM
+            | ImageFormat::R16G16_UINT
+            | ImageFormat::R16G16_SINT
+            | ImageFormat::R16G16_SFLOAT
+            | ImageFormat::R16G16_SBFLOAT
+            | ImageFormat::R32_UINT
+            | ImageFormat::R32_SINT
+            | ImageFormat::R32_SFLOAT
+            | ImageFormat::A2R10G10B10_UNORM
+            | ImageFormat::A2R10G10B10_UINT
+            | ImageFormat::A2R10G10B10_SNORM
+            | ImageFormat::A2R10G10B10_SINT
+            | ImageFormat::A2B10G10R10_UNORM
+            | ImageFormat::A2B10G10R10_UINT
+            | ImageFormat::A2B10G10R10_SNORM
+            | ImageFormat::A2B10G10R10_SINT
+            | ImageFormat::R10G10B10A2_UNORM
+            | ImageFormat::R10G10B10A2_UINT
+            | ImageFormat::R10G10B10A2_SNORM
+            | ImageFormat::R10G10B10A2_SINT
+            | ImageFormat::B10G10R10A2_UNORM
+            | ImageFormat::B10G10R10A2_UINT
+            | ImageFormat::B10G10R10A2_SNORM
+            | ImageFormat::B10G10R10A2_SINT
+            | ImageFormat::B10G11R11_UFLOAT
+            | ImageFormat::E5B9G9R9_UFLOAT
+            | ImageFormat::X8_D24_UNORM
+            | ImageFormat::D32_SFLOAT
+            | ImageFormat::D24_UNORM_S8_UINT
+            | ImageFormat::G8B8G8R8_422_UNORM
+            | ImageFormat::B8G8R8G8_422_UNORM => 32,
+            ImageFormat::R16G16B16_UNORM
+            | ImageFormat::R16G16B16_SNORM
+            | ImageFormat::R16G16B16_UINT
+            | ImageFormat::R16G16B16_SINT
+            | ImageFormat::R16G16B16_SFLOAT
+            | ImageFormat::R16G16B16_SBFLOAT => 48,
+            ImageFormat::R16G16B16A16_UNORM
+            | ImageFormat::R16G16B16A16_SNORM
+            | ImageFormat::R16G16B16A16_UINT
+            | ImageFormat::R16G16B16A16_SINT
+            | ImageFormat::R16G16B16A16_SFLOAT
+            | ImageFormat::R16G16B16A16_SBFLOAT
+            | ImageFormat::R32G32_UINT
+            | ImageFormat::R32G32_SINT
+            | ImageFormat::R32G32_SFLOAT
+            | ImageFormat::R64_UINT
+            | ImageFormat::R64_SINT
+            | ImageFormat::R64_SFLOAT
+            | ImageFormat::D32_SFLOAT_S8_UINT
+            | ImageFormat::G16B16G16R16_422_UNORM
+            | ImageFormat::B16G16R16G16_422_UNORM
+            | ImageFormat::R12X4G12X4B12X4A12X4_UNORM_4PACK16
+            | ImageFormat::G12X4B12X4G12X4R12X4_422_UNORM_4PACK16
+            | ImageFormat::B12X4G12X4R12X4G12X4_422_UNORM_4PACK16
+            | ImageFormat::R10X6G10X6B10X6A10X6_UNORM_4PACK16
+            | ImageFormat::G10X6B10X6G10X6R10X6_422_UNORM_4PACK16
+            | ImageFormat::B10X6G10X6R10X6G10X6_422_UNORM_4PACK16 => 64,
+            ImageFormat::R32G32B32_UINT
+            | ImageFormat::R32G32B32_SINT
+            | ImageFormat::R32G32B32_SFLOAT => 96,
+            ImageFormat::R32G32B32A32_UINT
+            | ImageFormat::R32G32B32A32_SINT
+            | ImageFormat::R32G32B32A32_SFLOAT
+            | ImageFormat::R64G64_UINT
+            | ImageFormat::R64G64_SINT
+            | ImageFormat::R64G64_SFLOAT => 128,
+            ImageFormat::R64G64B64_UINT
+            | ImageFormat::R64G64B64_SINT
+            | ImageFormat::R64G64B64_SFLOAT => 192,
+            ImageFormat::R64G64B64A64_UINT
+            | ImageFormat::R64G64B64A64_SINT
+            | ImageFormat::R64G64B64A64_SFLOAT => 256,
+            ImageFormat::DXBC1_RGB_UNORM
+            | ImageFormat::DXBC1_RGB_SRGB
+            | ImageFormat::DXBC1_RGBA_UNORM
+            | ImageFormat::DXBC1_RGBA_SRGB
+            | ImageFormat::DXBC4_UNORM
+            | ImageFormat::DXBC4_SNORM
+            | ImageFormat::PVRTC1_2BPP_UNORM
+            | ImageFormat::PVRTC1_4BPP_UNORM
+            | ImageFormat::PVRTC2_2BPP_UNORM
+            | ImageFormat::PVRTC2_4BPP_UNORM
+            | ImageFormat::PVRTC1_2BPP_SRGB
+            | ImageFormat::PVRTC1_4BPP_SRGB
+            | ImageFormat::PVRTC2_2BPP_SRGB
+            | ImageFormat::PVRTC2_4BPP_SRGB
+            | ImageFormat::ETC2_R8G8B8_UNORM
+            | ImageFormat::ETC2_R8G8B8_SRGB
+            | ImageFormat::ETC2_R8G8B8A1_UNORM
+            | ImageFormat::ETC2_R8G8B8A1_SRGB
+            | ImageFormat::ETC2_EAC_R11_UNORM
+            | ImageFormat::ETC2_EAC_R11_SNORM => 64,
+            ImageFormat::DXBC2_UNORM
+            | ImageFormat::DXBC2_SRGB
+            | ImageFormat::DXBC3_UNORM
+            | ImageFormat::DXBC3_SRGB
+            | ImageFormat::DXBC5_UNORM
+            | ImageFormat::DXBC5_SNORM
+            | ImageFormat::DXBC6H_UFLOAT
+            | ImageFormat::DXBC6H_SFLOAT
+            | ImageFormat::DXBC7_UNORM
+            | ImageFormat::DXBC7_SRGB
+            | ImageFormat::ETC2_R8G8B8A8_UNORM
+            | ImageFormat::ETC2_R8G8B8A8_SRGB
+            | ImageFormat::ETC2_EAC_R11G11_UNORM
+            | ImageFormat::ETC2_EAC_R11G11_SNORM
+            | ImageFormat::ASTC_4x4_UNORM
+            | ImageFormat::ASTC_4x4_SRGB
+            | ImageFormat::ASTC_5x4_UNORM
+            | ImageFormat::ASTC_5x4_SRGB
+            | ImageFormat::ASTC_5x5_UNORM
+            | ImageFormat::ASTC_5x5_SRGB
+            | ImageFormat::ASTC_6x5_UNORM
+            | ImageFormat::ASTC_6x5_SRGB
+            | ImageFormat::ASTC_6x6_UNORM
+            | ImageFormat::ASTC_6x6_SRGB
+            | ImageFormat::ASTC_8x5_UNORM
+            | ImageFormat::ASTC_8x5_SRGB
+            | ImageFormat::ASTC_8x6_UNORM
+            | ImageFormat::ASTC_8x6_SRGB
+            | ImageFormat::ASTC_8x8_UNORM
+            | ImageFormat::ASTC_8x8_SRGB
+            | ImageFormat::ASTC_10x5_UNORM
+            | ImageFormat::ASTC_10x5_SRGB
+            | ImageFormat::ASTC_10x6_UNORM
+            | ImageFormat::ASTC_10x6_SRGB
+            | ImageFormat::ASTC_10x8_UNORM
+            | ImageFormat::ASTC_10x8_SRGB
+            | ImageFormat::ASTC_10x10_UNORM
+            | ImageFormat::ASTC_10x10_SRGB
+            | ImageFormat::ASTC_12x10_UNORM
+            | ImageFormat::ASTC_12x10_SRGB
+            | ImageFormat::ASTC_12x12_UNORM
+            | ImageFormat::ASTC_12x12_SRGB => 128,
+            _ => 0,
+        }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::R8_SRGB
+                | ImageFormat::R8G8_SRGB
+                | ImageFormat::R8G8B8_SRGB
+                | ImageFormat::B8G8R8_SRGB
+                | ImageFormat::R8G8B8A8_SRGB
+                | ImageFormat::B8G8R8A8_SRGB
+                | ImageFormat::DXBC1_RGB_SRGB
+                | ImageFormat::DXBC1_RGBA_SRGB
+                | ImageFormat::DXBC2_SRGB
+                | ImageFormat::DXBC3_SRGB
+                | ImageFormat::DXBC7_SRGB
+                | ImageFormat::PVRTC1_2BPP_SRGB
+                | ImageFormat::PVRTC1_4BPP_SRGB
+                | ImageFormat::PVRTC2_2BPP_SRGB
+                | ImageFormat::PVRTC2_4BPP_SRGB
+                | ImageFormat::ETC2_R8G8B8_SRGB
+                | ImageFormat::ETC2_R8G8B8A1_SRGB
+                | ImageFormat::ETC2_R8G8B8A8_SRGB
+                | ImageFormat::ASTC_4x4_SRGB
+                | ImageFormat::ASTC_5x4_SRGB
+                | ImageFormat::ASTC_5x5_SRGB
+                | ImageFormat::ASTC_6x5_SRGB
+                | ImageFormat::ASTC_6x6_SRGB
+                | ImageFormat::ASTC_8x5_SRGB
+                | ImageFormat::ASTC_8x6_SRGB
+                | ImageFormat::ASTC_8x8_SRGB
+                | ImageFormat::ASTC_10x5_SRGB
+                | ImageFormat::ASTC_10x6_SRGB
+                | ImageFormat::ASTC_10x8_SRGB
+                | ImageFormat::ASTC_10x10_SRGB
+                | ImageFormat::ASTC_12x10_SRGB
+                | ImageFormat::ASTC_12x12_SRGB
+        )
+    }
+
     pub fn from_vk_format(format: ash::vk::Format) -> ImageFormat {
         match format {
             ash::vk::Format::R4G4_UNORM_PACK8 => ImageFormat::G4R4_UNORM,
@@ -420,7 +851,6 @@ impl ImageFormat {
             ash::vk::Format::R64G64B64A64_SFLOAT => ImageFormat::R64G64B64A64_SFLOAT,
             ash::vk::Format::A2R10G10B10_UNORM_PACK32 => ImageFormat::B10G10R10A2_UNORM,
             ash::vk::Format::A2R10G10B10_UINT_PACK32 => ImageFormat::B10G10R10A2_UINT,
-            ash::vk::Format::A2B10G10R10_UNORM_PACK32 => ImageFormat::R10G10B10A2_UNORM,
             ash::vk::Format::A2B10G10R10_UINT_PACK32 => ImageFormat::R10G10B10A2_UINT,
             ash::vk::Format::B10G11R11_UFLOAT_PACK32 => ImageFormat::B10G11R11_UFLOAT,
             ash::vk::Format::E5B9G9R9_UFLOAT_PACK32 => ImageFormat::E5B9G9R9_UFLOAT,
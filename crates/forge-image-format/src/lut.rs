@@ -0,0 +1,276 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    ImageFormat,
+};
+
+/// 1D tables with more entries than this are resampled when baked into a volume
+const MAX_BAKED_VOLUME_SIZE: u32 = 64;
+
+/// A single table of a `.cube` file together with the input range it covers.
+pub struct CubeTable {
+    pub size: u32,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    /// 1D tables hold `size` entries, 3D tables `size³` entries with red changing fastest, then
+    /// green and blue
+    pub data: Vec<[f32; 3]>,
+}
+
+/// Adobe/Resolve `.cube` LUT. Resolve files can carry a 1D shaper that is applied before the
+/// 3D table, Adobe files have exactly one of the two.
+pub struct CubeLut {
+    pub title: Option<String>,
+    pub lut_1d: Option<CubeTable>,
+    pub lut_3d: Option<CubeTable>,
+}
+
+fn to_domain(value: f32, min: f32, max: f32) -> f32 {
+    if max <= min {
+        return 0.0;
+    }
+    return ((value - min) / (max - min)).clamp(0.0, 1.0);
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    return [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ];
+}
+
+/// index of the lower entry and the blend factor towards the next one
+fn table_coordinate(value: f32, size: u32) -> (usize, f32) {
+    let position = value * (size - 1) as f32;
+    let index = (position.floor() as usize).min(size as usize - 2);
+    return (index, position - index as f32);
+}
+
+impl CubeTable {
+    fn sample_1d(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for channel in 0..3 {
+            let value = to_domain(
+                rgb[channel],
+                self.domain_min[channel],
+                self.domain_max[channel],
+            );
+            let (index, t) = table_coordinate(value, self.size);
+            let a = self.data[index][channel];
+            let b = self.data[index + 1][channel];
+            result[channel] = a + (b - a) * t;
+        }
+        return result;
+    }
+
+    fn sample_3d(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let size = self.size as usize;
+        let mut index = [0usize; 3];
+        let mut t = [0.0f32; 3];
+        for channel in 0..3 {
+            let value = to_domain(
+                rgb[channel],
+                self.domain_min[channel],
+                self.domain_max[channel],
+            );
+            (index[channel], t[channel]) = table_coordinate(value, self.size);
+        }
+        let fetch = |r: usize, g: usize, b: usize| -> [f32; 3] {
+            return self.data[(index[2] + b) * size * size + (index[1] + g) * size + index[0] + r];
+        };
+        let c00 = lerp(fetch(0, 0, 0), fetch(1, 0, 0), t[0]);
+        let c10 = lerp(fetch(0, 1, 0), fetch(1, 1, 0), t[0]);
+        let c01 = lerp(fetch(0, 0, 1), fetch(1, 0, 1), t[0]);
+        let c11 = lerp(fetch(0, 1, 1), fetch(1, 1, 1), t[0]);
+        return lerp(lerp(c00, c10, t[1]), lerp(c01, c11, t[1]), t[2]);
+    }
+}
+
+fn parse_floats<const N: usize>(values: &[&str]) -> ImageResult<[f32; N]> {
+    if values.len() != N {
+        return Err(ImageError::InvalidData(
+            "wrong number of values in cube lut",
+        ));
+    }
+    let mut result = [0.0; N];
+    for (value, text) in result.iter_mut().zip(values) {
+        *value = text
+            .parse()
+            .map_err(|_| ImageError::InvalidData("malformed number in cube lut"))?;
+    }
+    return Ok(result);
+}
+
+fn parse_size(values: &[&str], max: u32) -> ImageResult<u32> {
+    let size: u32 = match values {
+        [size] => size
+            .parse()
+            .map_err(|_| ImageError::InvalidData("malformed cube lut size"))?,
+        _ => return Err(ImageError::InvalidData("malformed cube lut size")),
+    };
+    if size < 2 || size > max {
+        return Err(ImageError::InvalidData("cube lut size out of range"));
+    }
+    return Ok(size);
+}
+
+impl CubeLut {
+    pub fn parse(text: &str) -> ImageResult<CubeLut> {
+        let mut title = None;
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut domain = None;
+        let mut range_1d = None;
+        let mut range_3d = None;
+        let mut data = vec![];
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let keyword_allowed = data.is_empty();
+            match tokens[0] {
+                "TITLE" if keyword_allowed => {
+                    title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string())
+                }
+                "LUT_1D_SIZE" if keyword_allowed => {
+                    size_1d = Some(parse_size(&tokens[1..], 65536)?)
+                }
+                "LUT_3D_SIZE" if keyword_allowed => size_3d = Some(parse_size(&tokens[1..], 256)?),
+                "DOMAIN_MIN" if keyword_allowed => {
+                    let min = parse_floats::<3>(&tokens[1..])?;
+                    domain = Some((min, domain.map_or([1.0; 3], |(_, max)| max)));
+                }
+                "DOMAIN_MAX" if keyword_allowed => {
+                    let max = parse_floats::<3>(&tokens[1..])?;
+                    domain = Some((domain.map_or([0.0; 3], |(min, _)| min), max));
+                }
+                "LUT_1D_INPUT_RANGE" if keyword_allowed => {
+                    range_1d = Some(parse_floats::<2>(&tokens[1..])?)
+                }
+                "LUT_3D_INPUT_RANGE" if keyword_allowed => {
+                    range_3d = Some(parse_floats::<2>(&tokens[1..])?)
+                }
+                _ => data.push(parse_floats::<3>(&tokens)?),
+            }
+        }
+
+        let entries_1d = size_1d.map_or(0, |size| size as usize);
+        let entries_3d = size_3d.map_or(0, |size| size as usize * size as usize * size as usize);
+        if entries_1d + entries_3d == 0 {
+            return Err(ImageError::InvalidData("cube lut has no size"));
+        }
+        if data.len() != entries_1d + entries_3d {
+            return Err(ImageError::InvalidData(
+                "cube lut has the wrong number of entries",
+            ));
+        }
+
+        let (domain_min, domain_max) = domain.unwrap_or(([0.0; 3], [1.0; 3]));
+        let ranges = range_1d
+            .iter()
+            .chain(range_3d.iter())
+            .map(|range| (range[0], range[1]));
+        if (0..3)
+            .map(|channel| (domain_min[channel], domain_max[channel]))
+            .chain(ranges)
+            .any(|(min, max)| min >= max)
+        {
+            return Err(ImageError::InvalidData("cube lut domain is empty"));
+        }
+        let table = |size: u32, range: Option<[f32; 2]>, data: Vec<[f32; 3]>| CubeTable {
+            size,
+            domain_min: range.map_or(domain_min, |range| [range[0]; 3]),
+            domain_max: range.map_or(domain_max, |range| [range[1]; 3]),
+            data,
+        };
+        let data_3d = data.split_off(entries_1d);
+        return Ok(CubeLut {
+            title,
+            lut_1d: size_1d.map(|size| table(size, range_1d, data)),
+            lut_3d: size_3d.map(|size| table(size, range_3d, data_3d)),
+        });
+    }
+
+    /// Input range covered by the LUT, colors have to be remapped from this range to [0, 1]
+    /// before they are used to sample the volume built by `to_image`.
+    pub fn domain(&self) -> ([f32; 3], [f32; 3]) {
+        let table = self.lut_1d.as_ref().or(self.lut_3d.as_ref()).unwrap();
+        return (table.domain_min, table.domain_max);
+    }
+
+    /// Per channel scale and bias mapping an input color to the texture coordinate of a volume
+    /// built by `to_image`, including the half texel offset so the domain bounds hit texel centers.
+    pub fn texture_scale_bias(&self) -> ([f32; 3], [f32; 3]) {
+        let (min, max) = self.domain();
+        let size = self.volume_size() as f32;
+        let mut scale = [0.0; 3];
+        let mut bias = [0.0; 3];
+        for channel in 0..3 {
+            let domain_scale = 1.0 / (max[channel] - min[channel]);
+            scale[channel] = domain_scale * (size - 1.0) / size;
+            bias[channel] = -min[channel] * scale[channel] + 0.5 / size;
+        }
+        return (scale, bias);
+    }
+
+    /// Applies the LUT to a single color with linear interpolation.
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let mut result = rgb;
+        if let Some(table) = &self.lut_1d {
+            result = table.sample_1d(result);
+        }
+        if let Some(table) = &self.lut_3d {
+            result = table.sample_3d(result);
+        }
+        return result;
+    }
+
+    /// edge length of the volume `to_image` builds
+    pub fn volume_size(&self) -> u32 {
+        match (&self.lut_1d, &self.lut_3d) {
+            (_, Some(table)) => table.size,
+            (Some(table), None) => table.size.min(MAX_BAKED_VOLUME_SIZE),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// Builds a `volume_size()`³ 3D image, one depth slice per blue entry, ready to be uploaded as
+    /// a 3D texture. `format` has to be `R16G16B16A16_SFLOAT` or `R32G32B32A32_SFLOAT`.
+    ///
+    /// A lone 3D table is copied as is, LUTs with a 1D table are baked by evaluating `sample`
+    /// over the input domain. The renderer's `TextureDesc::cube_lut` describes the matching
+    /// texture.
+    pub fn to_image(&self, format: ImageFormat) -> ImageResult<Image> {
+        if format != ImageFormat::R16G16B16A16_SFLOAT && format != ImageFormat::R32G32B32A32_SFLOAT
+        {
+            return Err(ImageError::UnsupportedFormat(format));
+        }
+        let size = self.volume_size();
+        let (min, max) = self.domain();
+        let mut image = Image::new(format, size, size, size, 1, 1);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let rgb = match (&self.lut_1d, &self.lut_3d) {
+                        (None, Some(table)) => table.data[((b * size + g) * size + r) as usize],
+                        _ => {
+                            let position = [r, g, b];
+                            let mut input = [0.0; 3];
+                            for channel in 0..3 {
+                                let t = position[channel] as f32 / (size - 1) as f32;
+                                input[channel] = min[channel] + (max[channel] - min[channel]) * t;
+                            }
+                            self.sample(input)
+                        }
+                    };
+                    image.write_texel(0, 0, r, g, b, [rgb[0], rgb[1], rgb[2], 1.0]);
+                }
+            }
+        }
+        return Ok(image);
+    }
+}
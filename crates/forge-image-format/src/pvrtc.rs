@@ -0,0 +1,358 @@
+use crate::{
+    error::{ImageError, ImageResult},
+    image::Image,
+    ImageFormat,
+};
+
+/// modulation weights out of 8 for the four 2 bit values of the standard mode
+const MODULATION_WEIGHTS: [u32; 4] = [0, 3, 5, 8];
+
+struct Variant {
+    /// 8x4 blocks with 2 bits per texel instead of 4x4 with 4
+    two_bpp: bool,
+    /// PVRTC2, blocks are stored in row order and bit 15 is the hard transition flag
+    version_2: bool,
+    srgb: bool,
+}
+
+fn variant(format: ImageFormat) -> Option<Variant> {
+    let (two_bpp, version_2, srgb) = match format {
+        ImageFormat::PVRTC1_2BPP_UNORM => (true, false, false),
+        ImageFormat::PVRTC1_4BPP_UNORM => (false, false, false),
+        ImageFormat::PVRTC2_2BPP_UNORM => (true, true, false),
+        ImageFormat::PVRTC2_4BPP_UNORM => (false, true, false),
+        ImageFormat::PVRTC1_2BPP_SRGB => (true, false, true),
+        ImageFormat::PVRTC1_4BPP_SRGB => (false, false, true),
+        ImageFormat::PVRTC2_2BPP_SRGB => (true, true, true),
+        ImageFormat::PVRTC2_4BPP_SRGB => (false, true, true),
+        _ => return None,
+    };
+    return Some(Variant {
+        two_bpp,
+        version_2,
+        srgb,
+    });
+}
+
+/// true for the eight PVRTC1/PVRTC2 formats `decode_pvrtc` accepts
+pub fn is_pvrtc(format: ImageFormat) -> bool {
+    return variant(format).is_some();
+}
+
+/// Block index of a PVRTC1 block, the low bits of x and y are interleaved (y first) and the
+/// remaining bits of the larger dimension are appended.
+fn twiddle(blocks_x: u32, blocks_y: u32, x: u32, y: u32) -> usize {
+    let (min_dimension, mut remaining) = if blocks_y < blocks_x {
+        (blocks_y, x)
+    } else {
+        (blocks_x, y)
+    };
+    let mut result = 0;
+    let mut shift = 0;
+    while (1 << shift) < min_dimension {
+        result |= ((y >> shift) & 1) << (2 * shift);
+        result |= ((x >> shift) & 1) << (2 * shift + 1);
+        shift += 1;
+    }
+    remaining >>= shift;
+    result |= remaining << (2 * shift);
+    return result as usize;
+}
+
+struct Block {
+    modulation: u32,
+    /// rgb with 5 bits and alpha with 4 bits per channel
+    color_a: [u32; 4],
+    color_b: [u32; 4],
+    mode: bool,
+    hard: bool,
+}
+
+fn expand(value: u32, from: u32, to: u32) -> u32 {
+    return (value << (to - from)) | (value >> (2 * from - to));
+}
+
+fn decode_block(bytes: &[u8], version_2: bool) -> Block {
+    let modulation = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    let color = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    let low = color & 0xffff;
+    let high = color >> 16;
+    let (opaque_a, opaque_b) = if version_2 {
+        (high & 0x8000 != 0, high & 0x8000 != 0)
+    } else {
+        (low & 0x8000 != 0, high & 0x8000 != 0)
+    };
+
+    let color_a = if opaque_a {
+        [
+            (low >> 10) & 31,
+            (low >> 5) & 31,
+            expand((low >> 1) & 15, 4, 5),
+            15,
+        ]
+    } else {
+        [
+            expand((low >> 8) & 15, 4, 5),
+            expand((low >> 4) & 15, 4, 5),
+            expand((low >> 1) & 7, 3, 5),
+            ((low >> 12) & 7) << 1,
+        ]
+    };
+    // PVRTC2 sets the missing alpha bit of color b so a fully transparent a and b can't coincide
+    let color_b = if opaque_b {
+        [(high >> 10) & 31, (high >> 5) & 31, high & 31, 15]
+    } else {
+        [
+            expand((high >> 8) & 15, 4, 5),
+            expand((high >> 4) & 15, 4, 5),
+            expand(high & 15, 4, 5),
+            ((high >> 12) & 7) << 1 | version_2 as u32,
+        ]
+    };
+    return Block {
+        modulation,
+        color_a,
+        color_b,
+        mode: color & 1 != 0,
+        hard: version_2 && low & 0x8000 != 0,
+    };
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum Interpolation {
+    Both,
+    Horizontal,
+    Vertical,
+}
+
+/// Per texel modulation of one 2D surface. `values` holds the 2 bit value of every texel that
+/// stores one, interpolated 2bpp blocks leave every other texel to `weight`.
+struct Modulation {
+    width: usize,
+    height: usize,
+    values: Vec<u8>,
+    interpolation: Vec<Option<Interpolation>>,
+}
+
+impl Modulation {
+    fn value(&self, x: i64, y: i64) -> u32 {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        return MODULATION_WEIGHTS[self.values[y * self.width + x] as usize];
+    }
+
+    /// modulation weight out of 8 for 2bpp texels
+    fn weight(&self, x: usize, y: usize, block_index: usize) -> u32 {
+        let stored = (x ^ y) & 1 == 0;
+        let (x, y) = (x as i64, y as i64);
+        match self.interpolation[block_index] {
+            Some(interpolation) if !stored => match interpolation {
+                Interpolation::Both => {
+                    (self.value(x - 1, y)
+                        + self.value(x + 1, y)
+                        + self.value(x, y - 1)
+                        + self.value(x, y + 1)
+                        + 2)
+                        / 4
+                }
+                Interpolation::Horizontal => {
+                    (self.value(x - 1, y) + self.value(x + 1, y)).div_ceil(2)
+                }
+                Interpolation::Vertical => {
+                    (self.value(x, y - 1) + self.value(x, y + 1)).div_ceil(2)
+                }
+            },
+            _ => self.value(x, y),
+        }
+    }
+}
+
+fn unpack_2bpp_modulation(blocks: &[Block], blocks_x: usize, blocks_y: usize) -> Modulation {
+    let mut modulation = Modulation {
+        width: blocks_x * 8,
+        height: blocks_y * 4,
+        values: vec![0; blocks_x * 8 * blocks_y * 4],
+        interpolation: vec![None; blocks.len()],
+    };
+    for (index, block) in blocks.iter().enumerate() {
+        let origin_x = index % blocks_x * 8;
+        let origin_y = index / blocks_x * 4;
+        let mut bits = block.modulation;
+        if block.mode {
+            // the lowest bit of the first and the center value are repurposed as mode bits,
+            // those values only keep their high bit
+            let interpolation = if bits & 1 == 0 {
+                Interpolation::Both
+            } else if bits & (1 << 20) != 0 {
+                Interpolation::Vertical
+            } else {
+                Interpolation::Horizontal
+            };
+            if interpolation != Interpolation::Both {
+                bits = (bits & !(1 << 20)) | ((bits >> 1) & (1 << 20));
+            }
+            bits = (bits & !1) | ((bits >> 1) & 1);
+            modulation.interpolation[index] = Some(interpolation);
+            for y in 0..4 {
+                for x in 0..8 {
+                    if (x ^ y) & 1 == 0 {
+                        modulation.values[(origin_y + y) * modulation.width + origin_x + x] =
+                            (bits & 3) as u8;
+                        bits >>= 2;
+                    }
+                }
+            }
+        } else {
+            for y in 0..4 {
+                for x in 0..8 {
+                    modulation.values[(origin_y + y) * modulation.width + origin_x + x] =
+                        if bits & 1 != 0 { 3 } else { 0 };
+                    bits >>= 1;
+                }
+            }
+        }
+    }
+    return modulation;
+}
+
+fn to_8bit(color: [u32; 4], scale: u32) -> [u32; 4] {
+    let rgb = |value: u32| value * 8 / scale + value / (4 * scale);
+    return [
+        rgb(color[0]),
+        rgb(color[1]),
+        rgb(color[2]),
+        color[3] * 16 / scale + color[3] / scale,
+    ];
+}
+
+fn decode_surface(
+    variant: &Variant,
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    out: &mut [u8],
+) -> ImageResult<()> {
+    let block_width = if variant.two_bpp { 8 } else { 4 };
+    let blocks_x = width.div_ceil(block_width);
+    let blocks_y = height.div_ceil(4);
+    if !variant.version_2 && (!blocks_x.is_power_of_two() || !blocks_y.is_power_of_two()) {
+        return Err(ImageError::InvalidDimensions);
+    }
+
+    let mut blocks = Vec::with_capacity((blocks_x * blocks_y) as usize);
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let index = if variant.version_2 {
+                (by * blocks_x + bx) as usize
+            } else {
+                twiddle(blocks_x, blocks_y, bx, by)
+            };
+            let block = decode_block(&bytes[index * 8..index * 8 + 8], variant.version_2);
+            if block.hard && block.mode && !variant.two_bpp {
+                return Err(ImageError::UnsupportedFeature("pvrtc2 local palette mode"));
+            }
+            blocks.push(block);
+        }
+    }
+    let (blocks_x, blocks_y) = (blocks_x as usize, blocks_y as usize);
+    let modulation = variant
+        .two_bpp
+        .then(|| unpack_2bpp_modulation(&blocks, blocks_x, blocks_y));
+
+    let block_width = block_width as i64;
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let block_index = y / 4 * blocks_x + x / block_width as usize;
+            let block = &blocks[block_index];
+
+            // a and b are bilinearly upscaled from the block grid with the block colors at the
+            // block centers, wrapping at the edges. PVRTC2 hard transition blocks use their own.
+            let (color_a, color_b) = if block.hard {
+                (to_8bit(block.color_a, 1), to_8bit(block.color_b, 1))
+            } else {
+                let px = x as i64 - block_width / 2;
+                let py = y as i64 - 2;
+                let (bx0, by0) = (px.div_euclid(block_width), py.div_euclid(4));
+                let (fx, fy) = ((px - bx0 * block_width) as u32, (py - by0 * 4) as u32);
+                let corners = [
+                    (bx0, by0, (block_width as u32 - fx) * (4 - fy)),
+                    (bx0 + 1, by0, fx * (4 - fy)),
+                    (bx0, by0 + 1, (block_width as u32 - fx) * fy),
+                    (bx0 + 1, by0 + 1, fx * fy),
+                ];
+                let mut color_a = [0; 4];
+                let mut color_b = [0; 4];
+                for (cx, cy, weight) in corners {
+                    let corner = &blocks[cy.rem_euclid(blocks_y as i64) as usize * blocks_x
+                        + cx.rem_euclid(blocks_x as i64) as usize];
+                    for channel in 0..4 {
+                        color_a[channel] += corner.color_a[channel] * weight;
+                        color_b[channel] += corner.color_b[channel] * weight;
+                    }
+                }
+                let scale = block_width as u32 * 4;
+                (to_8bit(color_a, scale), to_8bit(color_b, scale))
+            };
+
+            let (weight, punch_through) = match &modulation {
+                Some(modulation) => (modulation.weight(x, y, block_index), false),
+                None => {
+                    let value = (block.modulation >> (2 * ((y % 4) * 4 + x % 4))) & 3;
+                    if block.mode {
+                        ([0, 4, 4, 8][value as usize], value == 2)
+                    } else {
+                        (MODULATION_WEIGHTS[value as usize], false)
+                    }
+                }
+            };
+
+            let texel = &mut out[(y * width as usize + x) * 4..][..4];
+            for channel in 0..4 {
+                texel[channel] =
+                    ((color_a[channel] * (8 - weight) + color_b[channel] * weight) / 8) as u8;
+            }
+            if punch_through {
+                texel[3] = 0;
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Decodes every mip and layer of a PVRTC1/PVRTC2 image to `R8G8B8A8_UNORM`, or
+/// `R8G8B8A8_SRGB` for the sRGB variants.
+///
+/// PVRTC1 needs power of two block counts. PVRTC2 hard transition blocks are decoded with the
+/// colors of the block itself, the 4bpp local palette mode isn't supported.
+pub fn decode_pvrtc(image: &Image) -> ImageResult<Image> {
+    let variant = variant(image.format).ok_or(ImageError::UnsupportedFormat(image.format))?;
+    if image.depth != 1 {
+        return Err(ImageError::InvalidDimensions);
+    }
+    let format = if variant.srgb {
+        ImageFormat::R8G8B8A8_SRGB
+    } else {
+        ImageFormat::R8G8B8A8_UNORM
+    };
+    let mut result = Image::new(
+        format,
+        image.width,
+        image.height,
+        1,
+        image.array_size,
+        image.mip_levels,
+    );
+    for mip in 0..image.mip_levels {
+        let (width, height, _) = image.mip_extent(mip);
+        for layer in 0..image.array_size {
+            decode_surface(
+                &variant,
+                image.subresource(mip, layer),
+                width,
+                height,
+                result.subresource_mut(mip, layer),
+            )?;
+        }
+    }
+    return Ok(result);
+}
@@ -0,0 +1,1300 @@
+use std::sync::OnceLock;
+
+use crate::{
+    bptc::Bc7Block,
+    error::{ImageError, ImageResult},
+    ImageFormat,
+};
+
+/// the mode that stores a single rgba color instead of endpoints and weights
+const SOLID_MODE: usize = 8;
+
+/// prefix code and its length in bits for every mode, mode 19 is reserved
+const MODE_CODES: [(u32, u32); 20] = [
+    (0x1, 4),
+    (0x35, 6),
+    (0x1d, 5),
+    (0x3, 5),
+    (0x13, 5),
+    (0xb, 5),
+    (0x1b, 5),
+    (0x7, 5),
+    (0x17, 5),
+    (0xf, 5),
+    (0x2, 3),
+    (0x0, 2),
+    (0x6, 3),
+    (0x1f, 5),
+    (0xd, 5),
+    (0x5, 7),
+    (0x15, 6),
+    (0x25, 6),
+    (0x9, 4),
+    (0x45, 7),
+];
+
+struct Mode {
+    weight_bits: u32,
+    /// ASTC integer sequence range of the endpoints
+    endpoint_range: usize,
+    subsets: usize,
+    dual_plane: bool,
+    /// 2 for luminance alpha, 3 for rgb and 4 for rgba
+    components: usize,
+    /// the ETC1 hints include a color bias, modes without it also skip the second BC1 hint
+    etc1_bias: bool,
+    /// ASTC color endpoint mode
+    cem: u32,
+    /// 11 bit ASTC block mode with the same weight grid and range
+    astc_block_mode: u32,
+}
+
+const fn mode(
+    weight_bits: u32,
+    endpoint_range: usize,
+    subsets: usize,
+    dual_plane: bool,
+    components: usize,
+    etc1_bias: bool,
+    astc_block_mode: u32,
+) -> Mode {
+    let cem = match components {
+        2 => 4,
+        3 => 8,
+        _ => 12,
+    };
+    return Mode {
+        weight_bits,
+        endpoint_range,
+        subsets,
+        dual_plane,
+        components,
+        etc1_bias,
+        cem,
+        astc_block_mode,
+    };
+}
+
+#[rustfmt::skip]
+const MODES: [Mode; 19] = [
+    mode(4, 19, 1, false, 3, true, 0x242),
+    mode(2, 20, 1, false, 3, true, 0x42),
+    mode(3, 8, 2, false, 3, true, 0x53),
+    mode(2, 7, 3, false, 3, true, 0x42),
+    mode(2, 12, 2, false, 3, true, 0x42),
+    mode(3, 20, 1, false, 3, true, 0x53),
+    mode(2, 18, 1, true, 3, true, 0x442),
+    mode(2, 12, 2, false, 3, true, 0x42),
+    mode(0, 0, 1, false, 4, false, 0),
+    mode(2, 8, 2, false, 4, true, 0x42),
+    mode(4, 13, 1, false, 4, false, 0x242),
+    mode(2, 13, 1, true, 4, false, 0x442),
+    mode(3, 19, 1, false, 4, false, 0x53),
+    mode(1, 20, 1, true, 4, true, 0x441),
+    mode(2, 20, 1, false, 4, true, 0x42),
+    mode(4, 20, 1, false, 2, true, 0x242),
+    mode(2, 20, 2, false, 2, true, 0x42),
+    mode(2, 20, 1, true, 2, true, 0x442),
+    mode(5, 11, 1, false, 3, true, 0x253),
+];
+
+/// ASTC partition seeds of the two subset patterns shared with BC7
+#[rustfmt::skip]
+const PARTITION_SEEDS_2: [u32; 30] = [
+    28, 20, 16, 29, 91, 9, 107, 72, 149, 204, 50, 114, 496, 17, 78, 39, 252, 828, 43, 156, 116,
+    210, 476, 273, 684, 359, 246, 195, 694, 524,
+];
+
+/// ASTC partition seeds of the three subset patterns shared with BC7
+const PARTITION_SEEDS_3: [u32; 11] = [260, 74, 32, 156, 183, 15, 745, 0, 335, 902, 254];
+
+/// ASTC partition seeds of mode 7, two subset patterns that merge two subsets of a BC7 pattern
+#[rustfmt::skip]
+const PARTITION_SEEDS_2_MERGED: [u32; 19] = [
+    36, 48, 61, 137, 161, 183, 226, 281, 302, 307, 479, 495, 593, 594, 605, 799, 812, 988, 993,
+];
+
+/// BC7 partition of every two subset pattern and whether its subsets are in the opposite order
+#[rustfmt::skip]
+const BC7_PATTERNS_2: [(usize, bool); 30] = [
+    (0, false), (1, false), (2, true), (3, false), (4, true), (5, false), (6, true), (7, true),
+    (8, false), (9, true), (10, false), (11, true), (12, true), (13, true), (14, false),
+    (15, true), (17, true), (18, true), (19, false), (20, false), (21, false), (22, true),
+    (23, true), (24, false), (25, true), (26, false), (29, true), (32, true), (33, true),
+    (52, true),
+];
+
+/// BC7 partition of every three subset pattern and the permutation from ASTC to BC7 subsets
+#[rustfmt::skip]
+const BC7_PATTERNS_3: [(usize, usize); 11] = [
+    (4, 0), (8, 5), (9, 5), (10, 2), (11, 2), (12, 0), (13, 4), (20, 1), (35, 1), (36, 5),
+    (57, 0),
+];
+
+/// BC7 three subset partition of every mode 7 pattern and which of its subsets are merged
+#[rustfmt::skip]
+const BC7_PATTERNS_2_MERGED: [(usize, usize); 19] = [
+    (10, 4), (11, 4), (0, 3), (2, 4), (8, 5), (13, 4), (1, 2), (33, 2), (40, 3), (20, 4),
+    (21, 0), (58, 3), (3, 0), (32, 2), (59, 1), (34, 3), (20, 1), (14, 4), (31, 3),
+];
+
+const BC7_SUBSET_PERMUTATIONS: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+    [0, 2, 1],
+    [1, 0, 2],
+];
+
+/// bits, trits and quints of the ASTC integer sequence ranges
+#[rustfmt::skip]
+const ISE_RANGES: [(u32, u32, u32); 21] = [
+    (1, 0, 0), (0, 1, 0), (2, 0, 0), (0, 0, 1), (1, 1, 0), (3, 0, 0), (1, 0, 1), (2, 1, 0),
+    (4, 0, 0), (2, 0, 1), (3, 1, 0), (5, 0, 0), (3, 0, 1), (4, 1, 0), (6, 0, 0), (4, 0, 1),
+    (5, 1, 0), (7, 0, 0), (5, 0, 1), (6, 1, 0), (8, 0, 0),
+];
+
+/// bit swizzle `B` and multiplier `C` of the ASTC endpoint unquantization for trit and quint ranges
+#[rustfmt::skip]
+const UNQUANTIZE_PARAMS: [(&[u8; 9], u32); 21] = [
+    (b"         ", 0), (b"         ", 0), (b"         ", 0), (b"         ", 0),
+    (b"000000000", 204), (b"         ", 0), (b"000000000", 113), (b"b000b0bb0", 93),
+    (b"         ", 0), (b"b0000bb00", 54), (b"cb000cbcb", 44), (b"         ", 0),
+    (b"cb0000cbc", 26), (b"dcb000dcb", 22), (b"         ", 0), (b"dcb0000dc", 13),
+    (b"edcb000ed", 11), (b"         ", 0), (b"edcb0000e", 6), (b"fedcb000f", 5),
+    (b"         ", 0),
+];
+
+/// 8 bit packing of five trits, the inverse of the ASTC trit decoding
+#[rustfmt::skip]
+const TRIT_ENCODE: [u8; 243] = [
+    0, 1, 2, 4, 5, 6, 8, 9, 10, 16, 17, 18, 20, 21, 22, 24, 25, 26, 3, 7, 11, 19, 23, 27, 12, 13,
+    14, 32, 33, 34, 36, 37, 38, 40, 41, 42, 48, 49, 50, 52, 53, 54, 56, 57, 58, 35, 39, 43, 51,
+    55, 59, 44, 45, 46, 64, 65, 66, 68, 69, 70, 72, 73, 74, 80, 81, 82, 84, 85, 86, 88, 89, 90,
+    67, 71, 75, 83, 87, 91, 76, 77, 78, 128, 129, 130, 132, 133, 134, 136, 137, 138, 144, 145,
+    146, 148, 149, 150, 152, 153, 154, 131, 135, 139, 147, 151, 155, 140, 141, 142, 160, 161, 162,
+    164, 165, 166, 168, 169, 170, 176, 177, 178, 180, 181, 182, 184, 185, 186, 163, 167, 171, 179,
+    183, 187, 172, 173, 174, 192, 193, 194, 196, 197, 198, 200, 201, 202, 208, 209, 210, 212, 213,
+    214, 216, 217, 218, 195, 199, 203, 211, 215, 219, 204, 205, 206, 96, 97, 98, 100, 101, 102,
+    104, 105, 106, 112, 113, 114, 116, 117, 118, 120, 121, 122, 99, 103, 107, 115, 119, 123, 108,
+    109, 110, 224, 225, 226, 228, 229, 230, 232, 233, 234, 240, 241, 242, 244, 245, 246, 248, 249,
+    250, 227, 231, 235, 243, 247, 251, 236, 237, 238, 28, 29, 30, 60, 61, 62, 92, 93, 94, 156,
+    157, 158, 188, 189, 190, 220, 221, 222, 31, 63, 95, 159, 191, 223, 124, 125, 126,
+];
+
+/// 7 bit packing of three quints, the inverse of the ASTC quint decoding
+#[rustfmt::skip]
+const QUINT_ENCODE: [u8; 125] = [
+    0, 1, 2, 3, 4, 8, 9, 10, 11, 12, 16, 17, 18, 19, 20, 24, 25, 26, 27, 28, 5, 13, 21, 29, 6, 32,
+    33, 34, 35, 36, 40, 41, 42, 43, 44, 48, 49, 50, 51, 52, 56, 57, 58, 59, 60, 37, 45, 53, 61,
+    14, 64, 65, 66, 67, 68, 72, 73, 74, 75, 76, 80, 81, 82, 83, 84, 88, 89, 90, 91, 92, 69, 77,
+    85, 93, 22, 96, 97, 98, 99, 100, 104, 105, 106, 107, 108, 112, 113, 114, 115, 116, 120, 121,
+    122, 123, 124, 101, 109, 117, 125, 30, 102, 103, 70, 71, 38, 110, 111, 78, 79, 46, 118, 119,
+    86, 87, 54, 126, 127, 94, 95, 62, 39, 47, 55, 63, 31,
+];
+
+/// unquantized weights out of 64 for 1 to 5 bit weights
+const WEIGHTS_1: [u32; 2] = [0, 64];
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 8, 12, 17, 21, 25, 29, 35, 39, 43, 47, 52, 56, 60, 64];
+#[rustfmt::skip]
+const WEIGHTS_5: [u32; 32] = [
+    0, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 34, 36, 38, 40, 42, 44, 46, 48, 50,
+    52, 54, 56, 58, 60, 62, 64,
+];
+
+#[rustfmt::skip]
+const ETC1_INTENSITIES: [[i32; 4]; 8] = [
+    [-8, -2, 2, 8], [-17, -5, 5, 17], [-29, -9, 9, 29], [-42, -13, 13, 42],
+    [-60, -18, 18, 60], [-80, -24, 24, 80], [-106, -33, 33, 106], [-183, -47, 47, 183],
+];
+
+/// selector bytes of the solid color hint, by the hinted selector
+const ETC1_SOLID_SELECTORS: [[u8; 4]; 4] = [
+    [255, 255, 255, 255],
+    [255, 255, 0, 0],
+    [0, 0, 0, 0],
+    [0, 0, 255, 255],
+];
+
+#[rustfmt::skip]
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14], [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12], [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11], [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10], [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9], [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9], [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9], [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8], [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// ETC1 encoding hints the UASTC encoder stores next to every block
+#[derive(Default)]
+struct Etc1Hints {
+    flip: bool,
+    diff: bool,
+    intensity: [u32; 2],
+    bias: u32,
+    /// selector and 5 or 4 bit color of solid blocks
+    solid_selector: usize,
+    solid_color: [u32; 3],
+}
+
+struct UastcBlock {
+    mode: usize,
+    solid_color: [u8; 4],
+    /// subset of every pixel in row order
+    partition: [u8; 16],
+    /// index into the pattern tables of the mode
+    pattern: usize,
+    partition_seed: u32,
+    /// first pixel of every subset, its weights are stored with one bit less
+    anchors: [usize; 3],
+    /// channel that uses the second weight plane
+    plane_channel: usize,
+    /// ISE values, low and high endpoint of each component of each subset
+    endpoints: [u8; 18],
+    /// weights in pixel order, interleaved per plane for dual plane modes
+    weights: [u8; 32],
+    etc1: Etc1Hints,
+    /// EAC alpha table in the low and multiplier in the high 4 bits
+    etc2_hint: u32,
+}
+
+struct BitReader {
+    bits: u128,
+    offset: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.bits >> self.offset) as u32 & ((1u64 << count) - 1) as u32;
+        self.offset += count;
+        return value;
+    }
+}
+
+fn astc_hash52(value: u32) -> u32 {
+    let mut p = value;
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    return p;
+}
+
+/// ASTC partition of a texel in a small (fewer than 31 texels) 2D block
+fn astc_partition(seed: u32, x: u32, y: u32, subsets: u32) -> u8 {
+    let (x, y) = (x << 1, y << 1);
+    let seed = seed + (subsets - 1) * 1024;
+    let rnum = astc_hash52(seed);
+    let mut seeds = [0u32; 8];
+    for (index, value) in seeds.iter_mut().enumerate() {
+        let nibble = (rnum >> (index * 4)) & 0xf;
+        *value = nibble * nibble;
+    }
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (
+            if seed & 2 != 0 { 4 } else { 5 },
+            if subsets == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if subsets == 3 { 6 } else { 5 },
+            if seed & 2 != 0 { 4 } else { 5 },
+        )
+    };
+    let a = ((seeds[0] >> sh1) * x + (seeds[1] >> sh2) * y + (rnum >> 14)) & 0x3f;
+    let b = ((seeds[2] >> sh1) * x + (seeds[3] >> sh2) * y + (rnum >> 10)) & 0x3f;
+    let c = if subsets < 3 {
+        0
+    } else {
+        ((seeds[4] >> sh1) * x + (seeds[5] >> sh2) * y + (rnum >> 6)) & 0x3f
+    };
+    let d = if subsets < 4 {
+        0
+    } else {
+        ((seeds[6] >> sh1) * x + (seeds[7] >> sh2) * y + (rnum >> 2)) & 0x3f
+    };
+    if a >= b && a >= c && a >= d {
+        return 0;
+    } else if b >= c && b >= d {
+        return 1;
+    } else if c >= d {
+        return 2;
+    }
+    return 3;
+}
+
+/// ASTC endpoint unquantization of an ISE value to 8 bits
+fn unquantize_endpoint(value: u8, range: usize) -> u8 {
+    let (bits, trits, quints) = ISE_RANGES[range];
+    let value = value as u32;
+    if trits == 0 && quints == 0 {
+        // replicate the bits until all 8 are filled
+        let mut result = 0;
+        let mut bits_left = 8i32;
+        while bits_left > 0 {
+            let count = bits_left.min(bits as i32);
+            result |= (value >> (bits as i32 - count)) << (bits_left - count);
+            bits_left -= count;
+        }
+        return result as u8;
+    }
+    let low = value & ((1 << bits) - 1);
+    let (swizzle, c) = UNQUANTIZE_PARAMS[range];
+    let a = if low & 1 != 0 { 0x1ff } else { 0 };
+    let mut b = 0;
+    for symbol in swizzle {
+        b <<= 1;
+        if *symbol != b'0' {
+            b |= (low >> (symbol - b'a')) & 1;
+        }
+    }
+    let result = ((value >> bits) * c + b) ^ a;
+    return ((a & 0x80) | (result >> 2)) as u8;
+}
+
+fn unpack_block(bytes: &[u8; 16]) -> ImageResult<UastcBlock> {
+    let bits = u128::from_le_bytes(*bytes);
+    let mode = MODE_CODES
+        .iter()
+        .position(|(code, length)| bits as u32 & ((1 << length) - 1) == *code)
+        .unwrap();
+    if mode >= MODES.len() {
+        return Err(ImageError::InvalidData("uastc block uses a reserved mode"));
+    }
+    let mut reader = BitReader {
+        bits,
+        offset: MODE_CODES[mode].1,
+    };
+    let mut block = UastcBlock {
+        mode,
+        solid_color: [0; 4],
+        partition: [0; 16],
+        pattern: 0,
+        partition_seed: 0,
+        anchors: [0; 3],
+        plane_channel: 0,
+        endpoints: [0; 18],
+        weights: [0; 32],
+        etc1: Etc1Hints::default(),
+        etc2_hint: 0,
+    };
+
+    if mode == SOLID_MODE {
+        for channel in block.solid_color.iter_mut() {
+            *channel = reader.read(8) as u8;
+        }
+        block.etc1.diff = reader.read(1) != 0;
+        block.etc1.intensity[0] = reader.read(3);
+        block.etc1.solid_selector = reader.read(2) as usize;
+        for channel in block.etc1.solid_color.iter_mut() {
+            *channel = reader.read(5);
+        }
+        return Ok(block);
+    }
+
+    let desc = &MODES[mode];
+    // the bc1 hints aren't used by any target
+    reader.read(1 + desc.etc1_bias as u32);
+    block.etc1.flip = reader.read(1) != 0;
+    block.etc1.diff = reader.read(1) != 0;
+    block.etc1.intensity = [reader.read(3), reader.read(3)];
+    if desc.etc1_bias {
+        block.etc1.bias = reader.read(5);
+    }
+    if desc.components != 3 {
+        block.etc2_hint = reader.read(8);
+    }
+
+    let seeds: &[u32] = match mode {
+        7 => &PARTITION_SEEDS_2_MERGED,
+        2 | 4 | 9 | 16 => &PARTITION_SEEDS_2,
+        3 => &PARTITION_SEEDS_3,
+        _ => &[],
+    };
+    if desc.subsets > 1 {
+        block.pattern = reader.read(if desc.subsets == 3 { 4 } else { 5 }) as usize;
+        block.partition_seed = *seeds.get(block.pattern).ok_or(ImageError::InvalidData(
+            "uastc block uses an unknown partition",
+        ))?;
+        for (index, subset) in block.partition.iter_mut().enumerate() {
+            *subset = astc_partition(
+                block.partition_seed,
+                index as u32 & 3,
+                index as u32 >> 2,
+                desc.subsets as u32,
+            );
+        }
+        for subset in 1..desc.subsets {
+            block.anchors[subset] = block
+                .partition
+                .iter()
+                .position(|value| *value as usize == subset)
+                .unwrap();
+        }
+        block.anchors[0] = block
+            .partition
+            .iter()
+            .position(|value| *value == 0)
+            .unwrap();
+    }
+
+    if desc.dual_plane {
+        block.plane_channel = if mode == 17 {
+            3
+        } else {
+            reader.read(2) as usize
+        };
+    }
+
+    // trits and quints of all endpoints are stored first, bundled into 8 and 7 bit groups
+    let value_count = desc.components * 2 * desc.subsets;
+    let (bits_per_value, trits, quints) = ISE_RANGES[desc.endpoint_range];
+    let (bundle_size, base) = match (trits, quints) {
+        (1, _) => (5, 3),
+        (_, 1) => (3, 5),
+        _ => (0, 0),
+    };
+    let mut bundles = [0u32; 8];
+    let bundle_count = if bundle_size == 0 {
+        0
+    } else {
+        value_count.div_ceil(bundle_size)
+    };
+    for (index, bundle) in bundles.iter_mut().take(bundle_count).enumerate() {
+        let remaining = value_count - index * bundle_size;
+        let length = match (bundle_size, remaining) {
+            (5, 1) => 2,
+            (5, 2) => 4,
+            (5, 3) => 5,
+            (5, 4) => 7,
+            (5, _) => 8,
+            (3, 1) => 3,
+            (3, 2) => 5,
+            _ => 7,
+        };
+        *bundle = reader.read(length);
+    }
+    for index in 0..value_count {
+        let mut value = reader.read(bits_per_value);
+        if let Some(bundle_index) = index.checked_div(bundle_size) {
+            let bundle = &mut bundles[bundle_index];
+            value |= (*bundle % base) << bits_per_value;
+            *bundle /= base;
+        }
+        block.endpoints[index] = value as u8;
+    }
+
+    let planes = if desc.dual_plane { 2 } else { 1 };
+    for index in 0..16 * planes {
+        let pixel = index / planes;
+        let anchor = block.anchors[..desc.subsets].contains(&pixel);
+        block.weights[index] = reader.read(desc.weight_bits - anchor as u32) as u8;
+    }
+    return Ok(block);
+}
+
+fn weight_table(bits: u32) -> &'static [u32] {
+    match bits {
+        1 => &WEIGHTS_1,
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        4 => &WEIGHTS_4,
+        _ => &WEIGHTS_5,
+    }
+}
+
+/// ASTC interpolation of 8 bit endpoints without the srgb rounding
+fn interpolate(low: u32, high: u32, weight: u32) -> u8 {
+    let low = (low << 8) | low;
+    let high = (high << 8) | high;
+    return (((low * (64 - weight) + high * weight + 32) >> 6) >> 8) as u8;
+}
+
+fn decode_block(block: &UastcBlock) -> [[u8; 4]; 16] {
+    if block.mode == SOLID_MODE {
+        return [block.solid_color; 16];
+    }
+    let desc = &MODES[block.mode];
+    let mut endpoints = [[[255u32; 4]; 2]; 3];
+    for (subset, subset_endpoints) in endpoints.iter_mut().take(desc.subsets).enumerate() {
+        let values = &block.endpoints[subset * desc.components * 2..];
+        let value = |index: usize| unquantize_endpoint(values[index], desc.endpoint_range) as u32;
+        for (end, endpoint) in subset_endpoints.iter_mut().enumerate() {
+            if desc.components == 2 {
+                let luminance = value(end);
+                *endpoint = [luminance, luminance, luminance, value(2 + end)];
+            } else {
+                for (channel, component) in endpoint.iter_mut().take(desc.components).enumerate() {
+                    *component = value(channel * 2 + end);
+                }
+            }
+        }
+    }
+
+    let weights = weight_table(desc.weight_bits);
+    let planes = if desc.dual_plane { 2 } else { 1 };
+    let mut pixels = [[0u8; 4]; 16];
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let [low, high] = endpoints[block.partition[index] as usize];
+        for channel in 0..4 {
+            let plane = (desc.dual_plane && channel == block.plane_channel) as usize;
+            let weight = weights[block.weights[index * planes + plane] as usize];
+            pixel[channel] = interpolate(low[channel], high[channel], weight);
+        }
+    }
+    return pixels;
+}
+
+/// Decodes one UASTC block to rgba8 pixels in row order.
+pub fn decode_uastc_block(bytes: &[u8; 16]) -> ImageResult<[[u8; 4]; 16]> {
+    return Ok(decode_block(&unpack_block(bytes)?));
+}
+
+fn encode_trits(values: &[u8], bits: u32) -> (u64, u32) {
+    let mut trits = 0;
+    let mut low = [0u64; 5];
+    for (index, value) in values.iter().enumerate() {
+        trits += (*value as usize >> bits) * 3usize.pow(index as u32);
+        low[index] = *value as u64 & ((1 << bits) - 1);
+    }
+    let t = TRIT_ENCODE[trits] as u64;
+    let packed = low[0]
+        | (t & 3) << bits
+        | low[1] << (2 + bits)
+        | ((t >> 2) & 3) << (2 + bits * 2)
+        | low[2] << (4 + bits * 2)
+        | ((t >> 4) & 1) << (4 + bits * 3)
+        | low[3] << (5 + bits * 3)
+        | ((t >> 5) & 3) << (5 + bits * 4)
+        | low[4] << (7 + bits * 4)
+        | (t >> 7) << (7 + bits * 5);
+    return (packed, 8 + bits * 5);
+}
+
+fn encode_quints(values: &[u8], bits: u32) -> (u64, u32) {
+    let mut quints = 0;
+    let mut low = [0u64; 3];
+    for (index, value) in values.iter().enumerate() {
+        quints += (*value as usize >> bits) * 5usize.pow(index as u32);
+        low[index] = *value as u64 & ((1 << bits) - 1);
+    }
+    let q = QUINT_ENCODE[quints] as u64;
+    let packed = low[0]
+        | (q & 7) << bits
+        | low[1] << (3 + bits)
+        | ((q >> 3) & 3) << (3 + bits * 2)
+        | low[2] << (5 + bits * 2)
+        | (q >> 5) << (5 + bits * 3);
+    return (packed, 7 + bits * 3);
+}
+
+fn transcode_to_astc(block: &UastcBlock) -> [u8; 16] {
+    if block.mode == SOLID_MODE {
+        // void extent block with 16 bit unorm channels
+        let mut bits = 0xffff_ffff_ffff_fdfcu128;
+        for (channel, value) in block.solid_color.iter().enumerate() {
+            bits |= (*value as u128 * 257) << (64 + channel * 16);
+        }
+        return bits.to_le_bytes();
+    }
+    let desc = &MODES[block.mode];
+    let planes = if desc.dual_plane { 2 } else { 1 };
+    let mut endpoints = block.endpoints;
+    let mut weights = block.weights;
+
+    // ASTC swaps the endpoints and contracts blue when the high endpoint is darker, swap them
+    // back and invert the weights so the block decodes the same
+    if desc.components >= 3 {
+        let weight_max = (1 << desc.weight_bits) - 1;
+        for subset in 0..desc.subsets {
+            let values = &mut endpoints[subset * desc.components * 2..];
+            let sum = |end: usize| -> u32 {
+                return (0..3)
+                    .map(|channel| {
+                        unquantize_endpoint(values[channel * 2 + end], desc.endpoint_range) as u32
+                    })
+                    .sum();
+            };
+            if sum(1) < sum(0) {
+                for channel in 0..desc.components {
+                    values.swap(channel * 2, channel * 2 + 1);
+                }
+                for pixel in 0..16 {
+                    if block.partition[pixel] as usize == subset {
+                        for plane in 0..planes {
+                            let weight = &mut weights[pixel * planes + plane];
+                            *weight = weight_max - *weight;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut bits = desc.astc_block_mode as u128;
+    let mut offset = 11;
+    let mut write = |value: u64, count: u32| {
+        bits |= (value as u128) << offset;
+        offset += count;
+    };
+    write(desc.subsets as u64 - 1, 2);
+    if desc.subsets == 1 {
+        write(desc.cem as u64, 4);
+    } else {
+        write(block.partition_seed as u64, 10);
+        // every subset shares the endpoint mode
+        write((desc.cem as u64) << 2, 6);
+    }
+
+    let value_count = desc.components * 2 * desc.subsets;
+    let (bits_per_value, trits, quints) = ISE_RANGES[desc.endpoint_range];
+    let group_size = match (trits, quints) {
+        (1, _) => 5,
+        (_, 1) => 3,
+        _ => 1,
+    };
+    for group in endpoints[..value_count].chunks(group_size) {
+        let (value, count) = match group_size {
+            5 => encode_trits(group, bits_per_value),
+            3 => encode_quints(group, bits_per_value),
+            _ => (group[0] as u64, bits_per_value),
+        };
+        write(value, count);
+    }
+
+    // weights are stored bit reversed from the top of the block down
+    let weight_count = 16 * planes as u32;
+    for (index, weight) in weights[..weight_count as usize].iter().enumerate() {
+        let reversed = (*weight as u32).reverse_bits() >> (32 - desc.weight_bits);
+        bits |= (reversed as u128) << (128 - desc.weight_bits * (index as u32 + 1));
+    }
+    if desc.dual_plane {
+        let position = 128 - weight_count * desc.weight_bits - 2;
+        bits |= (block.plane_channel as u128) << position;
+    }
+    return bits.to_le_bytes();
+}
+
+/// BC7 endpoints and p-bits closest to `low` and `high` in 0..1, with `bits` per channel and
+/// either one p-bit per endpoint or one shared by both
+fn bc7_pbit_endpoints(
+    low: &[f32; 4],
+    high: &[f32; 4],
+    components: usize,
+    bits: u32,
+    shared: bool,
+) -> ([[u32; 4]; 2], [u32; 2]) {
+    let total_bits = bits + 1;
+    let scale_max = (1i32 << total_bits) - 1;
+    let scale = scale_max as f32;
+    let mut stored = [[0; 4]; 2];
+    let mut pbits = [0; 2];
+    let mut best_error = [1e9f32; 2];
+    for pbit in 0..2 {
+        let quantize = |value: f32| -> u32 {
+            let rounded = ((value * scale - pbit as f32) / 2.0 + 0.5) as i32 * 2 + pbit;
+            return rounded.clamp(pbit, scale_max - 1 + pbit) as u32;
+        };
+        let expand = |value: u32| -> f32 {
+            let value = value << (8 - total_bits);
+            return (value | (value >> total_bits)) as f32;
+        };
+        let quantized = [low.map(quantize), high.map(quantize)];
+        let mut error = [0.0f32; 2];
+        for channel in 0..components {
+            if shared {
+                error[0] += (expand(quantized[0][channel]) / 255.0 - low[channel]).powi(2)
+                    + (expand(quantized[1][channel]) / 255.0 - high[channel]).powi(2);
+            } else {
+                error[0] += (expand(quantized[0][channel]) - low[channel] * 255.0).powi(2);
+                error[1] += (expand(quantized[1][channel]) - high[channel] * 255.0).powi(2);
+            }
+        }
+        if shared {
+            error[1] = error[0];
+        }
+        for endpoint in 0..2 {
+            if error[endpoint] < best_error[endpoint] {
+                best_error[endpoint] = error[endpoint];
+                pbits[endpoint] = pbit as u32;
+                stored[endpoint] = quantized[endpoint].map(|value| value >> 1);
+            }
+        }
+    }
+    return (stored, pbits);
+}
+
+/// stored low and high value and the error of the BC7 endpoints that come closest to every
+/// channel value at a fixed index, for single color blocks
+struct SolidEndpoints {
+    /// mode 6 at index 5, per p-bit
+    mode_6: [[(u32, u32, u32); 256]; 2],
+    /// mode 5 at index 1
+    mode_5: [(u32, u32, u32); 256],
+}
+
+/// Finds the first low and high value, in order, that interpolates closest to each channel value.
+fn solid_endpoint_table(expand: impl Fn(u32) -> u32, weight: u32) -> [(u32, u32, u32); 256] {
+    // the error only depends on the interpolated value, so the first pair reaching each value
+    // is enough
+    let mut reached: [Option<(u32, u32)>; 256] = [None; 256];
+    for low in 0..128 {
+        for high in 0..128 {
+            let value = (expand(low) * (64 - weight) + expand(high) * weight + 32) >> 6;
+            reached[value as usize].get_or_insert((low, high));
+        }
+    }
+    let mut table = [(0, 0, 0); 256];
+    for (channel, entry) in table.iter_mut().enumerate() {
+        let mut best: Option<(u32, u32, u32)> = None;
+        for (value, pair) in reached.iter().enumerate() {
+            let Some((low, high)) = *pair else {
+                continue;
+            };
+            let error = (value as i32 - channel as i32).pow(2) as u32;
+            let better = best.is_none_or(|(best_low, best_high, best_error)| {
+                error < best_error || (error == best_error && (low, high) < (best_low, best_high))
+            });
+            if better {
+                best = Some((low, high, error));
+            }
+        }
+        *entry = best.unwrap();
+    }
+    return table;
+}
+
+fn solid_endpoints() -> &'static SolidEndpoints {
+    static TABLES: OnceLock<SolidEndpoints> = OnceLock::new();
+    return TABLES.get_or_init(|| SolidEndpoints {
+        mode_6: [0, 1].map(|pbit| solid_endpoint_table(|value| (value << 1) | pbit, 21)),
+        mode_5: solid_endpoint_table(|value| (value << 1) | (value >> 6), 21),
+    });
+}
+
+fn bc7_solid_block(color: [u8; 4]) -> Bc7Block {
+    let tables = solid_endpoints();
+    let mut block = Bc7Block {
+        mode: 6,
+        partition: 0,
+        rotation: 0,
+        index_selection: 0,
+        stored: [[[0; 4]; 2]; 3],
+        pbits: [[0; 2]; 3],
+        indices: [5; 16],
+        secondary_indices: [0; 16],
+        error: 0.0,
+    };
+    let errors = [0, 1].map(|pbit| -> u32 {
+        return color
+            .iter()
+            .map(|channel| tables.mode_6[pbit][*channel as usize].2)
+            .sum();
+    });
+    if errors[0] > 0 && errors[1] > 0 {
+        // mode 5 stores alpha exactly and has one more bit per color channel
+        block.mode = 5;
+        block.indices = [1; 16];
+        for (channel, value) in color.iter().take(3).enumerate() {
+            let (low, high, _) = tables.mode_5[*value as usize];
+            block.stored[0][0][channel] = low;
+            block.stored[0][1][channel] = high;
+        }
+        block.stored[0][0][3] = color[3] as u32;
+        block.stored[0][1][3] = color[3] as u32;
+        return block;
+    }
+    let pbit = (errors[1] < errors[0]) as usize;
+    for (channel, value) in color.iter().enumerate() {
+        let (low, high, _) = tables.mode_6[pbit][*value as usize];
+        block.stored[0][0][channel] = low;
+        block.stored[0][1][channel] = high;
+    }
+    block.pbits[0] = [pbit as u32; 2];
+    return block;
+}
+
+/// Maps the UASTC endpoints and weights straight to the BC7 mode with the same partition and
+/// index precision, no pixels are decoded.
+fn transcode_to_bc7(block: &UastcBlock) -> [u8; 16] {
+    if block.mode == SOLID_MODE {
+        return bc7_solid_block(block.solid_color).pack();
+    }
+    let desc = &MODES[block.mode];
+    let unquantized =
+        |index: usize| unquantize_endpoint(block.endpoints[index], desc.endpoint_range);
+    let normalized = |index: usize| unquantized(index) as f32 / 255.0;
+    let to_5_bits = |index: usize| (unquantized(index) as u32 * 31 + 127) / 255;
+    let to_7_bits = |value: u32| (value * 127 + 127) / 255;
+
+    let mut bc7 = Bc7Block {
+        mode: 6,
+        partition: 0,
+        rotation: 0,
+        index_selection: 0,
+        stored: [[[0; 4]; 2]; 3],
+        pbits: [[0; 2]; 3],
+        indices: [0; 16],
+        secondary_indices: [0; 16],
+        error: 0.0,
+    };
+    for pixel in 0..16 {
+        bc7.indices[pixel] = block.weights[pixel];
+    }
+    match block.mode {
+        0 | 5 | 10 | 12 | 14 | 15 | 18 => {
+            let (low, high) = if desc.components == 2 {
+                let (low, high) = (normalized(0), normalized(1));
+                (
+                    [low, low, low, normalized(2)],
+                    [high, high, high, normalized(3)],
+                )
+            } else {
+                let alpha = |index: usize| {
+                    return if desc.components == 4 {
+                        normalized(index)
+                    } else {
+                        1.0
+                    };
+                };
+                (
+                    [normalized(0), normalized(2), normalized(4), alpha(6)],
+                    [normalized(1), normalized(3), normalized(5), alpha(7)],
+                )
+            };
+            let components = if desc.components == 2 {
+                4
+            } else {
+                desc.components
+            };
+            let (stored, pbits) = bc7_pbit_endpoints(&low, &high, components, 7, false);
+            bc7.stored[0] = stored;
+            bc7.pbits[0] = pbits;
+            if desc.components == 3 {
+                bc7.stored[0][0][3] = 127;
+                bc7.stored[0][1][3] = 127;
+            }
+            // spread the weights over the 4 bit indices
+            let indices: &[u8] = match block.mode {
+                18 => &[
+                    0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 6, 7, 8, 9, 9, 9, 10, 10, 11, 11, 12,
+                    12, 13, 13, 14, 14, 15, 15,
+                ],
+                14 => &[0, 5, 10, 15],
+                5 | 12 => &[0, 2, 4, 6, 9, 11, 13, 15],
+                _ => &[],
+            };
+            if !indices.is_empty() {
+                for index in bc7.indices.iter_mut() {
+                    *index = indices[*index as usize];
+                }
+            }
+        }
+        1 | 4 => {
+            bc7.mode = 3;
+            let (partition, swapped) = if block.mode == 4 {
+                BC7_PATTERNS_2[block.pattern]
+            } else {
+                (0, false)
+            };
+            bc7.partition = partition;
+            for subset in 0..2 {
+                // mode 1 has a single subset and fills both
+                let values = if block.mode == 4 { subset * 6 } else { 0 };
+                let low = [0, 2, 4].map(|channel| normalized(values + channel));
+                let high = [1, 3, 5].map(|channel| normalized(values + channel));
+                let (stored, pbits) = bc7_pbit_endpoints(
+                    &[low[0], low[1], low[2], 1.0],
+                    &[high[0], high[1], high[2], 1.0],
+                    3,
+                    7,
+                    false,
+                );
+                let target = if swapped { 1 - subset } else { subset };
+                bc7.stored[target] = stored;
+                bc7.pbits[target] = pbits;
+            }
+        }
+        2 => {
+            bc7.mode = 1;
+            let (partition, swapped) = BC7_PATTERNS_2[block.pattern];
+            bc7.partition = partition;
+            for subset in 0..2 {
+                let low = [0, 2, 4].map(|channel| normalized(subset * 6 + channel));
+                let high = [1, 3, 5].map(|channel| normalized(subset * 6 + channel));
+                let (stored, pbits) = bc7_pbit_endpoints(
+                    &[low[0], low[1], low[2], 1.0],
+                    &[high[0], high[1], high[2], 1.0],
+                    3,
+                    6,
+                    true,
+                );
+                let target = if swapped { 1 - subset } else { subset };
+                bc7.stored[target] = stored;
+                bc7.pbits[target] = pbits;
+            }
+        }
+        3 => {
+            bc7.mode = 2;
+            let (partition, permutation) = BC7_PATTERNS_3[block.pattern];
+            bc7.partition = partition;
+            for (subset, &target) in BC7_SUBSET_PERMUTATIONS[permutation].iter().enumerate() {
+                for channel in 0..3 {
+                    for endpoint in 0..2 {
+                        bc7.stored[target][endpoint][channel] =
+                            to_5_bits(subset * 6 + channel * 2 + endpoint);
+                    }
+                }
+            }
+        }
+        7 => {
+            bc7.mode = 2;
+            let (partition, merged) = BC7_PATTERNS_2_MERGED[block.pattern];
+            bc7.partition = partition;
+            for target in 0..3 {
+                // the two BC7 subsets that share an ASTC subset
+                let mut subset = match merged >> 1 {
+                    0 => (target > 1) as usize,
+                    1 => (target != 0) as usize,
+                    _ => (target == 1) as usize,
+                };
+                if merged & 1 != 0 {
+                    subset = 1 - subset;
+                }
+                for channel in 0..3 {
+                    for endpoint in 0..2 {
+                        bc7.stored[target][endpoint][channel] =
+                            to_5_bits(subset * 6 + channel * 2 + endpoint);
+                    }
+                }
+            }
+        }
+        6 | 11 | 13 | 17 => {
+            // BC7 swaps the rotated channel with alpha and uses the alpha indices for it
+            bc7.mode = 5;
+            bc7.rotation = (block.plane_channel as u32 + 1) & 3;
+            if desc.components == 2 {
+                for endpoint in 0..2 {
+                    let luminance = to_7_bits(unquantized(endpoint) as u32);
+                    bc7.stored[0][endpoint] = [
+                        luminance,
+                        luminance,
+                        luminance,
+                        unquantized(2 + endpoint) as u32,
+                    ];
+                }
+            } else {
+                for channel in 0..4 {
+                    let target = if channel == block.plane_channel {
+                        3
+                    } else if channel == 3 {
+                        block.plane_channel
+                    } else {
+                        channel
+                    };
+                    for endpoint in 0..2 {
+                        let mut value = 255;
+                        if channel < desc.components {
+                            value = unquantized(channel * 2 + endpoint) as u32;
+                        }
+                        if target < 3 {
+                            value = to_7_bits(value);
+                        }
+                        bc7.stored[0][endpoint][target] = value;
+                    }
+                }
+            }
+            for pixel in 0..16 {
+                let mut weights = [block.weights[pixel * 2], block.weights[pixel * 2 + 1]];
+                if block.mode == 13 {
+                    // one bit weights become the ends of the two bit indices
+                    weights = weights.map(|weight| weight * 3);
+                }
+                bc7.indices[pixel] = weights[0];
+                bc7.secondary_indices[pixel] = weights[1];
+            }
+        }
+        _ => {
+            // modes 9 and 16
+            bc7.mode = 7;
+            let (partition, swapped) = BC7_PATTERNS_2[block.pattern];
+            bc7.partition = partition;
+            for subset in 0..2 {
+                let (low, high) = if desc.components == 2 {
+                    let values = subset * 4;
+                    let (low, high) = (normalized(values), normalized(values + 1));
+                    (
+                        [low, low, low, normalized(values + 2)],
+                        [high, high, high, normalized(values + 3)],
+                    )
+                } else {
+                    let values = subset * 8;
+                    (
+                        [0, 2, 4, 6].map(|channel| normalized(values + channel)),
+                        [1, 3, 5, 7].map(|channel| normalized(values + channel)),
+                    )
+                };
+                let (stored, pbits) = bc7_pbit_endpoints(&low, &high, 4, 5, false);
+                let target = if swapped { 1 - subset } else { subset };
+                bc7.stored[target] = stored;
+                bc7.pbits[target] = pbits;
+            }
+        }
+    }
+    bc7.fix_anchors();
+    return bc7.pack();
+}
+
+/// the four colors of an ETC1 subblock, sorted from darkest to brightest
+fn etc1_subblock_colors(block: &[u8; 8], subblock: usize) -> [[i32; 3]; 4] {
+    let diff = block[3] & 2 != 0;
+    let mut base = [0i32; 3];
+    for (channel, value) in base.iter_mut().enumerate() {
+        let byte = block[channel] as i32;
+        *value = if diff {
+            let mut color = byte >> 3;
+            if subblock == 1 {
+                color += ((byte & 7) << 29) >> 29;
+            }
+            (color << 3) | (color >> 2)
+        } else if subblock == 0 {
+            (byte >> 4) * 17
+        } else {
+            (byte & 15) * 17
+        };
+    }
+    let table = &ETC1_INTENSITIES[(block[3] >> if subblock == 0 { 5 } else { 2 }) as usize & 7];
+    return table.map(|modifier| base.map(|channel| (channel + modifier).clamp(0, 255)));
+}
+
+fn etc1_bias(color: [u32; 3], bias: u32, limit: u32, subblock: usize) -> [u32; 3] {
+    let mut result = [0; 3];
+    for channel in 0..3 {
+        let delta = match bias {
+            2 | 5 | 6 | 7 | 8 | 11 | 15 | 18 | 19 | 20 | 21 | 24 => {
+                // nudges a single channel of one subblock
+                let (bias_subblock, bias_channel, delta) = match bias {
+                    2 => (0, 0, -1),
+                    5 => (0, 1, -1),
+                    6 => (0, 2, -1),
+                    7 => (0, 0, 1),
+                    11 => (0, 1, 1),
+                    15 => (0, 2, 1),
+                    18 => (1, 0, -1),
+                    19 => (1, 1, -1),
+                    20 => (1, 2, -1),
+                    21 => (1, 0, 1),
+                    24 => (1, 1, 1),
+                    _ => (1, 2, 1),
+                };
+                if subblock == bias_subblock && channel == bias_channel {
+                    delta
+                } else {
+                    0
+                }
+            }
+            10 => -2,
+            27 => [-1, 0][subblock],
+            28 => [1, -1][subblock],
+            29 => [0, 1][subblock],
+            30 => [0, -1][subblock],
+            31 => [1, 0][subblock],
+            _ => ((bias / 3u32.pow(channel as u32)) % 3) as i32 - 1,
+        };
+        let value = color[channel] as i32;
+        let limit = limit as i32;
+        result[channel] = if value == 0 {
+            if delta == -2 {
+                3
+            } else {
+                delta + 1
+            }
+        } else if value == limit {
+            value + delta - 1
+        } else {
+            let biased = value + delta;
+            if biased < 0 || biased > limit {
+                value - delta
+            } else {
+                biased
+            }
+        } as u32;
+    }
+    return result;
+}
+
+/// ETC1 block from the decoded pixels using the encoder's flip, diff, intensity and bias hints
+fn transcode_to_etc1(block: &UastcBlock, pixels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let hints = &block.etc1;
+    let mut out = [0u8; 8];
+    if block.mode == SOLID_MODE {
+        out[3] =
+            ((hints.diff as u32) << 1 | hints.intensity[0] << 5 | hints.intensity[0] << 2) as u8;
+        for (channel, &color) in hints.solid_color.iter().enumerate() {
+            out[channel] = if hints.diff {
+                color << 3
+            } else {
+                color | color << 4
+            } as u8;
+        }
+        out[4..].copy_from_slice(&ETC1_SOLID_SELECTORS[hints.solid_selector]);
+        return out;
+    }
+
+    out[3] = (hints.flip as u32
+        | (hints.diff as u32) << 1
+        | hints.intensity[0] << 5
+        | hints.intensity[1] << 2) as u8;
+    let limit = if hints.diff { 31 } else { 15 };
+    // pixels of subblock 1 are the right half, or the bottom half when flipped
+    let in_subblock = |index: usize, subblock: usize| -> bool {
+        let coordinate = if hints.flip { index >> 2 } else { index & 3 };
+        return (coordinate >> 1) == subblock;
+    };
+    let mut colors = [[0u32; 3]; 2];
+    for (subblock, color) in colors.iter_mut().enumerate() {
+        let mut sum = [0u32; 3];
+        for (index, pixel) in pixels.iter().enumerate() {
+            if in_subblock(index, subblock) {
+                for channel in 0..3 {
+                    sum[channel] += pixel[channel] as u32;
+                }
+            }
+        }
+        *color = sum.map(|total| (total * limit + 1020) / (8 * 255));
+        if MODES[block.mode].etc1_bias {
+            *color = etc1_bias(*color, hints.bias, limit, subblock);
+        }
+    }
+    for channel in 0..3 {
+        out[channel] = if hints.diff {
+            let delta = (colors[1][channel] as i32 - colors[0][channel] as i32).clamp(-4, 3);
+            (colors[0][channel] << 3) as u8 | (delta & 7) as u8
+        } else {
+            (colors[1][channel] | colors[0][channel] << 4) as u8
+        };
+    }
+
+    // pick the closest of the four colors by luma, selectors are stored per column
+    let mut low_bits = 0u32;
+    let mut high_bits = 0u32;
+    for subblock in 0..2 {
+        let lumas = etc1_subblock_colors(&out, subblock)
+            .map(|color| (color[0] * 54 + color[1] * 183 + color[2] * 19) as u32);
+        for (index, pixel) in pixels.iter().enumerate() {
+            if !in_subblock(index, subblock) {
+                continue;
+            }
+            let luma = pixel[0] as u32 * 108 + pixel[1] as u32 * 366 + pixel[2] as u32 * 38;
+            let darker = (luma < lumas[0] + lumas[1]) as usize
+                + (luma < lumas[1] + lumas[2]) as usize
+                + (luma < lumas[2] + lumas[3]) as usize;
+            let selector = [1, 0, 2, 3][darker];
+            let bit = (index & 3) * 4 + (index >> 2);
+            low_bits |= (selector & 1) << bit;
+            high_bits |= (selector >> 1) << bit;
+        }
+    }
+    out[4..6].copy_from_slice(&(high_bits as u16).to_be_bytes());
+    out[6..8].copy_from_slice(&(low_bits as u16).to_be_bytes());
+    return out;
+}
+
+/// EAC alpha block using the encoder's table and multiplier hint
+fn transcode_to_eac_alpha(block: &UastcBlock, pixels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let has_alpha = MODES[block.mode].components != 3;
+    let min = pixels.iter().map(|pixel| pixel[3]).min().unwrap() as i32;
+    let max = pixels.iter().map(|pixel| pixel[3]).max().unwrap() as i32;
+    if block.mode == SOLID_MODE || !has_alpha || min == max {
+        let alpha = if block.mode == SOLID_MODE {
+            block.solid_color[3]
+        } else if has_alpha {
+            min as u8
+        } else {
+            255
+        };
+        // table 13 with a multiplier of 1 and every selector at 4 adds nothing to the base
+        return [alpha, 0x1d, 0x92, 0x49, 0x24, 0x92, 0x49, 0x24];
+    }
+
+    let table = (block.etc2_hint & 15) as usize;
+    let multiplier = (block.etc2_hint >> 4) as i32;
+    let modifiers = &EAC_MODIFIERS[table];
+    let range = (modifiers[7] - modifiers[3]) as f32;
+    let t = -modifiers[3] as f32 / range;
+    let center = (min as f32 + (max - min) as f32 * t).round() as i32;
+    let values = modifiers.map(|modifier| (center + modifier * multiplier).clamp(0, 255));
+
+    let mut selectors = 0u64;
+    for index in 0..16 {
+        // selectors are stored per column
+        let alpha = pixels[(index & 3) * 4 + (index >> 2)][3] as i32;
+        let best = (0..8)
+            .min_by_key(|selector| (values[*selector] - alpha).abs())
+            .unwrap();
+        selectors |= (best as u64) << (45 - index * 3);
+    }
+    let mut out = [0u8; 8];
+    out[0] = center as u8;
+    out[1] = (multiplier << 4) as u8 | table as u8;
+    out[2..].copy_from_slice(&selectors.to_be_bytes()[2..]);
+    return out;
+}
+
+/// true for the block compressed formats `transcode_uastc_block` can write
+pub fn is_uastc_transcode_target(format: ImageFormat) -> bool {
+    return matches!(
+        format,
+        ImageFormat::ASTC_4x4_UNORM
+            | ImageFormat::ASTC_4x4_SRGB
+            | ImageFormat::DXBC7_UNORM
+            | ImageFormat::DXBC7_SRGB
+            | ImageFormat::ETC2_R8G8B8_UNORM
+            | ImageFormat::ETC2_R8G8B8_SRGB
+            | ImageFormat::ETC2_R8G8B8A8_UNORM
+            | ImageFormat::ETC2_R8G8B8A8_SRGB
+    );
+}
+
+/// Transcodes one UASTC block to a 4x4 block of `format` and writes it to the start of `out`.
+///
+/// ASTC keeps the block as is, BC7 maps the endpoints and weights to the matching BC7 mode and
+/// ETC2 follows the ETC1 and EAC hints the encoder stored in the block.
+pub fn transcode_uastc_block(
+    bytes: &[u8; 16],
+    format: ImageFormat,
+    out: &mut [u8],
+) -> ImageResult<()> {
+    let block = unpack_block(bytes)?;
+    match format {
+        ImageFormat::ASTC_4x4_UNORM | ImageFormat::ASTC_4x4_SRGB => {
+            out[..16].copy_from_slice(&transcode_to_astc(&block));
+        }
+        ImageFormat::DXBC7_UNORM | ImageFormat::DXBC7_SRGB => {
+            out[..16].copy_from_slice(&transcode_to_bc7(&block));
+        }
+        ImageFormat::ETC2_R8G8B8_UNORM | ImageFormat::ETC2_R8G8B8_SRGB => {
+            let pixels = decode_block(&block);
+            out[..8].copy_from_slice(&transcode_to_etc1(&block, &pixels));
+        }
+        ImageFormat::ETC2_R8G8B8A8_UNORM | ImageFormat::ETC2_R8G8B8A8_SRGB => {
+            let pixels = decode_block(&block);
+            out[..8].copy_from_slice(&transcode_to_eac_alpha(&block, &pixels));
+            out[8..16].copy_from_slice(&transcode_to_etc1(&block, &pixels));
+        }
+        _ => return Err(ImageError::UnsupportedFormat(format)),
+    }
+    return Ok(());
+}
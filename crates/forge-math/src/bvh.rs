@@ -0,0 +1,412 @@
+use crate::culling::{Aabb, Containment, Frustum};
+use glam::{Vec2, Vec3};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// doesn't need to be unit length, distances along the ray are in multiples of it
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        return Ray { origin, direction };
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        return self.origin + self.direction * t;
+    }
+
+    /// Distance where the ray enters `aabb`, 0 when it starts inside, if that's before `max_t`.
+    pub fn intersect_aabb(&self, aabb: &Aabb, max_t: f32) -> Option<f32> {
+        return slab_test(self.origin, self.direction.recip(), aabb, max_t);
+    }
+
+    /// Distance and barycentrics of the second and third vertices where the ray hits the
+    /// triangle from either side, if that's in `0..max_t`.
+    pub fn intersect_triangle(&self, triangle: &[Vec3; 3], max_t: f32) -> Option<(f32, Vec2)> {
+        // Möller-Trumbore
+        let edge1 = triangle[1] - triangle[0];
+        let edge2 = triangle[2] - triangle[0];
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant == 0.0 {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let offset = self.origin - triangle[0];
+        let u = offset.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = offset.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(q) * inverse;
+        if t < 0.0 || t >= max_t {
+            return None;
+        }
+        return Some((t, Vec2::new(u, v)));
+    }
+}
+
+/// entry distance of a ray with precomputed reciprocal direction, NaNs from 0 * inf compare false
+/// and leave the other axes to decide
+fn slab_test(origin: Vec3, inverse_direction: Vec3, aabb: &Aabb, max_t: f32) -> Option<f32> {
+    let t0 = (aabb.min - origin) * inverse_direction;
+    let t1 = (aabb.max - origin) * inverse_direction;
+    let near = t0.min(t1).max_element().max(0.0);
+    let far = t0.max(t1).min_element().min(max_t);
+    if near > far {
+        return None;
+    }
+    return Some(near);
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct BvhNode {
+    pub bounds: Aabb,
+    /// first primitive of a leaf in `Bvh::primitive_indices`, or the left child of an interior
+    /// node, the right child follows it
+    pub first: u32,
+    /// primitives in a leaf, 0 for interior nodes
+    pub count: u32,
+}
+
+impl BvhNode {
+    pub fn is_leaf(&self) -> bool {
+        return self.count != 0;
+    }
+}
+
+/// Bounding volume hierarchy over primitive bounding boxes, built with the surface area
+/// heuristic. Node 0 is the root.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// primitive index of every leaf slot
+    indices: Vec<u32>,
+    /// bounds of every leaf slot
+    bounds: Vec<Aabb>,
+}
+
+/// centroid bins per axis when looking for a split
+const SAH_BINS: usize = 16;
+/// cost of visiting a node relative to testing a primitive
+const TRAVERSAL_COST: f32 = 1.0;
+/// leaves are split even when SAH says otherwise once they are this big
+const MAX_LEAF_SIZE: u32 = 16;
+
+fn surface_area(aabb: &Aabb) -> f32 {
+    let size = (aabb.max - aabb.min).max(Vec3::ZERO);
+    return 2.0 * (size.x * size.y + size.y * size.z + size.z * size.x);
+}
+
+fn empty_aabb() -> Aabb {
+    return Aabb::from_points(&[]);
+}
+
+impl Bvh {
+    /// Builds a hierarchy over `bounds`, primitives are identified by their index in it.
+    pub fn build(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity((bounds.len() * 2).max(1)),
+            indices: (0..bounds.len() as u32).collect(),
+            bounds: bounds.to_vec(),
+        };
+        bvh.nodes.push(BvhNode {
+            bounds: empty_aabb(),
+            first: 0,
+            count: bounds.len() as u32,
+        });
+        if bounds.is_empty() {
+            return bvh;
+        }
+        let centroids: Vec<Vec3> = bounds.iter().map(|aabb| aabb.center()).collect();
+        let mut pending = vec![0];
+        while let Some(node) = pending.pop() {
+            bvh.update_bounds(node);
+            if let Some(middle) = bvh.split(node, &centroids) {
+                let BvhNode { first, count, .. } = bvh.nodes[node];
+                let left = bvh.nodes.len();
+                bvh.nodes.push(BvhNode {
+                    bounds: empty_aabb(),
+                    first,
+                    count: middle - first,
+                });
+                bvh.nodes.push(BvhNode {
+                    bounds: empty_aabb(),
+                    first: middle,
+                    count: first + count - middle,
+                });
+                bvh.nodes[node].first = left as u32;
+                bvh.nodes[node].count = 0;
+                pending.push(left);
+                pending.push(left + 1);
+            }
+        }
+        // leaf slots are final, store their bounds in the same order
+        bvh.bounds = bvh
+            .indices
+            .iter()
+            .map(|index| bounds[*index as usize])
+            .collect();
+        return bvh;
+    }
+
+    fn update_bounds(&mut self, node: usize) {
+        let BvhNode { first, count, .. } = self.nodes[node];
+        let mut result = empty_aabb();
+        for index in &self.indices[first as usize..(first + count) as usize] {
+            result = result.union(&self.bounds[*index as usize]);
+        }
+        self.nodes[node].bounds = result;
+    }
+
+    /// partitions the primitives of a leaf at the cheapest SAH split and returns where the right
+    /// half starts, or `None` when the leaf is cheaper
+    fn split(&mut self, node: usize, centroids: &[Vec3]) -> Option<u32> {
+        let BvhNode {
+            bounds,
+            first,
+            count,
+        } = self.nodes[node];
+        if count <= 1 {
+            return None;
+        }
+        let range = first as usize..(first + count) as usize;
+        let mut centroid_bounds = empty_aabb();
+        for index in &self.indices[range.clone()] {
+            let centroid = centroids[*index as usize];
+            centroid_bounds = centroid_bounds.union(&Aabb::new(centroid, centroid));
+        }
+        let extent = centroid_bounds.max - centroid_bounds.min;
+
+        let bin_of = |centroid: Vec3, axis: usize| -> usize {
+            let position = (centroid[axis] - centroid_bounds.min[axis]) / extent[axis];
+            return ((position * SAH_BINS as f32) as usize).min(SAH_BINS - 1);
+        };
+        // axis and the last bin of the left half
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut bin_bounds = [empty_aabb(); SAH_BINS];
+            let mut bin_counts = [0u32; SAH_BINS];
+            for index in &self.indices[range.clone()] {
+                let bin = bin_of(centroids[*index as usize], axis);
+                bin_bounds[bin] = bin_bounds[bin].union(&self.bounds[*index as usize]);
+                bin_counts[bin] += 1;
+            }
+            // sweep from the right to get the cost of every right half, then from the left
+            let mut right_costs = [0.0; SAH_BINS];
+            let mut right = (empty_aabb(), 0);
+            for bin in (1..SAH_BINS).rev() {
+                right = (right.0.union(&bin_bounds[bin]), right.1 + bin_counts[bin]);
+                right_costs[bin - 1] = surface_area(&right.0) * right.1 as f32;
+            }
+            let mut left = (empty_aabb(), 0);
+            for bin in 0..SAH_BINS - 1 {
+                left = (left.0.union(&bin_bounds[bin]), left.1 + bin_counts[bin]);
+                let cost = surface_area(&left.0) * left.1 as f32 + right_costs[bin];
+                if left.1 != 0 && left.1 != count && best.is_none_or(|best| cost < best.0) {
+                    best = Some((cost, axis, bin));
+                }
+            }
+        }
+
+        let leaf_cost = count as f32;
+        let split_cost = best.map(|best| TRAVERSAL_COST + best.0 / surface_area(&bounds));
+        let middle = match (best, split_cost) {
+            (Some((_, axis, last_bin)), Some(cost))
+                if cost < leaf_cost || count > MAX_LEAF_SIZE =>
+            {
+                let indices = &mut self.indices[range];
+                let mut middle = 0;
+                for slot in 0..indices.len() {
+                    if bin_of(centroids[indices[slot] as usize], axis) <= last_bin {
+                        indices.swap(slot, middle);
+                        middle += 1;
+                    }
+                }
+                middle as u32
+            }
+            // every centroid in the same spot, split in the middle so leaves stay small
+            (None, _) if count > MAX_LEAF_SIZE => count / 2,
+            _ => return None,
+        };
+        return Some(first + middle);
+    }
+
+    pub fn nodes(&self) -> &[BvhNode] {
+        return &self.nodes;
+    }
+
+    /// Primitive indices referenced by the leaves.
+    pub fn primitive_indices(&self) -> &[u32] {
+        return &self.indices;
+    }
+
+    /// Closest primitive along `ray` before `max_t`. `intersect` tests the ray against a
+    /// primitive and returns the hit distance, it's only called for primitives whose bounds
+    /// the ray enters before the closest hit so far.
+    pub fn intersect_ray(
+        &self,
+        ray: &Ray,
+        max_t: f32,
+        mut intersect: impl FnMut(u32, f32) -> Option<f32>,
+    ) -> Option<(u32, f32)> {
+        let inverse_direction = ray.direction.recip();
+        let mut closest: Option<(u32, f32)> = None;
+        let mut max_t = max_t;
+        if self.indices.is_empty()
+            || slab_test(ray.origin, inverse_direction, &self.nodes[0].bounds, max_t).is_none()
+        {
+            return None;
+        }
+        let mut stack = vec![0u32];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node as usize];
+            if node.is_leaf() {
+                for slot in node.first..node.first + node.count {
+                    let slot = slot as usize;
+                    if slab_test(ray.origin, inverse_direction, &self.bounds[slot], max_t).is_none()
+                    {
+                        continue;
+                    }
+                    if let Some(t) = intersect(self.indices[slot], max_t) {
+                        if t < max_t {
+                            max_t = t;
+                            closest = Some((self.indices[slot], t));
+                        }
+                    }
+                }
+                continue;
+            }
+            let children = [node.first, node.first + 1].map(|child| {
+                let bounds = &self.nodes[child as usize].bounds;
+                return (
+                    child,
+                    slab_test(ray.origin, inverse_direction, bounds, max_t),
+                );
+            });
+            // push the further child first so the nearer one is visited next
+            match children {
+                [(near, Some(near_t)), (far, Some(far_t))] => {
+                    let (near, far) = if near_t <= far_t {
+                        (near, far)
+                    } else {
+                        (far, near)
+                    };
+                    stack.push(far);
+                    stack.push(near);
+                }
+                [(child, Some(_)), _] | [_, (child, Some(_))] => stack.push(child),
+                _ => {}
+            }
+        }
+        return closest;
+    }
+
+    /// Calls `visit` with every primitive whose bounds intersect `frustum`.
+    pub fn query_frustum(&self, frustum: &Frustum, mut visit: impl FnMut(u32)) {
+        if self.indices.is_empty() {
+            return;
+        }
+        let mut stack = vec![0u32];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node as usize];
+            let slots = node.first as usize..(node.first + node.count) as usize;
+            match frustum.classify_aabb(&node.bounds) {
+                Containment::Outside => {}
+                Containment::Inside => self.visit_all(node, &mut visit),
+                Containment::Intersecting if node.is_leaf() => {
+                    for slot in slots {
+                        if frustum.intersects_aabb(&self.bounds[slot]) {
+                            visit(self.indices[slot]);
+                        }
+                    }
+                }
+                Containment::Intersecting => {
+                    stack.push(node.first + 1);
+                    stack.push(node.first);
+                }
+            }
+        }
+    }
+
+    /// calls `visit` with every primitive under `node`
+    fn visit_all(&self, node: &BvhNode, visit: &mut impl FnMut(u32)) {
+        if node.is_leaf() {
+            let slots = node.first as usize..(node.first + node.count) as usize;
+            self.indices[slots].iter().for_each(|index| visit(*index));
+            return;
+        }
+        self.visit_all(&self.nodes[node.first as usize], visit);
+        self.visit_all(&self.nodes[node.first as usize + 1], visit);
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct RayHit {
+    /// index of the triangle, its first index is at `3 * triangle`
+    pub triangle: u32,
+    pub t: f32,
+    /// barycentrics of the second and third vertices
+    pub barycentrics: Vec2,
+}
+
+/// Bvh over the triangles of an indexed triangle list.
+#[derive(Clone, Debug)]
+pub struct TriangleBvh {
+    bvh: Bvh,
+    positions: Vec<Vec3>,
+    indices: Vec<u32>,
+}
+
+impl TriangleBvh {
+    pub fn new(positions: &[Vec3], indices: &[u32]) -> TriangleBvh {
+        let mut result = TriangleBvh {
+            bvh: Bvh::build(&[]),
+            positions: positions.to_vec(),
+            indices: indices.to_vec(),
+        };
+        let bounds: Vec<Aabb> = (0..indices.len() as u32 / 3)
+            .map(|triangle| Aabb::from_points(&result.triangle(triangle)))
+            .collect();
+        result.bvh = Bvh::build(&bounds);
+        return result;
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        return &self.bvh;
+    }
+
+    pub fn triangle(&self, triangle: u32) -> [Vec3; 3] {
+        let first = triangle as usize * 3;
+        return [0, 1, 2].map(|vertex| self.positions[self.indices[first + vertex] as usize]);
+    }
+
+    /// Closest triangle hit by `ray` before `max_t`, from either side.
+    pub fn intersect_ray(&self, ray: &Ray, max_t: f32) -> Option<RayHit> {
+        let mut barycentrics = Vec2::ZERO;
+        let (triangle, t) = self.bvh.intersect_ray(ray, max_t, |triangle, max_t| {
+            let (t, hit_barycentrics) = ray.intersect_triangle(&self.triangle(triangle), max_t)?;
+            barycentrics = hit_barycentrics;
+            return Some(t);
+        })?;
+        return Some(RayHit {
+            triangle,
+            t,
+            barycentrics,
+        });
+    }
+
+    /// Calls `visit` with every triangle whose bounds intersect `frustum`.
+    pub fn query_frustum(&self, frustum: &Frustum, visit: impl FnMut(u32)) {
+        self.bvh.query_frustum(frustum, visit);
+    }
+}
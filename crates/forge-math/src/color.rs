@@ -0,0 +1,140 @@
+use glam::{Mat3, Vec2, Vec3};
+
+/// Decodes an sRGB encoded value to linear.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+    return ((value + 0.055) / 1.055).powf(2.4);
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        return value * 12.92;
+    }
+    return 1.055 * value.powf(1.0 / 2.4) - 0.055;
+}
+
+/// Inverse of the Rec.709 camera transfer function.
+pub fn rec709_to_linear(value: f32) -> f32 {
+    if value < 0.081 {
+        return value / 4.5;
+    }
+    return ((value + 0.099) / 1.099).powf(1.0 / 0.45);
+}
+
+pub fn linear_to_rec709(value: f32) -> f32 {
+    if value < 0.018 {
+        return value * 4.5;
+    }
+    return 1.099 * value.powf(0.45) - 0.099;
+}
+
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+/// brightness the PQ curve encodes as 1
+pub const PQ_MAX_NITS: f32 = 10000.0;
+
+/// Encodes an absolute luminance in nits with the SMPTE ST 2084 (PQ) curve.
+pub fn nits_to_pq(nits: f32) -> f32 {
+    let y = (nits / PQ_MAX_NITS).clamp(0.0, 1.0).powf(PQ_M1);
+    return ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2);
+}
+
+/// Decodes a PQ value to an absolute luminance in nits.
+pub fn pq_to_nits(value: f32) -> f32 {
+    let e = value.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    let y = ((e - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * e)).powf(1.0 / PQ_M1);
+    return y * PQ_MAX_NITS;
+}
+
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 1.0 - 4.0 * HLG_A;
+const HLG_C: f32 = 0.559_910_7;
+
+/// Hybrid log-gamma encoding of a relative scene light value in 0..1.
+pub fn linear_to_hlg(value: f32) -> f32 {
+    let value = value.max(0.0);
+    if value <= 1.0 / 12.0 {
+        return (3.0 * value).sqrt();
+    }
+    return HLG_A * (12.0 * value - HLG_B).ln() + HLG_C;
+}
+
+pub fn hlg_to_linear(value: f32) -> f32 {
+    let value = value.max(0.0);
+    if value <= 0.5 {
+        return value * value / 3.0;
+    }
+    return (((value - HLG_C) / HLG_A).exp() + HLG_B) / 12.0;
+}
+
+/// RGB color spaces by their primaries, all with a D65 white point
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ColorPrimaries {
+    /// also used by sRGB
+    Rec709,
+    Rec2020,
+    DisplayP3,
+}
+
+/// xy chromaticity of the D65 white point
+const D65: [f32; 2] = [0.3127, 0.3290];
+
+impl ColorPrimaries {
+    /// CIE xy chromaticities of red, green and blue
+    pub fn chromaticities(self) -> [Vec2; 3] {
+        return match self {
+            ColorPrimaries::Rec709 => [
+                Vec2::new(0.640, 0.330),
+                Vec2::new(0.300, 0.600),
+                Vec2::new(0.150, 0.060),
+            ],
+            ColorPrimaries::Rec2020 => [
+                Vec2::new(0.708, 0.292),
+                Vec2::new(0.170, 0.797),
+                Vec2::new(0.131, 0.046),
+            ],
+            ColorPrimaries::DisplayP3 => [
+                Vec2::new(0.680, 0.320),
+                Vec2::new(0.265, 0.690),
+                Vec2::new(0.150, 0.060),
+            ],
+        };
+    }
+
+    /// Matrix from linear RGB in these primaries to CIE XYZ.
+    pub fn rgb_to_xyz(self) -> Mat3 {
+        let xyz = |xy: Vec2| Vec3::new(xy.x / xy.y, 1.0, (1.0 - xy.x - xy.y) / xy.y);
+        let [red, green, blue] = self.chromaticities().map(xyz);
+        let primaries = Mat3::from_cols(red, green, blue);
+        // scale the primaries so white adds up to the white point
+        let scale = primaries.inverse() * xyz(Vec2::from(D65));
+        return Mat3::from_cols(red * scale.x, green * scale.y, blue * scale.z);
+    }
+
+    pub fn xyz_to_rgb(self) -> Mat3 {
+        return self.rgb_to_xyz().inverse();
+    }
+}
+
+/// Matrix that converts linear RGB from the `from` to the `to` primaries.
+pub fn primaries_conversion(from: ColorPrimaries, to: ColorPrimaries) -> Mat3 {
+    if from == to {
+        return Mat3::IDENTITY;
+    }
+    return to.xyz_to_rgb() * from.rgb_to_xyz();
+}
+
+/// Encodes linear color for an HDR10 swap chain (Rec.2020 primaries, PQ), where 1.0 is
+/// `white_nits` bright. Pack the result with `packing::pack_a2b10g10r10_unorm`.
+pub fn encode_hdr10(color: Vec3, primaries: ColorPrimaries, white_nits: f32) -> Vec3 {
+    let rec2020 = primaries_conversion(primaries, ColorPrimaries::Rec2020) * color;
+    // colors outside the Rec.2020 gamut have negative components
+    let nits = rec2020.max(Vec3::ZERO) * white_nits;
+    return Vec3::new(nits_to_pq(nits.x), nits_to_pq(nits.y), nits_to_pq(nits.z));
+}
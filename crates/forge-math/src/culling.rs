@@ -0,0 +1,361 @@
+use glam::{Mat3, Mat4, Vec3, Vec4};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+/// box rotated by `axes`, whose columns are the unit box axes
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Obb {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub axes: Mat3,
+}
+
+/// cone from `apex` along the unit `direction`, with a flat base `height` away
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Cone {
+    pub apex: Vec3,
+    pub direction: Vec3,
+    pub height: f32,
+    /// radius of the base
+    pub radius: f32,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Sphere {
+        return Sphere { center, radius };
+    }
+
+    pub fn intersects_sphere(&self, other: &Sphere) -> bool {
+        let radius = self.radius + other.radius;
+        return self.center.distance_squared(other.center) <= radius * radius;
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let closest = self.center.clamp(aabb.min, aabb.max);
+        return closest.distance_squared(self.center) <= self.radius * self.radius;
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        return Aabb { min, max };
+    }
+
+    pub fn from_center_extents(center: Vec3, half_extents: Vec3) -> Aabb {
+        return Aabb {
+            min: center - half_extents,
+            max: center + half_extents,
+        };
+    }
+
+    /// Smallest box around `points`, an inverted empty box when there are none.
+    pub fn from_points(points: &[Vec3]) -> Aabb {
+        let mut result = Aabb {
+            min: Vec3::splat(f32::MAX),
+            max: Vec3::splat(f32::MIN),
+        };
+        for point in points {
+            result.min = result.min.min(*point);
+            result.max = result.max.max(*point);
+        }
+        return result;
+    }
+
+    pub fn center(&self) -> Vec3 {
+        return (self.min + self.max) * 0.5;
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        return (self.max - self.min) * 0.5;
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        return Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        };
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        return point.cmpge(self.min).all() && point.cmple(self.max).all();
+    }
+
+    pub fn intersects_aabb(&self, other: &Aabb) -> bool {
+        return self.min.cmple(other.max).all() && other.min.cmple(self.max).all();
+    }
+
+    /// Box around this box after an affine `transform`.
+    pub fn transform(&self, transform: &Mat4) -> Aabb {
+        let center = transform.transform_point3(self.center());
+        let half_extents = self.half_extents();
+        let extents = transform.x_axis.truncate().abs() * half_extents.x
+            + transform.y_axis.truncate().abs() * half_extents.y
+            + transform.z_axis.truncate().abs() * half_extents.z;
+        return Aabb::from_center_extents(center, extents);
+    }
+}
+
+impl Obb {
+    pub fn from_aabb(aabb: &Aabb, transform: &Mat4) -> Obb {
+        // keep the scale in the extents so the axes stay unit length
+        let columns =
+            [transform.x_axis, transform.y_axis, transform.z_axis].map(|column| column.truncate());
+        let scale = Vec3::new(
+            columns[0].length(),
+            columns[1].length(),
+            columns[2].length(),
+        );
+        return Obb {
+            center: transform.transform_point3(aabb.center()),
+            half_extents: aabb.half_extents() * scale,
+            axes: Mat3::from_cols(
+                columns[0] / scale.x,
+                columns[1] / scale.y,
+                columns[2] / scale.z,
+            ),
+        };
+    }
+}
+
+/// Planes whose positive side is inside, normalized so `dot(xyz, p) + w` is a distance.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Frustum {
+    /// left, right, bottom, top, near and far
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a Vulkan view projection matrix, with depth in 0..1 either way
+    /// round. The planes are in the space the matrix transforms from.
+    pub fn from_view_projection(view_projection: &Mat4) -> Frustum {
+        let rows = [0, 1, 2, 3].map(|index| view_projection.row(index));
+        let mut planes = [
+            rows[3] + rows[0],
+            rows[3] - rows[0],
+            rows[3] + rows[1],
+            rows[3] - rows[1],
+            rows[2],
+            rows[3] - rows[2],
+        ];
+        for plane in planes.iter_mut() {
+            // an infinite far plane has no normal and never culls
+            let length = plane.truncate().length();
+            if length > 0.0 {
+                *plane /= length;
+            }
+        }
+        return Frustum { planes };
+    }
+
+    fn distance(plane: Vec4, point: Vec3) -> f32 {
+        return plane.truncate().dot(point) + plane.w;
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        return self
+            .planes
+            .iter()
+            .all(|plane| Frustum::distance(*plane, point) >= 0.0);
+    }
+
+    pub fn classify_sphere(&self, sphere: &Sphere) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in &self.planes {
+            let distance = Frustum::distance(*plane, sphere.center);
+            if distance < -sphere.radius {
+                return Containment::Outside;
+            }
+            if distance < sphere.radius {
+                result = Containment::Intersecting;
+            }
+        }
+        return result;
+    }
+
+    pub fn classify_aabb(&self, aabb: &Aabb) -> Containment {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        let mut result = Containment::Inside;
+        for plane in &self.planes {
+            let distance = Frustum::distance(*plane, center);
+            let radius = plane.truncate().abs().dot(half_extents);
+            if distance < -radius {
+                return Containment::Outside;
+            }
+            if distance < radius {
+                result = Containment::Intersecting;
+            }
+        }
+        return result;
+    }
+
+    // the tests below are conservative, objects near a frustum corner can pass without touching
+    // it
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        return self
+            .planes
+            .iter()
+            .all(|plane| Frustum::distance(*plane, sphere.center) >= -sphere.radius);
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        return self.classify_aabb(aabb) != Containment::Outside;
+    }
+
+    pub fn intersects_obb(&self, obb: &Obb) -> bool {
+        return self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // projection of the box onto the plane normal
+            let radius = (obb.axes.transpose() * normal).abs().dot(obb.half_extents);
+            return Frustum::distance(*plane, obb.center) >= -radius;
+        });
+    }
+
+    pub fn intersects_cone(&self, cone: &Cone) -> bool {
+        let base = cone.apex + cone.direction * cone.height;
+        return self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // the point of the base rim furthest in front of the plane
+            let across = normal - cone.direction * normal.dot(cone.direction);
+            let rim = Frustum::distance(*plane, base) + cone.radius * across.length();
+            return Frustum::distance(*plane, cone.apex) >= 0.0 || rim >= 0.0;
+        });
+    }
+
+    /// Tests every sphere of `spheres` and writes whether it's visible to `visible`.
+    pub fn cull_spheres(&self, spheres: &SphereBatch, visible: &mut [bool]) {
+        let count = spheres.radius.len();
+        assert!(visible.len() >= count);
+        let lanes = [
+            &spheres.center_x,
+            &spheres.center_y,
+            &spheres.center_z,
+            &spheres.radius,
+        ];
+        for start in (0..count).step_by(4) {
+            let [x, y, z, radius] = lanes.map(|lane| load_lanes(lane, start));
+            let mut mask = 0xf;
+            for plane in &self.planes {
+                let distance = x * plane.x + y * plane.y + z * plane.z + Vec4::splat(plane.w);
+                mask &= distance.cmpge(-radius).bitmask();
+            }
+            store_mask(mask, &mut visible[start..count]);
+        }
+    }
+
+    /// Tests every box of `boxes` and writes whether it's visible to `visible`.
+    pub fn cull_aabbs(&self, boxes: &AabbBatch, visible: &mut [bool]) {
+        let count = boxes.center_x.len();
+        assert!(visible.len() >= count);
+        let lanes = [
+            &boxes.center_x,
+            &boxes.center_y,
+            &boxes.center_z,
+            &boxes.extent_x,
+            &boxes.extent_y,
+            &boxes.extent_z,
+        ];
+        for start in (0..count).step_by(4) {
+            let [x, y, z, extent_x, extent_y, extent_z] = lanes.map(|lane| load_lanes(lane, start));
+            let mut mask = 0xf;
+            for plane in &self.planes {
+                let distance = x * plane.x + y * plane.y + z * plane.z + Vec4::splat(plane.w);
+                let radius =
+                    extent_x * plane.x.abs() + extent_y * plane.y.abs() + extent_z * plane.z.abs();
+                mask &= distance.cmpge(-radius).bitmask();
+            }
+            store_mask(mask, &mut visible[start..count]);
+        }
+    }
+}
+
+/// four values starting at `start`, zero past the end
+fn load_lanes(values: &[f32], start: usize) -> Vec4 {
+    let mut lanes = [0.0; 4];
+    let end = values.len().min(start + 4);
+    lanes[..end - start].copy_from_slice(&values[start..end]);
+    return Vec4::from(lanes);
+}
+
+fn store_mask(mask: u32, visible: &mut [bool]) {
+    for (lane, value) in visible.iter_mut().take(4).enumerate() {
+        *value = mask & (1 << lane) != 0;
+    }
+}
+
+/// spheres stored as one array per component, for `Frustum::cull_spheres`
+#[derive(Clone, Debug, Default)]
+pub struct SphereBatch {
+    pub center_x: Vec<f32>,
+    pub center_y: Vec<f32>,
+    pub center_z: Vec<f32>,
+    pub radius: Vec<f32>,
+}
+
+impl SphereBatch {
+    pub fn push(&mut self, sphere: &Sphere) {
+        self.center_x.push(sphere.center.x);
+        self.center_y.push(sphere.center.y);
+        self.center_z.push(sphere.center.z);
+        self.radius.push(sphere.radius);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.radius.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.radius.is_empty();
+    }
+}
+
+/// boxes stored as center and half extents with one array per component, for
+/// `Frustum::cull_aabbs`
+#[derive(Clone, Debug, Default)]
+pub struct AabbBatch {
+    pub center_x: Vec<f32>,
+    pub center_y: Vec<f32>,
+    pub center_z: Vec<f32>,
+    pub extent_x: Vec<f32>,
+    pub extent_y: Vec<f32>,
+    pub extent_z: Vec<f32>,
+}
+
+impl AabbBatch {
+    pub fn push(&mut self, aabb: &Aabb) {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.center_x.push(center.x);
+        self.center_y.push(center.y);
+        self.center_z.push(center.z);
+        self.extent_x.push(half_extents.x);
+        self.extent_y.push(half_extents.y);
+        self.extent_z.push(half_extents.z);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.center_x.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.center_x.is_empty();
+    }
+}
@@ -1,4 +1,15 @@
-use num::Num;
+#![allow(clippy::needless_return, clippy::match_like_matches_macro)]
+
+use num::{Num, PrimInt};
+
+pub mod bvh;
+pub mod color;
+pub mod culling;
+pub mod packing;
+pub mod projection;
+pub mod sequences;
+pub mod spherical_harmonics;
+pub mod tiling;
 
 pub fn round_up<T: Num + Copy>(value: T, multiple: T) -> T {
     return ((value + multiple - T::one()) / multiple) * multiple;
@@ -7,3 +18,108 @@ pub fn round_up<T: Num + Copy>(value: T, multiple: T) -> T {
 pub fn round_down<T: Num + Copy>(value: T, multiple: T) -> T {
     return value - (value % multiple);
 }
+
+/// `round_up` that returns `None` instead of overflowing or dividing by zero
+pub fn checked_round_up<T: PrimInt>(value: T, multiple: T) -> Option<T> {
+    return value
+        .checked_add(&multiple.checked_sub(&T::one())?)?
+        .checked_div(&multiple)?
+        .checked_mul(&multiple);
+}
+
+/// Divides and rounds towards positive infinity for positive operands, without overflowing.
+pub fn div_ceil<T: PrimInt>(value: T, divisor: T) -> T {
+    let quotient = value / divisor;
+    if value % divisor != T::zero() {
+        return quotient + T::one();
+    }
+    return quotient;
+}
+
+/// `div_ceil` that returns `None` when `divisor` is zero
+pub fn checked_div_ceil<T: PrimInt>(value: T, divisor: T) -> Option<T> {
+    if divisor == T::zero() {
+        return None;
+    }
+    return Some(div_ceil(value, divisor));
+}
+
+pub fn is_pow2<T: PrimInt>(value: T) -> bool {
+    return value > T::zero() && value.count_ones() == 1;
+}
+
+/// Rounds `value` up to a multiple of `alignment`, which has to be a power of two.
+pub fn align_up_pow2<T: PrimInt>(value: T, alignment: T) -> T {
+    debug_assert!(is_pow2(alignment));
+    let mask = alignment - T::one();
+    return (value + mask) & !mask;
+}
+
+/// `align_up_pow2` that returns `None` instead of overflowing or for an alignment that isn't
+/// a power of two
+pub fn checked_align_up_pow2<T: PrimInt>(value: T, alignment: T) -> Option<T> {
+    if !is_pow2(alignment) {
+        return None;
+    }
+    let mask = alignment - T::one();
+    return Some(value.checked_add(&mask)? & !mask);
+}
+
+/// Rounds `value` down to a multiple of `alignment`, which has to be a power of two.
+pub fn align_down_pow2<T: PrimInt>(value: T, alignment: T) -> T {
+    debug_assert!(is_pow2(alignment));
+    return value & !(alignment - T::one());
+}
+
+pub fn is_aligned_pow2<T: PrimInt>(value: T, alignment: T) -> bool {
+    debug_assert!(is_pow2(alignment));
+    return value & (alignment - T::one()) == T::zero();
+}
+
+/// Smallest power of two that is at least `value`, 1 for zero.
+pub fn next_pow2<T: PrimInt>(value: T) -> T {
+    return checked_next_pow2(value).expect("next power of two overflows");
+}
+
+/// `next_pow2` that returns `None` when the result doesn't fit in `T`
+pub fn checked_next_pow2<T: PrimInt>(value: T) -> Option<T> {
+    if value <= T::one() {
+        return Some(T::one());
+    }
+    let shift = bit_width::<T>() - (value - T::one()).leading_zeros();
+    // the sign bit of signed types isn't a valid power of two either
+    if shift >= bit_width::<T>() - (T::min_value() < T::zero()) as u32 {
+        return None;
+    }
+    return Some(T::one() << shift as usize);
+}
+
+/// Index of the highest set bit, `value` has to be positive.
+pub fn log2_floor<T: PrimInt>(value: T) -> u32 {
+    debug_assert!(value > T::zero());
+    return bit_width::<T>() - 1 - value.leading_zeros();
+}
+
+/// Smallest `n` with `2^n >= value`, `value` has to be positive.
+pub fn log2_ceil<T: PrimInt>(value: T) -> u32 {
+    debug_assert!(value > T::zero());
+    if value == T::one() {
+        return 0;
+    }
+    return bit_width::<T>() - (value - T::one()).leading_zeros();
+}
+
+/// Number of levels of a full mip chain down to 1x1x1.
+pub fn mip_level_count(width: u32, height: u32, depth: u32) -> u32 {
+    return log2_floor(width.max(height).max(depth).max(1)) + 1;
+}
+
+/// Extent of `level` in a mip chain, no dimension goes below 1.
+pub fn mip_extent(width: u32, height: u32, depth: u32, level: u32) -> (u32, u32, u32) {
+    let shrink = |extent: u32| extent.checked_shr(level).unwrap_or(0).max(1);
+    return (shrink(width), shrink(height), shrink(depth));
+}
+
+fn bit_width<T: PrimInt>() -> u32 {
+    return T::zero().count_zeros();
+}
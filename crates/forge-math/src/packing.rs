@@ -0,0 +1,324 @@
+use glam::{Vec2, Vec3, Vec4};
+
+// Conversions follow the Vulkan rules for writing normalized and small float formats: normalized
+// values are clamped and rounded to nearest, floats round to nearest even and keep NaN and
+// infinity.
+
+/// Converts to a `bits` wide UNORM value.
+pub fn f32_to_unorm(value: f32, bits: u32) -> u32 {
+    let max = ((1u64 << bits) - 1) as f32;
+    // NaN converts to 0
+    return (value.clamp(0.0, 1.0) * max).round() as u32;
+}
+
+pub fn unorm_to_f32(value: u32, bits: u32) -> f32 {
+    return value as f32 / ((1u64 << bits) - 1) as f32;
+}
+
+/// Converts to a `bits` wide SNORM value, -1 maps to the second smallest integer so 0 is exact.
+pub fn f32_to_snorm(value: f32, bits: u32) -> i32 {
+    let max = ((1u64 << (bits - 1)) - 1) as f32;
+    return (value.clamp(-1.0, 1.0) * max).round() as i32;
+}
+
+/// Converts a `bits` wide SNORM value, the smallest integer also maps to -1.
+pub fn snorm_to_f32(value: i32, bits: u32) -> f32 {
+    let max = ((1u64 << (bits - 1)) - 1) as f32;
+    return (value as f32 / max).max(-1.0);
+}
+
+/// Rounds `value` right by `shift` bits to nearest even.
+fn round_shift(value: u32, shift: u32) -> u32 {
+    if shift >= 32 {
+        return 0;
+    }
+    if shift == 0 {
+        return value;
+    }
+    let quotient = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if remainder > half || (remainder == half && quotient & 1 != 0) {
+        return quotient + 1;
+    }
+    return quotient;
+}
+
+/// Magnitude of `value` as a float with the given exponent and mantissa bits and no sign bit.
+fn f32_to_small_float(value: f32, exponent_bits: u32, mantissa_bits: u32) -> u32 {
+    let bias = (1i32 << (exponent_bits - 1)) - 1;
+    let max_exponent = (1u32 << exponent_bits) - 1;
+    let bits = value.abs().to_bits();
+    if value.is_nan() {
+        return (max_exponent << mantissa_bits) | (1 << (mantissa_bits - 1));
+    }
+    let exponent = (bits >> 23) as i32 - 127 + bias;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= max_exponent as i32 {
+        return max_exponent << mantissa_bits;
+    }
+    if exponent <= 0 {
+        // denormal, shift the implicit one in as well
+        let shift = (24 - mantissa_bits) as i32 - exponent;
+        return round_shift(mantissa | 0x80_0000, shift as u32);
+    }
+    // rounding may carry into the exponent, up to infinity
+    return round_shift(((exponent as u32) << 23) | mantissa, 23 - mantissa_bits);
+}
+
+fn small_float_to_f32(value: u32, exponent_bits: u32, mantissa_bits: u32) -> f32 {
+    let bias = (1i32 << (exponent_bits - 1)) - 1;
+    let max_exponent = (1u32 << exponent_bits) - 1;
+    let exponent = value >> mantissa_bits;
+    let mantissa = value & ((1 << mantissa_bits) - 1);
+    if exponent == 0 {
+        return mantissa as f32 * 2f32.powi(1 - bias - mantissa_bits as i32);
+    }
+    if exponent == max_exponent {
+        return if mantissa == 0 {
+            f32::INFINITY
+        } else {
+            f32::NAN
+        };
+    }
+    let exponent = (exponent as i32 - bias + 127) as u32;
+    return f32::from_bits((exponent << 23) | (mantissa << (23 - mantissa_bits)));
+}
+
+pub fn f32_to_f16(value: f32) -> u16 {
+    let sign = (value.to_bits() >> 16) & 0x8000;
+    return (sign | f32_to_small_float(value, 5, 10)) as u16;
+}
+
+pub fn f16_to_f32(value: u16) -> f32 {
+    let magnitude = small_float_to_f32(value as u32 & 0x7fff, 5, 10);
+    return if value & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    };
+}
+
+/// unsigned floats clamp negative values to 0
+fn f32_to_ufloat(value: f32, exponent_bits: u32, mantissa_bits: u32) -> u32 {
+    if value.is_sign_negative() && !value.is_nan() {
+        return 0;
+    }
+    return f32_to_small_float(value, exponent_bits, mantissa_bits);
+}
+
+pub fn pack_half2x16(value: Vec2) -> u32 {
+    return f32_to_f16(value.x) as u32 | (f32_to_f16(value.y) as u32) << 16;
+}
+
+pub fn unpack_half2x16(value: u32) -> Vec2 {
+    return Vec2::new(f16_to_f32(value as u16), f16_to_f32((value >> 16) as u16));
+}
+
+pub fn pack_half4x16(value: Vec4) -> u64 {
+    return pack_half2x16(Vec2::new(value.x, value.y)) as u64
+        | (pack_half2x16(Vec2::new(value.z, value.w)) as u64) << 32;
+}
+
+pub fn unpack_half4x16(value: u64) -> Vec4 {
+    let low = unpack_half2x16(value as u32);
+    let high = unpack_half2x16((value >> 32) as u32);
+    return Vec4::new(low.x, low.y, high.x, high.y);
+}
+
+/// `R8G8B8A8_UNORM` with x in the lowest byte
+pub fn pack_unorm4x8(value: Vec4) -> u32 {
+    return value
+        .to_array()
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| f32_to_unorm(*channel, 8) << (index * 8))
+        .sum();
+}
+
+pub fn unpack_unorm4x8(value: u32) -> Vec4 {
+    let channel = |index: u32| unorm_to_f32((value >> (index * 8)) & 0xff, 8);
+    return Vec4::new(channel(0), channel(1), channel(2), channel(3));
+}
+
+/// `R8G8B8A8_SNORM` with x in the lowest byte
+pub fn pack_snorm4x8(value: Vec4) -> u32 {
+    return value
+        .to_array()
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| (f32_to_snorm(*channel, 8) as u32 & 0xff) << (index * 8))
+        .sum();
+}
+
+pub fn unpack_snorm4x8(value: u32) -> Vec4 {
+    let channel = |index: u32| snorm_to_f32((value >> (index * 8)) as u8 as i8 as i32, 8);
+    return Vec4::new(channel(0), channel(1), channel(2), channel(3));
+}
+
+/// `R16G16_UNORM` with x in the low half
+pub fn pack_unorm2x16(value: Vec2) -> u32 {
+    return f32_to_unorm(value.x, 16) | f32_to_unorm(value.y, 16) << 16;
+}
+
+pub fn unpack_unorm2x16(value: u32) -> Vec2 {
+    return Vec2::new(
+        unorm_to_f32(value & 0xffff, 16),
+        unorm_to_f32(value >> 16, 16),
+    );
+}
+
+/// `R16G16_SNORM` with x in the low half
+pub fn pack_snorm2x16(value: Vec2) -> u32 {
+    return (f32_to_snorm(value.x, 16) as u32 & 0xffff) | (f32_to_snorm(value.y, 16) as u32) << 16;
+}
+
+pub fn unpack_snorm2x16(value: u32) -> Vec2 {
+    return Vec2::new(
+        snorm_to_f32(value as u16 as i16 as i32, 16),
+        snorm_to_f32((value >> 16) as u16 as i16 as i32, 16),
+    );
+}
+
+/// `A2B10G10R10_UNORM`, red in the lowest bits
+pub fn pack_a2b10g10r10_unorm(value: Vec4) -> u32 {
+    return f32_to_unorm(value.x, 10)
+        | f32_to_unorm(value.y, 10) << 10
+        | f32_to_unorm(value.z, 10) << 20
+        | f32_to_unorm(value.w, 2) << 30;
+}
+
+pub fn unpack_a2b10g10r10_unorm(value: u32) -> Vec4 {
+    return Vec4::new(
+        unorm_to_f32(value & 0x3ff, 10),
+        unorm_to_f32((value >> 10) & 0x3ff, 10),
+        unorm_to_f32((value >> 20) & 0x3ff, 10),
+        unorm_to_f32(value >> 30, 2),
+    );
+}
+
+/// `A2B10G10R10_SNORM`, red in the lowest bits
+pub fn pack_a2b10g10r10_snorm(value: Vec4) -> u32 {
+    let channel = |value: f32, bits: u32, shift: u32| -> u32 {
+        return (f32_to_snorm(value, bits) as u32 & ((1 << bits) - 1)) << shift;
+    };
+    return channel(value.x, 10, 0)
+        | channel(value.y, 10, 10)
+        | channel(value.z, 10, 20)
+        | channel(value.w, 2, 30);
+}
+
+pub fn unpack_a2b10g10r10_snorm(value: u32) -> Vec4 {
+    // shift each field to the top and back down to sign extend it
+    let channel = |bits: u32, shift: u32| -> f32 {
+        let field = ((value << (32 - bits - shift)) as i32) >> (32 - bits);
+        return snorm_to_f32(field, bits);
+    };
+    return Vec4::new(
+        channel(10, 0),
+        channel(10, 10),
+        channel(10, 20),
+        channel(2, 30),
+    );
+}
+
+/// `B10G11R11_UFLOAT`, red in the lowest bits with 6 mantissa bits for red and green and 5 for
+/// blue
+pub fn pack_b10g11r11_ufloat(value: Vec3) -> u32 {
+    return f32_to_ufloat(value.x, 5, 6)
+        | f32_to_ufloat(value.y, 5, 6) << 11
+        | f32_to_ufloat(value.z, 5, 5) << 22;
+}
+
+pub fn unpack_b10g11r11_ufloat(value: u32) -> Vec3 {
+    return Vec3::new(
+        small_float_to_f32(value & 0x7ff, 5, 6),
+        small_float_to_f32((value >> 11) & 0x7ff, 5, 6),
+        small_float_to_f32(value >> 22, 5, 5),
+    );
+}
+
+/// `E5B9G9R9_UFLOAT`, three 9 bit mantissas sharing the 5 bit exponent in the top bits
+pub fn pack_e5b9g9r9_ufloat(value: Vec3) -> u32 {
+    const MANTISSA_BITS: i32 = 9;
+    const BIAS: i32 = 15;
+    const MAX_EXPONENT: i32 = 31;
+    let max_value = 511.0 / 512.0 * 2f32.powi(MAX_EXPONENT - BIAS);
+    // NaN becomes 0
+    let channels = value.to_array().map(|channel| {
+        if channel > 0.0 {
+            channel.min(max_value)
+        } else {
+            0.0
+        }
+    });
+    let max_channel = channels[0].max(channels[1]).max(channels[2]);
+
+    let mut exponent = if max_channel > 0.0 {
+        (-BIAS - 1).max(max_channel.log2().floor() as i32) + 1 + BIAS
+    } else {
+        0
+    };
+    let scale = |exponent: i32| 2f32.powi(exponent - BIAS - MANTISSA_BITS);
+    let max_mantissa = (max_channel / scale(exponent) + 0.5).floor() as i32;
+    if max_mantissa >= 1 << MANTISSA_BITS {
+        exponent += 1;
+    }
+    let mantissas = channels.map(|channel| (channel / scale(exponent) + 0.5).floor() as u32);
+    return mantissas[0] | mantissas[1] << 9 | mantissas[2] << 18 | (exponent as u32) << 27;
+}
+
+pub fn unpack_e5b9g9r9_ufloat(value: u32) -> Vec3 {
+    let scale = 2f32.powi((value >> 27) as i32 - 15 - 9);
+    return Vec3::new(
+        (value & 0x1ff) as f32 * scale,
+        ((value >> 9) & 0x1ff) as f32 * scale,
+        ((value >> 18) & 0x1ff) as f32 * scale,
+    );
+}
+
+/// Maps a unit vector to the octahedron unfolded onto -1..1, for two component normals.
+pub fn octahedral_encode(normal: Vec3) -> Vec2 {
+    let normal = normal / (normal.x.abs() + normal.y.abs() + normal.z.abs());
+    let folded = Vec2::new(normal.x, normal.y);
+    if normal.z >= 0.0 {
+        return folded;
+    }
+    // fold the lower half over the diagonals
+    let sign = Vec2::new(
+        if folded.x >= 0.0 { 1.0 } else { -1.0 },
+        if folded.y >= 0.0 { 1.0 } else { -1.0 },
+    );
+    return (Vec2::ONE - Vec2::new(folded.y, folded.x).abs()) * sign;
+}
+
+pub fn octahedral_decode(encoded: Vec2) -> Vec3 {
+    let z = 1.0 - encoded.x.abs() - encoded.y.abs();
+    let offset = (-z).max(0.0);
+    let x = encoded.x + if encoded.x >= 0.0 { -offset } else { offset };
+    let y = encoded.y + if encoded.y >= 0.0 { -offset } else { offset };
+    return Vec3::new(x, y, z).normalize();
+}
+
+/// octahedral normal in `R16G16_SNORM`
+pub fn pack_octahedral_snorm2x16(normal: Vec3) -> u32 {
+    return pack_snorm2x16(octahedral_encode(normal));
+}
+
+pub fn unpack_octahedral_snorm2x16(value: u32) -> Vec3 {
+    return octahedral_decode(unpack_snorm2x16(value));
+}
+
+/// octahedral normal in `R8G8_SNORM`
+pub fn pack_octahedral_snorm2x8(normal: Vec3) -> u16 {
+    let encoded = octahedral_encode(normal);
+    return (f32_to_snorm(encoded.x, 8) as u8 as u16)
+        | (f32_to_snorm(encoded.y, 8) as u8 as u16) << 8;
+}
+
+pub fn unpack_octahedral_snorm2x8(value: u16) -> Vec3 {
+    return octahedral_decode(Vec2::new(
+        snorm_to_f32(value as u8 as i8 as i32, 8),
+        snorm_to_f32((value >> 8) as u8 as i8 as i32, 8),
+    ));
+}
@@ -0,0 +1,128 @@
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+// View space is right handed and looks down -Z with +Y up. The projections map it to Vulkan clip
+// space, where +Y points down the screen and depth goes from 0 to 1.
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum DepthRange {
+    /// near plane at depth 0, far plane at 1
+    Standard,
+    /// near plane at depth 1, far plane at 0, spreads float precision evenly over distance
+    Reversed,
+}
+
+/// Off-center perspective projection through the rectangle `left..right`, `bottom..top` on the
+/// near plane. `far` can be infinite.
+pub fn frustum_vk(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+    depth: DepthRange,
+) -> Mat4 {
+    let width = right - left;
+    let height = top - bottom;
+    // depth is a * z + b over w = -z
+    let (a, b) = match (depth, far.is_infinite()) {
+        (DepthRange::Standard, false) => (far / (near - far), near * far / (near - far)),
+        (DepthRange::Standard, true) => (-1.0, -near),
+        (DepthRange::Reversed, false) => (near / (far - near), far * near / (far - near)),
+        (DepthRange::Reversed, true) => (0.0, near),
+    };
+    return Mat4::from_cols(
+        Vec4::new(2.0 * near / width, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, -2.0 * near / height, 0.0, 0.0),
+        Vec4::new((right + left) / width, -(top + bottom) / height, a, -1.0),
+        Vec4::new(0.0, 0.0, b, 0.0),
+    );
+}
+
+/// Symmetric perspective projection, `fov_y` is the full vertical field of view in radians and
+/// `aspect` is width over height. `far` can be infinite.
+pub fn perspective_vk(fov_y: f32, aspect: f32, near: f32, far: f32, depth: DepthRange) -> Mat4 {
+    let top = near * (fov_y * 0.5).tan();
+    let right = top * aspect;
+    return frustum_vk(-right, right, -top, top, near, far, depth);
+}
+
+/// Asymmetric projection from the angles of the four frustum sides to the view direction, as
+/// VR runtimes report them per eye. Left and down angles are negative for a frustum that
+/// contains the view direction.
+pub fn perspective_from_angles_vk(
+    angle_left: f32,
+    angle_right: f32,
+    angle_down: f32,
+    angle_up: f32,
+    near: f32,
+    far: f32,
+    depth: DepthRange,
+) -> Mat4 {
+    return frustum_vk(
+        near * angle_left.tan(),
+        near * angle_right.tan(),
+        near * angle_down.tan(),
+        near * angle_up.tan(),
+        near,
+        far,
+        depth,
+    );
+}
+
+pub fn orthographic_vk(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+    depth: DepthRange,
+) -> Mat4 {
+    let width = right - left;
+    let height = top - bottom;
+    let (a, b) = match depth {
+        DepthRange::Standard => (1.0 / (near - far), near / (near - far)),
+        DepthRange::Reversed => (1.0 / (far - near), far / (far - near)),
+    };
+    return Mat4::from_cols(
+        Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, -2.0 / height, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, a, 0.0),
+        Vec4::new(-(right + left) / width, (top + bottom) / height, b, 1.0),
+    );
+}
+
+/// Replaces the near plane of a perspective `projection` with `clip_plane`, a view space plane
+/// whose positive side is kept, for mirrors and portals.
+///
+/// The far plane is tilted as little as possible to keep depth precision, which needs a finite
+/// far plane in `projection`.
+pub fn oblique_near_plane(projection: Mat4, clip_plane: Vec4, depth: DepthRange) -> Mat4 {
+    // clip space corner of the far plane opposite the new near plane
+    let clip_space_plane = projection.inverse().transpose() * clip_plane;
+    let far_depth = match depth {
+        DepthRange::Standard => 1.0,
+        DepthRange::Reversed => 0.0,
+    };
+    let corner = projection.inverse()
+        * Vec4::new(
+            clip_space_plane.x.signum(),
+            clip_space_plane.y.signum(),
+            far_depth,
+            1.0,
+        );
+    let scaled_plane = clip_plane / clip_plane.dot(corner);
+
+    let mut rows = projection.transpose();
+    rows.z_axis = match depth {
+        DepthRange::Standard => scaled_plane,
+        DepthRange::Reversed => rows.w_axis - scaled_plane,
+    };
+    return rows.transpose();
+}
+
+/// View matrix of a camera at `position` with the given orientation.
+pub fn view_from_camera(position: Vec3, rotation: Quat) -> Mat4 {
+    return Mat4::from_rotation_translation(rotation, position).inverse();
+}
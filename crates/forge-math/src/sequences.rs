@@ -0,0 +1,231 @@
+use glam::{Mat4, Vec2, Vec3};
+
+// Every sequence is a plain function of the sample index using integer or single precision math,
+// so shaders can compute the same values.
+
+/// Radical inverse of `index` in `base`, the Halton sequence for that base in 0..1.
+pub fn halton(index: u32, base: u32) -> f32 {
+    assert!(base >= 2);
+    let mut index = index;
+    let mut fraction = 1.0 / base as f32;
+    let mut result = 0.0;
+    while index > 0 {
+        result += fraction * (index % base) as f32;
+        index /= base;
+        fraction /= base as f32;
+    }
+    return result;
+}
+
+/// Halton sequence with bases 2 and 3.
+pub fn halton_2d(index: u32) -> Vec2 {
+    return Vec2::new(halton(index, 2), halton(index, 3));
+}
+
+/// reciprocals of the plastic number and its square in 0.32 fixed point, the generalized golden
+/// ratio for 2D
+const R2_ALPHA: [u32; 2] = [3242174889, 2447445414];
+
+/// Roberts' R2 sequence, an additive recurrence with better coverage than Halton for small counts.
+pub fn r2(index: u32) -> Vec2 {
+    // fixed point wraps around exactly where the fraction would
+    let value = |alpha: u32| -> f32 {
+        let fraction = alpha.wrapping_mul(index).wrapping_add(1 << 31);
+        return fraction as f32 / 4294967296.0;
+    };
+    return Vec2::new(value(R2_ALPHA[0]), value(R2_ALPHA[1]));
+}
+
+/// First two dimensions of the Sobol sequence, the first is the base 2 radical inverse.
+pub fn sobol_2d(index: u32) -> Vec2 {
+    let mut direction = 1u32 << 31;
+    let mut y = 0u32;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= direction;
+        }
+        // direction numbers of the primitive polynomial x + 1
+        direction ^= direction >> 1;
+        bits >>= 1;
+    }
+    let scale = 1.0 / 4294967296.0;
+    return Vec2::new(index.reverse_bits() as f32 * scale, y as f32 * scale);
+}
+
+/// Sub-pixel offset in -0.5..0.5 for frame `index` of a repeating `count` sample Halton(2, 3)
+/// jitter pattern, the usual choice for TAA.
+pub fn halton_jitter(index: u32, count: u32) -> Vec2 {
+    assert!(count > 0);
+    // the sequence starts at 0, skip it so the first sample is off-center like the others
+    return halton_2d(index % count + 1) - Vec2::splat(0.5);
+}
+
+/// Shifts `projection` by `jitter` pixels on a `width` by `height` target, +Y is down like in
+/// Vulkan clip space.
+pub fn jitter_projection(projection: &Mat4, jitter: Vec2, width: u32, height: u32) -> Mat4 {
+    let offset = Vec3::new(
+        2.0 * jitter.x / width as f32,
+        2.0 * jitter.y / height as f32,
+        0.0,
+    );
+    return Mat4::from_translation(offset) * *projection;
+}
+
+/// Square tile of blue noise ranks made with void and cluster, tiled over the screen to decorrelate
+/// per pixel random values.
+pub struct BlueNoiseTile {
+    size: u32,
+    /// rank of every pixel in row order, each value in 0..size * size appears once
+    ranks: Vec<u32>,
+}
+
+impl BlueNoiseTile {
+    /// Generates a `size` by `size` tile, the same seed always gives the same tile.
+    pub fn generate(size: u32, seed: u32) -> BlueNoiseTile {
+        assert!(size > 0);
+        let count = (size * size) as usize;
+        let mut energy = VoidAndCluster::new(size);
+        let mut state = seed | 1;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            return state;
+        };
+
+        // start from a tenth of the pixels at random and spread them out until stable
+        let initial_count = (count / 10).max(1);
+        let mut pattern = vec![false; count];
+        let mut placed = 0;
+        while placed < initial_count {
+            let pixel = random() as usize % count;
+            if !pattern[pixel] {
+                pattern[pixel] = true;
+                energy.splat(pixel, 1.0);
+                placed += 1;
+            }
+        }
+        loop {
+            let cluster = energy.tightest_cluster(&pattern);
+            pattern[cluster] = false;
+            energy.splat(cluster, -1.0);
+            let void = energy.largest_void(&pattern);
+            pattern[void] = true;
+            energy.splat(void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut ranks = vec![0; count];
+        // rank the initial pixels by removing the tightest clusters first
+        let mut removal = pattern.clone();
+        let mut removal_energy = energy.clone();
+        for rank in (0..initial_count).rev() {
+            let cluster = removal_energy.tightest_cluster(&removal);
+            removal[cluster] = false;
+            removal_energy.splat(cluster, -1.0);
+            ranks[cluster] = rank as u32;
+        }
+        // then fill the largest voids, with a toroidal filter this also picks the tightest
+        // clusters of empty pixels once more than half are set
+        for rank in initial_count..count {
+            let void = energy.largest_void(&pattern);
+            pattern[void] = true;
+            energy.splat(void, 1.0);
+            ranks[void] = rank as u32;
+        }
+        return BlueNoiseTile { size, ranks };
+    }
+
+    pub fn size(&self) -> u32 {
+        return self.size;
+    }
+
+    /// Value in 0..1 of the pixel, the tile repeats in both directions.
+    pub fn value(&self, x: u32, y: u32) -> f32 {
+        let index = (y % self.size) * self.size + x % self.size;
+        return (self.ranks[index as usize] as f32 + 0.5) / self.ranks.len() as f32;
+    }
+
+    /// Value of the pixel on `frame`, offset by the golden ratio every frame so each pixel also
+    /// cycles through its values evenly over time.
+    pub fn temporal_value(&self, x: u32, y: u32, frame: u32) -> f32 {
+        let offset = (frame as f64 * 0.618_033_988_749_894_8).fract() as f32;
+        return (self.value(x, y) + offset).fract();
+    }
+
+    /// Ranks in row order as normalized bytes, to upload as an `R8_UNORM` texture.
+    pub fn to_unorm8(&self) -> Vec<u8> {
+        let count = self.ranks.len() as u64;
+        return self
+            .ranks
+            .iter()
+            .map(|rank| (*rank as u64 * 256 / count) as u8)
+            .collect();
+    }
+}
+
+/// gaussian filtered energy of the set pixels of a toroidal pattern
+#[derive(Clone)]
+struct VoidAndCluster {
+    size: u32,
+    energy: Vec<f32>,
+    /// filter weight by wrapped x and y distance
+    weights: Vec<f32>,
+}
+
+impl VoidAndCluster {
+    fn new(size: u32) -> VoidAndCluster {
+        const SIGMA: f32 = 1.5;
+        let mut weights = vec![0.0; (size * size) as usize];
+        for y in 0..size {
+            for x in 0..size {
+                let dx = x.min(size - x) as f32;
+                let dy = y.min(size - y) as f32;
+                weights[(y * size + x) as usize] =
+                    (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+        return VoidAndCluster {
+            size,
+            energy: vec![0.0; (size * size) as usize],
+            weights,
+        };
+    }
+
+    fn splat(&mut self, pixel: usize, sign: f32) {
+        let size = self.size as usize;
+        let (px, py) = (pixel % size, pixel / size);
+        for y in 0..size {
+            let dy = (y + size - py) % size;
+            for x in 0..size {
+                let dx = (x + size - px) % size;
+                self.energy[y * size + x] += sign * self.weights[dy * size + dx];
+            }
+        }
+    }
+
+    /// set pixel with the highest energy
+    fn tightest_cluster(&self, pattern: &[bool]) -> usize {
+        let mut best = (0, f32::MIN);
+        for (pixel, energy) in self.energy.iter().enumerate() {
+            if pattern[pixel] && *energy > best.1 {
+                best = (pixel, *energy);
+            }
+        }
+        return best.0;
+    }
+
+    /// empty pixel with the lowest energy
+    fn largest_void(&self, pattern: &[bool]) -> usize {
+        let mut best = (0, f32::MAX);
+        for (pixel, energy) in self.energy.iter().enumerate() {
+            if !pattern[pixel] && *energy < best.1 {
+                best = (pixel, *energy);
+            }
+        }
+        return best.0;
+    }
+}
@@ -0,0 +1,263 @@
+use glam::{Mat3, Vec3};
+use std::f32::consts::PI;
+use std::ops::{Add, Mul};
+
+// Real spherical harmonics without the Condon-Shortley phase, ordered by band and then by m from
+// -l to l. Directions are unit vectors.
+
+const Y00: f32 = 0.282_094_8;
+const Y1: f32 = 0.488_602_5;
+/// factor of the xy, yz and xz functions of band 2
+const Y2_CROSS: f32 = 1.092_548_4;
+const Y20: f32 = 0.315_391_57;
+const Y22: f32 = 0.546_274_2;
+
+/// Values of the L1 basis functions in `direction`.
+pub fn sh_basis_l1(direction: Vec3) -> [f32; 4] {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    return [Y00, Y1 * y, Y1 * z, Y1 * x];
+}
+
+/// Values of the L2 basis functions in `direction`.
+pub fn sh_basis_l2(direction: Vec3) -> [f32; 9] {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    return [
+        Y00,
+        Y1 * y,
+        Y1 * z,
+        Y1 * x,
+        Y2_CROSS * x * y,
+        Y2_CROSS * y * z,
+        Y20 * (3.0 * z * z - 1.0),
+        Y2_CROSS * x * z,
+        Y22 * (x * x - y * y),
+    ];
+}
+
+/// Window applied to reduce ringing before evaluating a projected function, `width` is the band
+/// where the window reaches zero and must be greater than the highest band.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ShWindow {
+    Hanning { width: f32 },
+    Lanczos { width: f32 },
+}
+
+impl ShWindow {
+    /// factor for the coefficients of `band`
+    fn weight(self, band: usize) -> f32 {
+        let band = band as f32;
+        return match self {
+            ShWindow::Hanning { width } => 0.5 * (1.0 + (PI * band / width).cos()),
+            ShWindow::Lanczos { width } => {
+                if band == 0.0 {
+                    1.0
+                } else {
+                    let x = PI * band / width;
+                    x.sin() / x
+                }
+            }
+        };
+    }
+}
+
+/// RGB function on the sphere stored as `N` spherical harmonics coefficients, either 4 for
+/// `ShL1` or 9 for `ShL2`.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Sh<const N: usize> {
+    pub coefficients: [Vec3; N],
+}
+
+pub type ShL1 = Sh<4>;
+pub type ShL2 = Sh<9>;
+
+/// band of coefficient `index`
+fn band(index: usize) -> usize {
+    return match index {
+        0 => 0,
+        1..=3 => 1,
+        _ => 2,
+    };
+}
+
+/// integral of `atan2(xy, sqrt(x² + y² + 1))` from the face center, used for texel solid angles
+fn face_area(x: f32, y: f32) -> f32 {
+    return (x * y).atan2((x * x + y * y + 1.0).sqrt());
+}
+
+/// direction through the face coordinate in -1..1, in vulkan cube face order
+fn face_direction(face: u32, s: f32, t: f32) -> Vec3 {
+    return match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    };
+}
+
+impl<const N: usize> Sh<N> {
+    pub const ZERO: Self = Sh {
+        coefficients: [Vec3::ZERO; N],
+    };
+
+    /// basis functions of this order in `direction`
+    fn basis(direction: Vec3) -> [f32; N] {
+        let all = sh_basis_l2(direction);
+        let mut result = [0.0; N];
+        result.copy_from_slice(&all[..N]);
+        return result;
+    }
+
+    /// Adds `color` arriving from `direction` to the projection, weighted by the solid angle
+    /// it covers. Monte Carlo projections use `4π / sample count` for uniform samples.
+    pub fn add_sample(&mut self, direction: Vec3, color: Vec3, weight: f32) {
+        for (coefficient, basis) in self.coefficients.iter_mut().zip(Self::basis(direction)) {
+            *coefficient += color * (basis * weight);
+        }
+    }
+
+    /// Projects a cubemap of `size` by `size` faces, `texel` returns the color of a face, x
+    /// and y in the array layer order and orientation vulkan samples cube textures with.
+    ///
+    /// With `forge_image_format` images that's
+    /// `|face, x, y| Vec3::from_slice(&image.read_texel(0, face, x, y, 0))`.
+    pub fn project_cubemap(size: u32, mut texel: impl FnMut(u32, u32, u32) -> Vec3) -> Self {
+        let mut result = Self::ZERO;
+        let mut total_weight = 0.0;
+        let texel_size = 2.0 / size as f32;
+        for face in 0..6 {
+            for y in 0..size {
+                let t0 = y as f32 * texel_size - 1.0;
+                for x in 0..size {
+                    let s0 = x as f32 * texel_size - 1.0;
+                    let (s1, t1) = (s0 + texel_size, t0 + texel_size);
+                    let solid_angle = face_area(s0, t0) - face_area(s0, t1) - face_area(s1, t0)
+                        + face_area(s1, t1);
+                    let direction =
+                        face_direction(face, s0 + 0.5 * texel_size, t0 + 0.5 * texel_size);
+                    result.add_sample(direction.normalize(), texel(face, x, y), solid_angle);
+                    total_weight += solid_angle;
+                }
+            }
+        }
+        // the solid angles add up to 4π up to rounding, remove the error
+        return result * (4.0 * PI / total_weight);
+    }
+
+    /// Value of the function in `direction`.
+    pub fn evaluate(&self, direction: Vec3) -> Vec3 {
+        let mut result = Vec3::ZERO;
+        for (coefficient, basis) in self.coefficients.iter().zip(Self::basis(direction)) {
+            result += *coefficient * basis;
+        }
+        return result;
+    }
+
+    /// Function convolved with a clamped cosine lobe, evaluating it with a surface normal gives
+    /// the irradiance on the surface. Divide by π for the radiance off a white diffuse surface.
+    pub fn convolve_cosine(&self) -> Self {
+        const BAND_FACTORS: [f32; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+        let mut result = *self;
+        for (index, coefficient) in result.coefficients.iter_mut().enumerate() {
+            *coefficient *= BAND_FACTORS[band(index)];
+        }
+        return result;
+    }
+
+    pub fn apply_window(&mut self, window: ShWindow) {
+        for (index, coefficient) in self.coefficients.iter_mut().enumerate() {
+            *coefficient *= window.weight(band(index));
+        }
+    }
+
+    /// Function rotated by the rotation matrix `rotation`, so that evaluating the result in
+    /// `rotation * d` gives the original value in `d`.
+    pub fn rotate(&self, rotation: &Mat3) -> Self {
+        let mut result = *self;
+        if N >= 4 {
+            // band 1 is a dot product with the vector of its (x, y, z) coefficients
+            let [_, y, z, x] = [0, 1, 2, 3].map(|index| self.coefficients[index]);
+            for channel in 0..3 {
+                let vector = *rotation * Vec3::new(x[channel], y[channel], z[channel]);
+                result.coefficients[1][channel] = vector.y;
+                result.coefficients[2][channel] = vector.z;
+                result.coefficients[3][channel] = vector.x;
+            }
+        }
+        if N >= 9 {
+            // band 2 is a traceless quadratic form of the direction, rotate its matrix
+            for channel in 0..3 {
+                let band2 = [4, 5, 6, 7, 8].map(|index| self.coefficients[index][channel]);
+                let form = *rotation * quadratic_form(band2) * rotation.transpose();
+                for (offset, value) in band2_coefficients(&form).into_iter().enumerate() {
+                    result.coefficients[4 + offset][channel] = value;
+                }
+            }
+        }
+        return result;
+    }
+}
+
+impl ShL2 {
+    /// The first two bands.
+    pub fn to_l1(&self) -> ShL1 {
+        let [c0, c1, c2, c3, ..] = self.coefficients;
+        return Sh {
+            coefficients: [c0, c1, c2, c3],
+        };
+    }
+}
+
+/// symmetric matrix `q` with `dᵀ q d` equal to the band 2 sum for unit `d`
+fn quadratic_form(band2: [f32; 5]) -> Mat3 {
+    let [xy, yz, zz, xz, xx_yy] = band2;
+    let (xy, yz, xz) = (
+        0.5 * Y2_CROSS * xy,
+        0.5 * Y2_CROSS * yz,
+        0.5 * Y2_CROSS * xz,
+    );
+    // 3z² - 1 is 2z² - x² - y² on the unit sphere
+    let zz = Y20 * zz;
+    let xx_yy = Y22 * xx_yy;
+    return Mat3::from_cols(
+        Vec3::new(xx_yy - zz, xy, xz),
+        Vec3::new(xy, -xx_yy - zz, yz),
+        Vec3::new(xz, yz, 2.0 * zz),
+    );
+}
+
+fn band2_coefficients(form: &Mat3) -> [f32; 5] {
+    let scale = 2.0 / Y2_CROSS;
+    return [
+        form.x_axis.y * scale,
+        form.y_axis.z * scale,
+        form.z_axis.z / (2.0 * Y20),
+        form.x_axis.z * scale,
+        (form.x_axis.x - form.y_axis.y) / (2.0 * Y22),
+    ];
+}
+
+impl<const N: usize> Add for Sh<N> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut result = self;
+        for (coefficient, other) in result.coefficients.iter_mut().zip(other.coefficients) {
+            *coefficient += other;
+        }
+        return result;
+    }
+}
+
+impl<const N: usize> Mul<f32> for Sh<N> {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        let mut result = self;
+        for coefficient in result.coefficients.iter_mut() {
+            *coefficient *= scale;
+        }
+        return result;
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{div_ceil, is_pow2, log2_floor};
+
+/// spreads the low 32 bits of `value` to every other bit
+fn part_1_by_1(value: u32) -> u64 {
+    let mut value = value as u64;
+    value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+    value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+    value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+    value = (value | (value << 1)) & 0x5555_5555_5555_5555;
+    return value;
+}
+
+fn compact_1_by_1(value: u64) -> u32 {
+    let mut value = value & 0x5555_5555_5555_5555;
+    value = (value | (value >> 1)) & 0x3333_3333_3333_3333;
+    value = (value | (value >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    value = (value | (value >> 4)) & 0x00ff_00ff_00ff_00ff;
+    value = (value | (value >> 8)) & 0x0000_ffff_0000_ffff;
+    value = (value | (value >> 16)) & 0x0000_0000_ffff_ffff;
+    return value as u32;
+}
+
+/// spreads the low 21 bits of `value` to every third bit
+fn part_1_by_2(value: u32) -> u64 {
+    let mut value = value as u64 & 0x1f_ffff;
+    value = (value | (value << 32)) & 0x001f_0000_0000_ffff;
+    value = (value | (value << 16)) & 0x001f_0000_ff00_00ff;
+    value = (value | (value << 8)) & 0x100f_00f0_0f00_f00f;
+    value = (value | (value << 4)) & 0x10c3_0c30_c30c_30c3;
+    value = (value | (value << 2)) & 0x1249_2492_4924_9249;
+    return value;
+}
+
+fn compact_1_by_2(value: u64) -> u32 {
+    let mut value = value & 0x1249_2492_4924_9249;
+    value = (value | (value >> 2)) & 0x10c3_0c30_c30c_30c3;
+    value = (value | (value >> 4)) & 0x100f_00f0_0f00_f00f;
+    value = (value | (value >> 8)) & 0x001f_0000_ff00_00ff;
+    value = (value | (value >> 16)) & 0x001f_0000_0000_ffff;
+    value = (value | (value >> 32)) & 0x1f_ffff;
+    return value as u32;
+}
+
+/// Z-order index of `x`, `y` with the bits of `x` in the even positions.
+pub fn morton_encode_2d(x: u32, y: u32) -> u64 {
+    return part_1_by_1(x) | (part_1_by_1(y) << 1);
+}
+
+pub fn morton_decode_2d(code: u64) -> (u32, u32) {
+    return (compact_1_by_1(code), compact_1_by_1(code >> 1));
+}
+
+/// Z-order index of `x`, `y`, `z`, only the low 21 bits of each coordinate are used.
+pub fn morton_encode_3d(x: u32, y: u32, z: u32) -> u64 {
+    return part_1_by_2(x) | (part_1_by_2(y) << 1) | (part_1_by_2(z) << 2);
+}
+
+pub fn morton_decode_3d(code: u64) -> (u32, u32, u32) {
+    return (
+        compact_1_by_2(code),
+        compact_1_by_2(code >> 1),
+        compact_1_by_2(code >> 2),
+    );
+}
+
+/// Texel extent of a 64KiB tile in the vulkan standard sparse image block shapes, for 2D or 3D
+/// images with `bytes_per_texel` in 1, 2, 4, 8 or 16 and a single sample.
+pub fn standard_sparse_tile_extent(bytes_per_texel: u32, is_3d: bool) -> Option<[u32; 3]> {
+    let extent = match (bytes_per_texel, is_3d) {
+        (1, false) => [256, 256, 1],
+        (2, false) => [256, 128, 1],
+        (4, false) => [128, 128, 1],
+        (8, false) => [128, 64, 1],
+        (16, false) => [64, 64, 1],
+        (1, true) => [64, 32, 32],
+        (2, true) => [32, 32, 32],
+        (4, true) => [32, 32, 16],
+        (8, true) => [32, 16, 16],
+        (16, true) => [16, 16, 16],
+        _ => return None,
+    };
+    return Some(extent);
+}
+
+/// Memory layout of one mip level split into equally sized tiles stored in row order, with the
+/// elements of a tile in Z-order. Coordinates and extents are in blocks, which are texels for
+/// uncompressed formats.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TiledLayout {
+    extent: [u32; 3],
+    tile_extent: [u32; 3],
+    bytes_per_block: u32,
+    /// tiles along each axis, the last ones are padded
+    tile_counts: [u32; 3],
+    /// offset of every coordinate inside a tile in elements per axis, the offset of a block is
+    /// the bitwise or of its coordinates' entries
+    swizzle: [Vec<u32>; 3],
+}
+
+impl TiledLayout {
+    /// `tile_extent` must be a power of two along every axis.
+    pub fn new(extent: [u32; 3], tile_extent: [u32; 3], bytes_per_block: u32) -> TiledLayout {
+        assert!(tile_extent.iter().all(|extent| is_pow2(*extent)));
+        let bits = tile_extent.map(log2_floor);
+        let mut swizzle = tile_extent.map(|extent| vec![0; extent as usize]);
+        // interleave the coordinate bits x, y, z from the lowest up, skipping an axis once it
+        // has no bits left, so non-square tiles stay dense
+        let mut output_bit = 0;
+        for bit in 0..bits.into_iter().max().unwrap_or(0) {
+            for axis in 0..3 {
+                if bit >= bits[axis] {
+                    continue;
+                }
+                for (coordinate, offset) in swizzle[axis].iter_mut().enumerate() {
+                    *offset |= ((coordinate as u32 >> bit) & 1) << output_bit;
+                }
+                output_bit += 1;
+            }
+        }
+        return TiledLayout {
+            extent,
+            tile_extent,
+            bytes_per_block,
+            tile_counts: [0, 1, 2].map(|axis| div_ceil(extent[axis], tile_extent[axis])),
+            swizzle,
+        };
+    }
+
+    pub fn extent(&self) -> [u32; 3] {
+        return self.extent;
+    }
+
+    pub fn tile_extent(&self) -> [u32; 3] {
+        return self.tile_extent;
+    }
+
+    pub fn tile_counts(&self) -> [u32; 3] {
+        return self.tile_counts;
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        return self.tile_counts.iter().product();
+    }
+
+    pub fn tile_size(&self) -> u64 {
+        let blocks: u64 = self
+            .tile_extent
+            .iter()
+            .map(|extent| *extent as u64)
+            .product();
+        return blocks * self.bytes_per_block as u64;
+    }
+
+    /// Size of the whole level including the padding of the last tiles.
+    pub fn size(&self) -> u64 {
+        return self.tile_count() as u64 * self.tile_size();
+    }
+
+    /// Index of the tile at tile coordinate `x`, `y`, `z`, which is also its page table entry.
+    pub fn tile_index(&self, x: u32, y: u32, z: u32) -> u32 {
+        let [count_x, count_y, _] = self.tile_counts;
+        return (z * count_y + y) * count_x + x;
+    }
+
+    /// Tile coordinate of the tile containing block `x`, `y`, `z`.
+    pub fn tile_of_block(&self, x: u32, y: u32, z: u32) -> [u32; 3] {
+        let [tile_x, tile_y, tile_z] = self.tile_extent;
+        return [x / tile_x, y / tile_y, z / tile_z];
+    }
+
+    /// Byte offset of block `x`, `y`, `z`.
+    pub fn block_offset(&self, x: u32, y: u32, z: u32) -> u64 {
+        let [tile_x, tile_y, tile_z] = self.tile_of_block(x, y, z);
+        let tile = self.tile_index(tile_x, tile_y, tile_z) as u64;
+        let [mask_x, mask_y, mask_z] = self.tile_extent.map(|extent| extent - 1);
+        let element = self.swizzle[0][(x & mask_x) as usize]
+            | self.swizzle[1][(y & mask_y) as usize]
+            | self.swizzle[2][(z & mask_z) as usize];
+        return tile * self.tile_size() + element as u64 * self.bytes_per_block as u64;
+    }
+
+    /// Rearranges a tightly packed level in row order into this layout, padding is zeroed.
+    pub fn tile(&self, linear: &[u8]) -> Vec<u8> {
+        let mut tiled = vec![0; self.size() as usize];
+        self.copy_blocks(linear, &mut tiled, true);
+        return tiled;
+    }
+
+    /// Inverse of `tile`.
+    pub fn untile(&self, tiled: &[u8]) -> Vec<u8> {
+        let [width, height, depth] = self.extent.map(|extent| extent as usize);
+        let mut linear = vec![0; width * height * depth * self.bytes_per_block as usize];
+        self.copy_blocks(tiled, &mut linear, false);
+        return linear;
+    }
+
+    fn copy_blocks(&self, source: &[u8], destination: &mut [u8], to_tiled: bool) {
+        let block_size = self.bytes_per_block as usize;
+        let [width, height, depth] = self.extent;
+        let mut linear_offset = 0;
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let tiled_offset = self.block_offset(x, y, z) as usize;
+                    let (from, to) = if to_tiled {
+                        (linear_offset, tiled_offset)
+                    } else {
+                        (tiled_offset, linear_offset)
+                    };
+                    destination[to..to + block_size]
+                        .copy_from_slice(&source[from..from + block_size]);
+                    linear_offset += block_size;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// little endian writer and reader for the binary tables kept on disk
+
+/// 64 bit FNV-1a, stable between runs and builds.
+pub(crate) fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+pub(crate) struct ByteWriter {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub(crate) fn u32_array(&mut self, values: &[u32]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.u32(*value);
+        }
+    }
+}
+
+// reads fail on truncated or out of range data
+pub(crate) struct ByteReader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn take(&mut self, size: usize) -> Option<&'a [u8]> {
+        let result = self
+            .bytes
+            .get(self.cursor..self.cursor.checked_add(size)?)?;
+        self.cursor += size;
+        Some(result)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub(crate) fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn string(&mut self) -> Option<String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+
+    // element count of an array, bounded by the remaining bytes so corrupt counts can't
+    // allocate
+    pub(crate) fn count(&mut self, min_element_size: usize) -> Option<usize> {
+        let count = self.u32()? as usize;
+        if count.checked_mul(min_element_size)? > self.bytes.len() - self.cursor {
+            return None;
+        }
+        Some(count)
+    }
+
+    pub(crate) fn u32_array(&mut self) -> Option<Vec<u32>> {
+        let count = self.count(4)?;
+        (0..count).map(|_| self.u32()).collect()
+    }
+
+    // enum stored as its index into `values`
+    pub(crate) fn enum_value<T: Copy>(&mut self, values: &[T]) -> Option<T> {
+        values.get(self.u32()? as usize).copied()
+    }
+}
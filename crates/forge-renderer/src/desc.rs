@@ -1,12 +1,13 @@
 use crate::{
     types::{
-        AddressMode, BufferCreationFlag, CompareMode, DescriptorType, FilterType,
-        IndirectArgumentType, MipMapMode, QueueFlag, QueuePriority, QueueType, ResourceMemoryUsage,
-        ResourceState, RootSignatureFlags, SampleCount, ShaderStageFlags, TextureCreationFlags,
+        AddressMode, BufferCreationFlag, CompareMode, CrossCompileTarget, DescriptorType,
+        FilterType, IndirectArgumentType, MipMapMode, QueueFlag, QueuePriority, QueueType,
+        ResourceMemoryUsage, ResourceState, RootSignatureFlags, SampleCount, ShaderStageFlags,
+        SpecConstantValue, TextureCreationFlags, VertexAttribRate,
     },
     Api,
 };
-use forge_image_format::ImageFormat;
+use forge_image_format::{lut::CubeLut, ImageFormat};
 use raw_window_handle::HasRawWindowHandle;
 use std::ffi::{c_void, CStr, CString};
 
@@ -54,6 +55,8 @@ pub struct PipelineComputeDesc<'a, T: Api> {
 
 pub struct PipelineGraphicsDesc<'a, T: Api> {
     pub shader: &'a T::Shader,
+    // built with `match_vertex_layout`, none for pipelines without vertex inputs
+    pub vertex_layout: Option<&'a VertexLayout>,
 }
 
 pub enum PipelineDescType<'a, T: Api> {
@@ -64,6 +67,41 @@ pub enum PipelineDescType<'a, T: Api> {
 pub struct PipelineDesc<'a, T: Api> {
     pub pipeline: PipelineDescType<'a, T>,
     pub name: &'a CString,
+    // override the constants of the shader
+    pub spec_constants: Vec<SpecConstantDesc>,
+}
+
+#[derive(Clone)]
+pub struct SpecConstantDesc {
+    // matched against the name of the specialization constant, when empty `id` is used
+    pub name: CString,
+    // constant_id of the specialization constant
+    pub id: u32,
+    pub value: SpecConstantValue,
+}
+
+#[derive(Clone)]
+pub struct VertexBinding {
+    pub stride: u32,
+    pub rate: VertexAttribRate,
+}
+
+#[derive(Clone)]
+pub struct VertexAttrib {
+    // matched against the name of the vertex shader input, when empty `location` is used
+    pub name: CString,
+    pub format: ImageFormat,
+    // index into `VertexLayout::bindings`
+    pub binding: u32,
+    pub location: u32,
+    // from the start of the vertex in the buffer
+    pub offset: u32,
+}
+
+#[derive(Clone)]
+pub struct VertexLayout {
+    pub bindings: Vec<VertexBinding>,
+    pub attribs: Vec<VertexAttrib>,
 }
 
 pub struct RootSignatureDesc<'a, T: Api> {
@@ -159,6 +197,30 @@ pub struct TextureDesc {
     pub node_index: u32,
 }
 
+impl TextureDesc {
+    /// Sampled 3D texture for the image `CubeLut::to_image` builds in `format`.
+    pub fn cube_lut(name: CString, lut: &CubeLut, format: ImageFormat) -> TextureDesc {
+        let size = lut.volume_size();
+        TextureDesc {
+            native_handle: std::ptr::null_mut(),
+            name,
+            flags: TextureCreationFlags::TEXTURE_CREATION_FLAG_FORCE_3D,
+            width: size,
+            height: size,
+            depth: size,
+            array_size: 1,
+            mip_levels: 1,
+            sample_count: SampleCount::SampleCount1,
+            sample_quality: 0,
+            format,
+            start_state: ResourceState::SHADER_RESOURCE,
+            descriptors: DescriptorType::DESCRIPTOR_TYPE_TEXTURE,
+            shared_node_indices: vec![],
+            node_index: 0,
+        }
+    }
+}
+
 pub struct RenderTargetDesc {
     // Texture creation flags (decides memory allocation strategy, sharing access,...)
     pub flags: TextureCreationFlags,
@@ -200,8 +262,25 @@ pub struct BinaryShaderStageDesc {
     pub entry_point_name: CString,
 }
 
+#[derive(Clone)]
+pub struct BindingRemap {
+    // descriptor set and binding in the SPIR-V
+    pub set: u32,
+    pub binding: u32,
+    // register space for hlsl, ignored by glsl and msl
+    pub new_set: u32,
+    // binding for glsl, register for hlsl, buffer, texture and sampler index for msl
+    pub new_binding: u32,
+}
+
+pub struct CrossCompileDesc {
+    pub target: CrossCompileTarget,
+    pub binding_remaps: Vec<BindingRemap>,
+}
+
 pub struct BinaryShaderDesc {
     pub stages: ShaderStageFlags,
     pub shaders: Vec<BinaryShaderStageDesc>,
-    pub constants: Vec<CString>,
+    // specialization constant values for every pipeline using the shader
+    pub constants: Vec<SpecConstantDesc>,
 }
@@ -2,6 +2,12 @@ pub enum RendererError {
     Unhandled,
 
     VulkanError(ash::vk::Result),
+
+    // keyword not declared by the shader variant set
+    UnknownShaderKeyword(String),
+    // neither precompiled nor produced by the variant compiler
+    MissingShaderVariant(u64),
+    InvalidShaderVariantTable,
 }
 
 pub type RendererResult<T> = Result<T, RendererError>;
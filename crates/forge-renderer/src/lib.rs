@@ -19,9 +19,13 @@ use std::{
     sync::Arc,
 };
 
+mod byte_io;
 mod desc;
 mod error;
+mod shader_cross_compile;
 mod shader_reflection;
+mod shader_reflection_cache;
+mod shader_variant;
 mod types;
 mod vulkan;
 //
@@ -171,6 +175,11 @@ pub trait Command<A: Api>: Sized {
         first_vertex: i32,
     );
     unsafe fn cmd_dispatch(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32);
+    // dispatches enough groups of `workgroup_size` to cover `thread_count`
+    unsafe fn cmd_dispatch_threads(&mut self, workgroup_size: [u32; 3], thread_count: [u32; 3]) {
+        let [x, y, z] = shader_reflection::dispatch_group_counts(workgroup_size, thread_count);
+        self.cmd_dispatch(x, y, z);
+    }
 
     // transition commands
     unsafe fn cmd_resource_barrier(&self);
@@ -0,0 +1,187 @@
+use crate::{
+    desc::{BinaryShaderStageDesc, BindingRemap, CrossCompileDesc},
+    shader_reflection::{reflect_entry_points, ExecutionModel},
+    types::CrossCompileTarget,
+};
+use spirv_cross::{glsl, hlsl, msl, spirv, ErrorCode};
+
+fn unsupported(what: &str, version: u32) -> ErrorCode {
+    ErrorCode::CompilationError(format!("unsupported {} {}", what, version))
+}
+
+fn glsl_version(version: u32, es: bool) -> Result<glsl::Version, ErrorCode> {
+    let version = match (version, es) {
+        (110, false) => glsl::Version::V1_10,
+        (120, false) => glsl::Version::V1_20,
+        (130, false) => glsl::Version::V1_30,
+        (140, false) => glsl::Version::V1_40,
+        (150, false) => glsl::Version::V1_50,
+        (330, false) => glsl::Version::V3_30,
+        (400, false) => glsl::Version::V4_00,
+        (410, false) => glsl::Version::V4_10,
+        (420, false) => glsl::Version::V4_20,
+        (430, false) => glsl::Version::V4_30,
+        (440, false) => glsl::Version::V4_40,
+        (450, false) => glsl::Version::V4_50,
+        (460, false) => glsl::Version::V4_60,
+        (100, true) => glsl::Version::V1_00Es,
+        (300, true) => glsl::Version::V3_00Es,
+        (310, true) => glsl::Version::V3_10Es,
+        (320, true) => glsl::Version::V3_20Es,
+        (version, false) => return Err(unsupported("glsl version", version)),
+        (version, true) => return Err(unsupported("essl version", version)),
+    };
+    Ok(version)
+}
+
+fn hlsl_shader_model(shader_model: u32) -> Result<hlsl::ShaderModel, ErrorCode> {
+    let shader_model = match shader_model {
+        30 => hlsl::ShaderModel::V3_0,
+        40 => hlsl::ShaderModel::V4_0,
+        41 => hlsl::ShaderModel::V4_1,
+        50 => hlsl::ShaderModel::V5_0,
+        51 => hlsl::ShaderModel::V5_1,
+        60 => hlsl::ShaderModel::V6_0,
+        _ => return Err(unsupported("hlsl shader model", shader_model)),
+    };
+    Ok(shader_model)
+}
+
+fn msl_version(version: u32) -> Result<msl::Version, ErrorCode> {
+    let version = match version {
+        10000 => msl::Version::V1_0,
+        10100 => msl::Version::V1_1,
+        10200 => msl::Version::V1_2,
+        20000 => msl::Version::V2_0,
+        20100 => msl::Version::V2_1,
+        20200 => msl::Version::V2_2,
+        _ => return Err(unsupported("msl version", version)),
+    };
+    Ok(version)
+}
+
+fn spirv_execution_model(execution_model: ExecutionModel) -> Option<spirv::ExecutionModel> {
+    match execution_model {
+        ExecutionModel::ExecutionModelVertex => Some(spirv::ExecutionModel::Vertex),
+        ExecutionModel::ExecutionModelTessellationControl => {
+            Some(spirv::ExecutionModel::TessellationControl)
+        }
+        ExecutionModel::ExecutionModelTessellationEvaluation => {
+            Some(spirv::ExecutionModel::TessellationEvaluation)
+        }
+        ExecutionModel::ExecutionModelGeometry => Some(spirv::ExecutionModel::Geometry),
+        ExecutionModel::ExecutionModelFragment => Some(spirv::ExecutionModel::Fragment),
+        ExecutionModel::ExecutionModelGLCompute => Some(spirv::ExecutionModel::GlCompute),
+        ExecutionModel::ExecutionModelKernel => Some(spirv::ExecutionModel::Kernel),
+        _ => None,
+    }
+}
+
+// moves every resource at a remapped set and binding, glsl and msl drop the set
+fn remap_bindings<TTarget>(
+    ast: &mut spirv::Ast<TTarget>,
+    binding_remaps: &[BindingRemap],
+) -> Result<(), ErrorCode>
+where
+    TTarget: spirv::Target,
+    spirv::Ast<TTarget>: spirv::Parse<TTarget> + spirv::Compile<TTarget>,
+{
+    let resources = ast.get_shader_resources()?;
+    for resource in resources
+        .uniform_buffers
+        .iter()
+        .chain(&resources.storage_buffers)
+        .chain(&resources.separate_images)
+        .chain(&resources.storage_images)
+        .chain(&resources.separate_samplers)
+        .chain(&resources.sampled_images)
+        .chain(&resources.subpass_inputs)
+    {
+        let set = ast.get_decoration(resource.id, spirv::Decoration::DescriptorSet)?;
+        let binding = ast.get_decoration(resource.id, spirv::Decoration::Binding)?;
+        let remap = binding_remaps
+            .iter()
+            .find(|remap| remap.set == set && remap.binding == binding);
+        if let Some(remap) = remap {
+            ast.set_decoration(resource.id, spirv::Decoration::DescriptorSet, remap.new_set)?;
+            ast.set_decoration(resource.id, spirv::Decoration::Binding, remap.new_binding)?;
+        }
+    }
+    Ok(())
+}
+
+/// Translates the SPIR-V of `stage` to source for `desc.target`, for porting tools and for
+/// reading shaders while debugging.
+///
+/// # Safety
+///
+/// `stage.byte_code` must point to `stage.byte_code_size` bytes of SPIR-V.
+pub unsafe fn cross_compile(
+    stage: &BinaryShaderStageDesc,
+    desc: &CrossCompileDesc,
+) -> Result<String, ErrorCode> {
+    let words = std::slice::from_raw_parts(stage.byte_code, stage.byte_code_size as usize / 4);
+    let entry_point_name = stage.entry_point_name.to_string_lossy().into_owned();
+    let execution_model = reflect_entry_points(words)?
+        .into_iter()
+        .find(|entry_point| entry_point.name == entry_point_name)
+        .and_then(|entry_point| spirv_execution_model(entry_point.execution_model))
+        .ok_or_else(|| {
+            ErrorCode::CompilationError(format!(
+                "entry point {} not found or not cross compilable",
+                entry_point_name
+            ))
+        })?;
+    let entry_point = Some((entry_point_name, execution_model));
+    let module = spirv::Module::from_words(words);
+
+    match desc.target {
+        CrossCompileTarget::Glsl { version } | CrossCompileTarget::Essl { version } => {
+            let es = matches!(desc.target, CrossCompileTarget::Essl { .. });
+            let mut ast = spirv::Ast::<glsl::Target>::parse(&module)?;
+            let mut options = glsl::CompilerOptions::default();
+            options.version = glsl_version(version, es)?;
+            options.entry_point = entry_point;
+            ast.set_compiler_options(&options)?;
+            remap_bindings(&mut ast, &desc.binding_remaps)?;
+            ast.compile()
+        }
+        CrossCompileTarget::Hlsl { shader_model } => {
+            let mut ast = spirv::Ast::<hlsl::Target>::parse(&module)?;
+            let mut options = hlsl::CompilerOptions::default();
+            options.shader_model = hlsl_shader_model(shader_model)?;
+            options.entry_point = entry_point;
+            ast.set_compiler_options(&options)?;
+            remap_bindings(&mut ast, &desc.binding_remaps)?;
+            ast.compile()
+        }
+        CrossCompileTarget::Msl { version, ios } => {
+            let mut ast = spirv::Ast::<msl::Target>::parse(&module)?;
+            let mut options = msl::CompilerOptions::default();
+            options.version = msl_version(version)?;
+            options.platform = if ios {
+                msl::Platform::iOS
+            } else {
+                msl::Platform::macOS
+            };
+            for remap in &desc.binding_remaps {
+                options.resource_binding_overrides.insert(
+                    msl::ResourceBindingLocation {
+                        stage: execution_model,
+                        desc_set: remap.set,
+                        binding: remap.binding,
+                    },
+                    msl::ResourceBinding {
+                        buffer_id: remap.new_binding,
+                        texture_id: remap.new_binding,
+                        sampler_id: remap.new_binding,
+                        count: 0,
+                    },
+                );
+            }
+            options.entry_point = entry_point;
+            ast.set_compiler_options(&options)?;
+            ast.compile()
+        }
+    }
+}
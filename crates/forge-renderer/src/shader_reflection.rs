@@ -1,12 +1,15 @@
-use crate::shader_reflection::ResourceType::{TypeStorageTexelBuffers, TypeUniformTexelBuffers};
+use crate::{
+    desc::{SpecConstantDesc, VertexAttrib, VertexLayout},
+    types::{ShaderStage, ShaderStageFlags, SpecConstantValue},
+};
+use forge_image_format::ImageFormat;
 use spirv_cross::{
-    glsl, spirv,
-    spirv::{Ast, Compile, Dim, Parse, Resource, ShaderResources, Target, Type},
+    spirv::{Ast, Compile, Decoration, Dim, Parse, Resource, Target, Type},
     ErrorCode,
 };
-use std::{ffi::CString, marker::PhantomData};
+use std::collections::{HashMap, HashSet};
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ResourceType {
     TypeStageInputs = 0,
     TypeStageOutputs,
@@ -24,7 +27,45 @@ pub enum ResourceType {
     TypeCount,
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum BaseType {
+    BaseTypeUnknown,
+    BaseTypeBool,
+    BaseTypeInt8,
+    BaseTypeUInt8,
+    BaseTypeInt16,
+    BaseTypeUInt16,
+    BaseTypeInt,
+    BaseTypeUInt,
+    BaseTypeInt64,
+    BaseTypeUInt64,
+    BaseTypeHalf,
+    BaseTypeFloat,
+    BaseTypeDouble,
+    BaseTypeStruct,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ExecutionModel {
+    ExecutionModelVertex,
+    ExecutionModelTessellationControl,
+    ExecutionModelTessellationEvaluation,
+    ExecutionModelGeometry,
+    ExecutionModelFragment,
+    ExecutionModelGLCompute,
+    ExecutionModelKernel,
+    ExecutionModelTask,
+    ExecutionModelMesh,
+    ExecutionModelRayGeneration,
+    ExecutionModelIntersection,
+    ExecutionModelAnyHit,
+    ExecutionModelClosestHit,
+    ExecutionModelMiss,
+    ExecutionModelCallable,
+    ExecutionModelUnknown,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ResourceDim {
     DimUndefined,
     DimBuffer,
@@ -51,148 +92,1356 @@ pub struct SprivResource {
 
     pub set: u32,
 
+    // location for stage inputs and outputs
     pub binding: u32,
 
+    // size of every array dimension from the innermost, 0 for runtime sized arrays
     pub array_size: Vec<u32>,
 
+    // accessed by a function reachable from the entry point
     pub is_used: bool,
+
+    // declared size of a uniform, storage or push constant block without its runtime sized
+    // array, 0 for other resources
+    pub size: u32,
+
+    // members of a uniform, storage or push constant block
+    pub members: Vec<SprivStructMember>,
+}
+
+pub struct SprivStructMember {
+    pub name: String,
+
+    pub base_type: BaseType,
+
+    // components of a vector or rows of a matrix
+    pub vec_size: u32,
+
+    // 1 unless the member is a matrix
+    pub columns: u32,
+
+    // from the start of the containing struct
+    pub offset: u32,
+
+    // including every array element, 0 for runtime sized arrays
+    pub size: u32,
+
+    // size of every array dimension from the innermost, 0 for runtime sized arrays
+    pub array_size: Vec<u32>,
+
+    // distance between elements of the outermost array dimension
+    pub array_stride: u32,
+
+    pub matrix_stride: u32,
+
+    pub row_major: bool,
+
+    // members of a struct member
+    pub members: Vec<SprivStructMember>,
+}
+
+pub struct SprivSpecConstant {
+    pub name: String,
+
+    // SpecId decoration
+    pub constant_id: u32,
+
+    pub base_type: BaseType,
+
+    // bits of the default value, booleans are 0 or 1
+    pub default_value: u64,
+}
+
+pub struct SprivVertexInput {
+    pub name: String,
+
+    pub location: u32,
+
+    pub base_type: BaseType,
+
+    pub vec_size: u32,
+
+    // consecutive locations taken, one per matrix column and array element
+    pub location_count: u32,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct SprivWorkgroupSize {
+    // threads per workgroup along x, y and z with the default specialization constant values
+    pub size: [u32; 3],
+
+    // specialization constant id driving each dimension
+    pub spec_ids: [Option<u32>; 3],
+}
+
+pub struct SprivEntryPoint {
+    pub name: String,
+
+    pub execution_model: ExecutionModel,
+
+    // compute, kernel, task and mesh entry points only
+    pub workgroup_size: Option<SprivWorkgroupSize>,
 }
 
 pub struct ShaderReflection {
+    pub entry_point: SprivEntryPoint,
+
     pub resources: Vec<SprivResource>,
+
+    pub spec_constants: Vec<SprivSpecConstant>,
+
+    // inputs of a vertex shader entry point, empty for other stages
+    pub vertex_inputs: Vec<SprivVertexInput>,
 }
 
-pub fn get_shader_resource<TTarget>(ast: &Ast<TTarget>) -> Result<ShaderReflection, ErrorCode>
+pub struct PipelineResource {
+    pub name: String,
+
+    pub resource_type: ResourceType,
+
+    pub resource_dim: ResourceDim,
+
+    pub set: u32,
+
+    pub binding: u32,
+
+    // size of every array dimension from the innermost, 0 for runtime sized arrays
+    pub array_size: Vec<u32>,
+
+    // block size, the largest of every stage for push constants
+    pub size: u32,
+
+    // stages declaring the resource
+    pub stages: ShaderStageFlags,
+
+    // stages with an entry point accessing the resource
+    pub used_stages: ShaderStageFlags,
+}
+
+pub struct PipelineReflection {
+    pub stages: ShaderStageFlags,
+
+    // descriptors ordered by set and binding, push constants last
+    pub resources: Vec<PipelineResource>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ReflectionMergeError {
+    // entry point of an execution model the pipeline has no stage for
+    UnsupportedStage {
+        entry_point: String,
+    },
+    DuplicateStage {
+        stage: ShaderStage,
+    },
+    // the same set and binding declared with a different type, dimension, array size or size
+    BindingConflict {
+        set: u32,
+        binding: u32,
+        name: String,
+        other_name: String,
+        stages: ShaderStageFlags,
+    },
+}
+
+pub struct SpecializationMapEntry {
+    pub constant_id: u32,
+
+    // into `SpecializationData::data`
+    pub offset: u32,
+
+    pub size: u32,
+}
+
+// constant values in the layout of VkSpecializationInfo
+pub struct SpecializationData {
+    pub map_entries: Vec<SpecializationMapEntry>,
+
+    pub data: Vec<u8>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum SpecConstantError {
+    UnknownName {
+        name: String,
+    },
+    UnknownId {
+        id: u32,
+    },
+    // the value isn't of the type the constant is declared with
+    TypeMismatch {
+        constant_id: u32,
+        base_type: BaseType,
+        value: SpecConstantValue,
+    },
+}
+
+impl ShaderReflection {
+    pub fn find_resource(&self, name: &str) -> Option<&SprivResource> {
+        self.resources.iter().find(|resource| resource.name == name)
+    }
+
+    /// Builds the specialization data for `values`, which are matched by name or, when the name
+    /// is empty, by constant id. Later values override earlier ones for the same constant, so
+    /// the constants of a shader can be followed by the ones of a pipeline. Constants without a
+    /// value keep their default and are left out.
+    pub fn specialize(
+        &self,
+        values: &[SpecConstantDesc],
+    ) -> Result<SpecializationData, Vec<SpecConstantError>> {
+        let mut errors = vec![];
+        let mut resolved: Vec<(u32, Vec<u8>)> = vec![];
+        for value in values {
+            let name = value.name.to_string_lossy();
+            let spec_constant = self.spec_constants.iter().find(|spec_constant| {
+                if name.is_empty() {
+                    spec_constant.constant_id == value.id
+                } else {
+                    spec_constant.name == name
+                }
+            });
+            let spec_constant = match spec_constant {
+                Some(spec_constant) => spec_constant,
+                None if name.is_empty() => {
+                    errors.push(SpecConstantError::UnknownId { id: value.id });
+                    continue;
+                }
+                None => {
+                    errors.push(SpecConstantError::UnknownName {
+                        name: name.into_owned(),
+                    });
+                    continue;
+                }
+            };
+            let bytes = match (spec_constant.base_type, value.value) {
+                (BaseType::BaseTypeBool, SpecConstantValue::Bool(value)) => {
+                    (value as u32).to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeInt8, SpecConstantValue::Int8(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeUInt8, SpecConstantValue::UInt8(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeInt16, SpecConstantValue::Int16(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeUInt16, SpecConstantValue::UInt16(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeInt, SpecConstantValue::Int(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeUInt, SpecConstantValue::UInt(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeHalf, SpecConstantValue::Half(value)) => {
+                    forge_math::packing::f32_to_f16(value)
+                        .to_le_bytes()
+                        .to_vec()
+                }
+                (BaseType::BaseTypeFloat, SpecConstantValue::Float(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeInt64, SpecConstantValue::Int64(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeUInt64, SpecConstantValue::UInt64(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (BaseType::BaseTypeDouble, SpecConstantValue::Double(value)) => {
+                    value.to_le_bytes().to_vec()
+                }
+                (base_type, value) => {
+                    errors.push(SpecConstantError::TypeMismatch {
+                        constant_id: spec_constant.constant_id,
+                        base_type,
+                        value,
+                    });
+                    continue;
+                }
+            };
+            match resolved
+                .iter_mut()
+                .find(|(constant_id, _)| *constant_id == spec_constant.constant_id)
+            {
+                Some((_, resolved_bytes)) => *resolved_bytes = bytes,
+                None => resolved.push((spec_constant.constant_id, bytes)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut specialization = SpecializationData {
+            map_entries: vec![],
+            data: vec![],
+        };
+        for (constant_id, bytes) in resolved {
+            // keep every value aligned to its size
+            while !specialization.data.len().is_multiple_of(bytes.len()) {
+                specialization.data.push(0);
+            }
+            specialization.map_entries.push(SpecializationMapEntry {
+                constant_id,
+                offset: specialization.data.len() as u32,
+                size: bytes.len() as u32,
+            });
+            specialization.data.extend(bytes);
+        }
+        Ok(specialization)
+    }
+}
+
+impl ExecutionModel {
+    fn from_spirv(execution_model: u32) -> ExecutionModel {
+        match execution_model {
+            0 => ExecutionModel::ExecutionModelVertex,
+            1 => ExecutionModel::ExecutionModelTessellationControl,
+            2 => ExecutionModel::ExecutionModelTessellationEvaluation,
+            3 => ExecutionModel::ExecutionModelGeometry,
+            4 => ExecutionModel::ExecutionModelFragment,
+            5 => ExecutionModel::ExecutionModelGLCompute,
+            6 => ExecutionModel::ExecutionModelKernel,
+            5267 | 5364 => ExecutionModel::ExecutionModelTask,
+            5268 | 5365 => ExecutionModel::ExecutionModelMesh,
+            5313 => ExecutionModel::ExecutionModelRayGeneration,
+            5314 => ExecutionModel::ExecutionModelIntersection,
+            5315 => ExecutionModel::ExecutionModelAnyHit,
+            5316 => ExecutionModel::ExecutionModelClosestHit,
+            5317 => ExecutionModel::ExecutionModelMiss,
+            5318 => ExecutionModel::ExecutionModelCallable,
+            _ => ExecutionModel::ExecutionModelUnknown,
+        }
+    }
+
+    /// Pipeline stage the entry point runs in, none for the models the renderer has no stage for.
+    pub fn stage(&self) -> Option<ShaderStage> {
+        match self {
+            ExecutionModel::ExecutionModelVertex => Some(ShaderStage::Vertex),
+            ExecutionModel::ExecutionModelTessellationControl => {
+                Some(ShaderStage::TesselationControl)
+            }
+            ExecutionModel::ExecutionModelTessellationEvaluation => {
+                Some(ShaderStage::TesselationEvaluation)
+            }
+            ExecutionModel::ExecutionModelGeometry => Some(ShaderStage::Geometry),
+            ExecutionModel::ExecutionModelFragment => Some(ShaderStage::Fragment),
+            ExecutionModel::ExecutionModelGLCompute => Some(ShaderStage::Compute),
+            ExecutionModel::ExecutionModelRayGeneration
+            | ExecutionModel::ExecutionModelIntersection
+            | ExecutionModel::ExecutionModelAnyHit
+            | ExecutionModel::ExecutionModelClosestHit
+            | ExecutionModel::ExecutionModelMiss
+            | ExecutionModel::ExecutionModelCallable => Some(ShaderStage::Raytracing),
+            _ => None,
+        }
+    }
+
+    fn has_workgroup(&self) -> bool {
+        matches!(
+            self,
+            ExecutionModel::ExecutionModelGLCompute
+                | ExecutionModel::ExecutionModelKernel
+                | ExecutionModel::ExecutionModelTask
+                | ExecutionModel::ExecutionModelMesh
+        )
+    }
+}
+
+impl SprivWorkgroupSize {
+    /// Size with the values `specialization` has for the size constants overriding their
+    /// defaults, so the pipeline and the dispatch use the same constants. `specialization` comes
+    /// from `ShaderReflection::specialize`.
+    pub fn resolve(&self, specialization: &SpecializationData) -> [u32; 3] {
+        let mut size = self.size;
+        for (dimension, spec_id) in size.iter_mut().zip(self.spec_ids) {
+            let value = specialization
+                .map_entries
+                .iter()
+                .find(|entry| Some(entry.constant_id) == spec_id)
+                .and_then(|entry| {
+                    let offset = entry.offset as usize;
+                    specialization
+                        .data
+                        .get(offset..offset + entry.size as usize)
+                })
+                // size constants are 32 bit integers
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u32::from_le_bytes);
+            if let Some(value) = value {
+                *dimension = value;
+            }
+        }
+        size
+    }
+
+    /// Group counts for `cmd_dispatch` covering `thread_count` threads along x, y and z.
+    pub fn group_counts(
+        &self,
+        thread_count: [u32; 3],
+        specialization: &SpecializationData,
+    ) -> [u32; 3] {
+        dispatch_group_counts(self.resolve(specialization), thread_count)
+    }
+}
+
+/// Workgroups needed to run `thread_count` threads along each axis, the last group along an
+/// axis is partially outside when the count isn't a multiple of the workgroup size.
+pub fn dispatch_group_counts(workgroup_size: [u32; 3], thread_count: [u32; 3]) -> [u32; 3] {
+    [0, 1, 2].map(|axis| forge_math::div_ceil(thread_count[axis], workgroup_size[axis].max(1)))
+}
+
+impl SprivResource {
+    /// Finds a block member by a path like `lights[2].color` and returns it with its offset
+    /// from the start of the block. Indices step over the outermost array dimension.
+    pub fn find_member(&self, path: &str) -> Option<(u32, &SprivStructMember)> {
+        let mut members = &self.members;
+        let mut offset = 0;
+        let mut found = None;
+        for part in path.split('.') {
+            let (name, index) = match part.split_once('[') {
+                Some((name, index)) => (name, Some(index.strip_suffix(']')?.parse::<u32>().ok()?)),
+                None => (part, None),
+            };
+            let member = members.iter().find(|member| member.name == name)?;
+            if let Some(index) = index {
+                match member.array_size.last() {
+                    Some(0) => {}
+                    Some(size) if index < *size => {}
+                    _ => return None,
+                }
+                offset += index * member.array_stride;
+            }
+            offset += member.offset;
+            members = &member.members;
+            found = Some(member);
+        }
+        Some((offset, found?))
+    }
+}
+
+fn reflect_resource_type(spirv_type: &Type, resource_type: ResourceType) -> ResourceType {
+    match spirv_type {
+        Type::Image { image, .. } | Type::SampledImage { image, .. } => match image.dim {
+            Dim::DimBuffer => match resource_type {
+                ResourceType::TypeImages => ResourceType::TypeUniformTexelBuffers,
+                ResourceType::TypeStorageImages => ResourceType::TypeStorageTexelBuffers,
+                _ => resource_type,
+            },
+            _ => resource_type,
+        },
+        _ => resource_type,
+    }
+}
+
+fn reflect_resource_dim(spirv_type: &Type) -> ResourceDim {
+    match spirv_type {
+        Type::Image { image, .. } | Type::SampledImage { image, .. } => match image.dim {
+            Dim::DimBuffer => ResourceDim::DimBuffer,
+            Dim::Dim1D => {
+                if image.arrayed {
+                    ResourceDim::DimTexture1DArray
+                } else {
+                    ResourceDim::DimTexture1D
+                }
+            }
+            Dim::Dim2D => match (image.ms, image.arrayed) {
+                (true, true) => ResourceDim::DimTexture2DMSArray,
+                (true, false) => ResourceDim::DimTexture2DMS,
+                (false, true) => ResourceDim::DimTexture2DArray,
+                (false, false) => ResourceDim::DimTexture2D,
+            },
+            Dim::Dim3D => ResourceDim::DimTexture3D,
+            Dim::DimCube => {
+                if image.arrayed {
+                    ResourceDim::DimTextureCubeArray
+                } else {
+                    ResourceDim::DimTextureCube
+                }
+            }
+            _ => ResourceDim::DimUndefined,
+        },
+        _ => ResourceDim::DimUndefined,
+    }
+}
+
+// base type, vector size and columns
+fn reflect_base_type(spirv_type: &Type) -> (BaseType, u32, u32) {
+    match *spirv_type {
+        Type::Boolean {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeBool, vecsize, columns),
+        Type::SByte { vecsize, .. } => (BaseType::BaseTypeInt8, vecsize, 1),
+        Type::UByte { vecsize, .. } => (BaseType::BaseTypeUInt8, vecsize, 1),
+        Type::Short { vecsize, .. } => (BaseType::BaseTypeInt16, vecsize, 1),
+        Type::UShort { vecsize, .. } => (BaseType::BaseTypeUInt16, vecsize, 1),
+        Type::Int {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeInt, vecsize, columns),
+        Type::UInt {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeUInt, vecsize, columns),
+        Type::Int64 { vecsize, .. } => (BaseType::BaseTypeInt64, vecsize, 1),
+        Type::UInt64 { vecsize, .. } => (BaseType::BaseTypeUInt64, vecsize, 1),
+        Type::Half {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeHalf, vecsize, columns),
+        Type::Float {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeFloat, vecsize, columns),
+        Type::Double {
+            vecsize, columns, ..
+        } => (BaseType::BaseTypeDouble, vecsize, columns),
+        Type::Struct { .. } => (BaseType::BaseTypeStruct, 1, 1),
+        _ => (BaseType::BaseTypeUnknown, 1, 1),
+    }
+}
+
+// members of the struct type `struct_id`
+fn reflect_struct_members<TTarget>(
+    ast: &Ast<TTarget>,
+    struct_id: u32,
+    array_types: &HashMap<u32, (u32, u32)>,
+) -> Result<Vec<SprivStructMember>, ErrorCode>
 where
     TTarget: Target,
     Ast<TTarget>: Parse<TTarget> + Compile<TTarget>,
 {
-    let mut shader_reflection = ShaderReflection { resources: vec![] };
+    let member_types = match ast.get_type(struct_id)? {
+        Type::Struct { member_types, .. } => member_types,
+        _ => return Ok(vec![]),
+    };
+    let mut members = vec![];
+    for (index, member_type_id) in member_types.into_iter().enumerate() {
+        let index = index as u32;
+        let member_type = ast.get_type(member_type_id)?;
+        let (base_type, vec_size, columns) = reflect_base_type(&member_type);
+        // arrays of structs have their own type id, names and decorations are on the struct
+        let mut element_id = member_type_id;
+        while let Some((element, _)) = array_types.get(&element_id) {
+            element_id = *element;
+        }
+        members.push(SprivStructMember {
+            name: ast.get_member_name(struct_id, index)?,
+            base_type,
+            vec_size,
+            columns,
+            offset: ast.get_member_decoration(struct_id, index, Decoration::Offset)?,
+            size: ast.get_declared_struct_member_size(struct_id, index)?,
+            array_size: reflect_array_size(&member_type),
+            array_stride: ast.get_decoration(member_type_id, Decoration::ArrayStride)?,
+            matrix_stride: ast.get_member_decoration(struct_id, index, Decoration::MatrixStride)?,
+            row_major: ast.get_member_decoration(struct_id, index, Decoration::RowMajor)? != 0,
+            members: match base_type {
+                BaseType::BaseTypeStruct => reflect_struct_members(ast, element_id, array_types)?,
+                _ => vec![],
+            },
+        });
+    }
+    Ok(members)
+}
+
+fn reflect_array_size(spirv_type: &Type) -> Vec<u32> {
+    match spirv_type {
+        Type::Boolean { array, .. }
+        | Type::Char { array, .. }
+        | Type::Int { array, .. }
+        | Type::UInt { array, .. }
+        | Type::Int64 { array, .. }
+        | Type::UInt64 { array, .. }
+        | Type::AtomicCounter { array, .. }
+        | Type::Half { array, .. }
+        | Type::Float { array, .. }
+        | Type::Double { array, .. }
+        | Type::Struct { array, .. }
+        | Type::Image { array, .. }
+        | Type::SampledImage { array, .. }
+        | Type::Sampler { array, .. }
+        | Type::SByte { array, .. }
+        | Type::UByte { array, .. }
+        | Type::Short { array, .. }
+        | Type::UShort { array, .. } => array.clone(),
+        _ => vec![],
+    }
+}
 
-    let mut reflect_bound_resource = |reflection: &mut ShaderReflection,
-                                      ast: &mut Ast<TTarget>,
-                                      resources: &Vec<Resource>,
-                                      resource_type: ResourceType|
+/// Reflects every resource `ast` declares. `spirv` is the module `ast` was parsed from, it's
+/// scanned for the resources `entry_point` uses and for acceleration structures, which
+/// `ShaderResources` doesn't list.
+pub fn get_shader_resource<TTarget>(
+    ast: &mut Ast<TTarget>,
+    spirv: &[u32],
+    entry_point: &str,
+) -> Result<ShaderReflection, ErrorCode>
+where
+    TTarget: Target,
+    Ast<TTarget>: Parse<TTarget> + Compile<TTarget>,
+{
+    let instructions = spirv_instructions(spirv)?;
+    let entry_point = entry_points(&instructions)?
+        .into_iter()
+        .find(|entry| entry.name == entry_point)
+        .ok_or_else(|| {
+            ErrorCode::CompilationError(format!("entry point {} not found", entry_point))
+        })?;
+    let mut shader_reflection = ShaderReflection {
+        entry_point,
+        resources: vec![],
+        spec_constants: reflect_spec_constants(&instructions),
+        vertex_inputs: vec![],
+    };
+    let active_variables = active_variables(&instructions, &shader_reflection.entry_point.name)?;
+    let array_types = array_types(&instructions);
+
+    let reflect_bound_resource = |reflection: &mut ShaderReflection,
+                                  ast: &mut Ast<TTarget>,
+                                  resources: &Vec<Resource>,
+                                  resource_type: ResourceType|
      -> Result<(), ErrorCode> {
         for resource in resources {
             let spirv_type = ast.get_type(resource.type_id)?;
             let mut resource_name = ast.get_name(resource.id)?;
+            if resource_name.is_empty() {
+                // blocks declared without an instance name
+                resource_name = resource.name.clone();
+            }
+            let (set, binding) = match resource_type {
+                ResourceType::TypeStageInputs | ResourceType::TypeStageOutputs => {
+                    (0, ast.get_decoration(resource.id, Decoration::Location)?)
+                }
+                ResourceType::TypePushConstant => (0, 0),
+                _ => (
+                    ast.get_decoration(resource.id, Decoration::DescriptorSet)?,
+                    ast.get_decoration(resource.id, Decoration::Binding)?,
+                ),
+            };
+            let (size, members) = match resource_type {
+                ResourceType::TypeUniformBuffers
+                | ResourceType::TypeStorageBuffers
+                | ResourceType::TypePushConstant => (
+                    ast.get_declared_struct_size(resource.base_type_id)?,
+                    reflect_struct_members(ast, resource.base_type_id, &array_types)?,
+                ),
+                _ => (0, vec![]),
+            };
 
-            let mut resource = SprivResource {
+            reflection.resources.push(SprivResource {
                 name: resource_name,
                 code: resource.clone(),
-                resource_type: {
-                    match &spirv_type {
-                        Type::Image { image, .. } | Type::SampledImage { image, .. } => {
-                            match image.dim {
-                                Dim::DimBuffer => match resource_type {
-                                    ResourceType::TypeImages => TypeUniformTexelBuffers,
-                                    ResourceType::TypeStorageImages => TypeStorageTexelBuffers,
-                                    _ => resource_type,
-                                },
-                                _ => resource_type,
-                            }
-                        }
-                        _ => resource_type,
-                    }
-                },
-                resource_dim: {
-                    match &spirv_type {
-                        Type::Image { image, .. } | Type::SampledImage { image, .. } => {
-                            match image.dim {
-                                Dim::DimBuffer => ResourceDim::DimBuffer,
-                                Dim::Dim1D => {
-                                    if image.arrayed {
-                                        ResourceDim::DimTexture1DArray
-                                    } else {
-                                        ResourceDim::DimTexture1D
-                                    }
-                                }
-                                Dim::Dim2D => {
-                                    if image.ms {
-                                        if image.arrayed {
-                                            ResourceDim::DimTexture2DMSArray
-                                        } else {
-                                            ResourceDim::DimTexture2DMS
-                                        }
-                                    } else {
-                                        if image.arrayed {
-                                            ResourceDim::DimTexture2DArray
-                                        } else {
-                                            ResourceDim::DimTexture1D
-                                        }
-                                    }
-                                }
-                                Dim::Dim3D => ResourceDim::DimTexture3D,
-                                Dim::DimCube => {
-                                    if image.arrayed {
-                                        ResourceDim::DimTextureCubeArray
-                                    } else {
-                                        ResourceDim::DimTextureCube
-                                    }
-                                }
-                                _ => ResourceDim::DimUndefined,
-                            }
-                        }
-                        _ => ResourceDim::DimUndefined,
-                    }
-                },
-                set: 0,
-                binding: 0,
-                array_size: match &spirv_type {
-                    Type::Boolean { array, .. } => array.clone(),
-                    Type::Char { array, .. } => array.clone(),
-                    Type::Int { array, .. } => array.clone(),
-                    Type::UInt { array, .. } => array.clone(),
-                    Type::Int64 { array, .. } => array.clone(),
-                    Type::UInt64 { array, .. } => array.clone(),
-                    Type::AtomicCounter { array, .. } => array.clone(),
-                    Type::Half { array, .. } => array.clone(),
-                    Type::Float { array, .. } => array.clone(),
-                    Type::Double { array, .. } => array.clone(),
-                    Type::Struct { array, .. } => array.clone(),
-                    Type::Image { array, .. } => array.clone(),
-                    Type::SampledImage { array, .. } => array.clone(),
-                    Type::Sampler { array, .. } => array.clone(),
-                    Type::SByte { array, .. } => array.clone(),
-                    Type::UByte { array, .. } => array.clone(),
-                    Type::Short { array, .. } => array.clone(),
-                    Type::UShort { array, .. } => array.clone(),
-                    _ => vec![],
-                },
-                is_used: false,
-            };
+                resource_type: reflect_resource_type(&spirv_type, resource_type),
+                resource_dim: reflect_resource_dim(&spirv_type),
+                set,
+                binding,
+                array_size: reflect_array_size(&spirv_type),
+                is_used: active_variables.contains(&resource.id),
+                size,
+                members,
+            });
         }
         Ok(())
     };
 
-    let mut resource = ast.get_shader_resources()?;
+    let resources = ast.get_shader_resources()?;
+    for (resources, resource_type) in [
+        (&resources.stage_inputs, ResourceType::TypeStageInputs),
+        (&resources.stage_outputs, ResourceType::TypeStageOutputs),
+        (&resources.uniform_buffers, ResourceType::TypeUniformBuffers),
+        (&resources.storage_buffers, ResourceType::TypeStorageBuffers),
+        (&resources.separate_images, ResourceType::TypeImages),
+        (&resources.storage_images, ResourceType::TypeStorageImages),
+        (&resources.separate_samplers, ResourceType::TypeSamplers),
+        (
+            &resources.push_constant_buffers,
+            ResourceType::TypePushConstant,
+        ),
+        (&resources.subpass_inputs, ResourceType::TypeSubpassInputs),
+        (
+            &resources.sampled_images,
+            ResourceType::TypeCombinedSamplers,
+        ),
+    ] {
+        reflect_bound_resource(&mut shader_reflection, ast, resources, resource_type)?;
+    }
+
+    if shader_reflection.entry_point.execution_model == ExecutionModel::ExecutionModelVertex {
+        for resource in &resources.stage_inputs {
+            let spirv_type = ast.get_type(resource.type_id)?;
+            let (base_type, vec_size, columns) = reflect_base_type(&spirv_type);
+            let elements: u32 = reflect_array_size(&spirv_type).iter().product();
+            shader_reflection.vertex_inputs.push(SprivVertexInput {
+                name: ast.get_name(resource.id)?,
+                location: ast.get_decoration(resource.id, Decoration::Location)?,
+                base_type,
+                vec_size,
+                location_count: columns * elements,
+            });
+        }
+    }
+
+    for (mut resource, array_size) in acceleration_structures(&instructions, &array_types) {
+        resource.name = ast.get_name(resource.id)?;
+        shader_reflection.resources.push(SprivResource {
+            name: resource.name.clone(),
+            set: ast.get_decoration(resource.id, Decoration::DescriptorSet)?,
+            binding: ast.get_decoration(resource.id, Decoration::Binding)?,
+            resource_type: ResourceType::TypeAccelerationStructures,
+            resource_dim: ResourceDim::DimUndefined,
+            array_size,
+            is_used: active_variables.contains(&resource.id),
+            size: 0,
+            members: vec![],
+            code: resource,
+        });
+    }
     Ok(shader_reflection)
 }
 
-// impl <T> Ast<T> where
-//     Self: Parse<TTarget> + Compile<TTarget>,
-//     TTarget: Target,
-// {
-//
-// }
-
-// pub struct ReflectionUtil {
-// }
-
-// impl <T> ReflectionUtil {
-//     pub fn get_shader_resource<T>(ast: &mut Ast<T>) -> Result<ShaderReflection, ErrorCode> {
-//         let mut resource = ast.get_shader_resources()?;
-//
-//         Ok(ShaderReflection{})
-//     }
-//
-// }
-
-// pub fn get_shader_resource<T>(ast: &mut Ast<T>) -> Result<ShaderReflection, ErrorCode> where
-//     Self: Parse<TTarget> + Compile<TTarget>,
-//     T: Target {
-//     let mut resource = ast.get_shader_resources()?;
-//
-//     Ok(ShaderReflection {})
-// }
+/// Merges the reflections of the stages of a pipeline into one resource table. Resources are
+/// matched by set and binding, stage inputs and outputs are left out and the push constant
+/// blocks of every stage become a single entry.
+pub fn merge_shader_reflections(
+    reflections: &[&ShaderReflection],
+) -> Result<PipelineReflection, Vec<ReflectionMergeError>> {
+    let mut errors = vec![];
+    let mut pipeline = PipelineReflection {
+        stages: ShaderStageFlags::STAGE_NONE,
+        resources: vec![],
+    };
+    for reflection in reflections {
+        let shader_stage = match reflection.entry_point.execution_model.stage() {
+            Some(shader_stage) => shader_stage,
+            None => {
+                errors.push(ReflectionMergeError::UnsupportedStage {
+                    entry_point: reflection.entry_point.name.clone(),
+                });
+                continue;
+            }
+        };
+        let stage = ShaderStageFlags::from_bits_truncate(1 << shader_stage as u32);
+        // ray tracing stages share one flag and may come from several modules
+        if pipeline.stages.contains(stage) && stage != ShaderStageFlags::STAGE_RAYTRACING {
+            errors.push(ReflectionMergeError::DuplicateStage {
+                stage: shader_stage,
+            });
+            continue;
+        }
+        pipeline.stages |= stage;
+
+        for resource in &reflection.resources {
+            let is_push_constant = match resource.resource_type {
+                ResourceType::TypeStageInputs | ResourceType::TypeStageOutputs => continue,
+                ResourceType::TypePushConstant => true,
+                _ => false,
+            };
+            let used_stage = if resource.is_used {
+                stage
+            } else {
+                ShaderStageFlags::STAGE_NONE
+            };
+            let merged = pipeline.resources.iter_mut().find(|merged| {
+                (merged.resource_type == ResourceType::TypePushConstant) == is_push_constant
+                    && merged.set == resource.set
+                    && merged.binding == resource.binding
+            });
+            let merged = match merged {
+                Some(merged) => merged,
+                None => {
+                    pipeline.resources.push(PipelineResource {
+                        name: resource.name.clone(),
+                        resource_type: resource.resource_type,
+                        resource_dim: resource.resource_dim,
+                        set: resource.set,
+                        binding: resource.binding,
+                        array_size: resource.array_size.clone(),
+                        size: resource.size,
+                        stages: stage,
+                        used_stages: used_stage,
+                    });
+                    continue;
+                }
+            };
+
+            // stages may declare only the part of the push constants they read
+            let conflict = if is_push_constant {
+                merged.size = merged.size.max(resource.size);
+                false
+            } else {
+                merged.resource_type != resource.resource_type
+                    || merged.resource_dim != resource.resource_dim
+                    || merged.array_size != resource.array_size
+                    || merged.size != resource.size
+            };
+            if conflict {
+                errors.push(ReflectionMergeError::BindingConflict {
+                    set: resource.set,
+                    binding: resource.binding,
+                    name: merged.name.clone(),
+                    other_name: resource.name.clone(),
+                    stages: merged.stages | stage,
+                });
+            }
+            merged.stages |= stage;
+            merged.used_stages |= used_stage;
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    pipeline.resources.sort_by_key(|resource| {
+        (
+            resource.resource_type == ResourceType::TypePushConstant,
+            resource.set,
+            resource.binding,
+        )
+    });
+    Ok(pipeline)
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum VertexLayoutError {
+    // vertex input without an attribute
+    MissingAttrib {
+        name: String,
+        location: u32,
+    },
+    UnsupportedFormat {
+        location: u32,
+        format: ImageFormat,
+    },
+    // the format doesn't read as the numeric type of the input
+    ComponentTypeMismatch {
+        location: u32,
+        base_type: BaseType,
+        format: ImageFormat,
+    },
+    InvalidBinding {
+        location: u32,
+        binding: u32,
+    },
+    // the attribute reaches past the end of the vertex
+    OutsideStride {
+        location: u32,
+        offset: u32,
+        size: u32,
+        stride: u32,
+    },
+    TooManyBindings {
+        count: u32,
+        max: u32,
+    },
+}
+
+// numeric type a vertex format is read as and its component count
+fn vertex_format_components(format: ImageFormat) -> Option<(BaseType, u32)> {
+    use ImageFormat::*;
+    let components = match format {
+        R8_UNORM | R8_SNORM | R16_UNORM | R16_SNORM | R16_SFLOAT | R32_SFLOAT => {
+            (BaseType::BaseTypeFloat, 1)
+        }
+        R8G8_UNORM | R8G8_SNORM | R16G16_UNORM | R16G16_SNORM | R16G16_SFLOAT | R32G32_SFLOAT => {
+            (BaseType::BaseTypeFloat, 2)
+        }
+        R8G8B8_UNORM | R8G8B8_SNORM | B8G8R8_UNORM | B8G8R8_SNORM | R16G16B16_UNORM
+        | R16G16B16_SNORM | R16G16B16_SFLOAT | R32G32B32_SFLOAT | B10G11R11_UFLOAT => {
+            (BaseType::BaseTypeFloat, 3)
+        }
+        R8G8B8A8_UNORM | R8G8B8A8_SNORM | B8G8R8A8_UNORM | B8G8R8A8_SNORM | R16G16B16A16_UNORM
+        | R16G16B16A16_SNORM | R16G16B16A16_SFLOAT | R32G32B32A32_SFLOAT | A2R10G10B10_UNORM
+        | A2R10G10B10_SNORM | A2B10G10R10_UNORM | A2B10G10R10_SNORM => (BaseType::BaseTypeFloat, 4),
+        R8_UINT | R16_UINT | R32_UINT => (BaseType::BaseTypeUInt, 1),
+        R8G8_UINT | R16G16_UINT | R32G32_UINT => (BaseType::BaseTypeUInt, 2),
+        R8G8B8_UINT | B8G8R8_UINT | R16G16B16_UINT | R32G32B32_UINT => (BaseType::BaseTypeUInt, 3),
+        R8G8B8A8_UINT | B8G8R8A8_UINT | R16G16B16A16_UINT | R32G32B32A32_UINT
+        | A2R10G10B10_UINT | A2B10G10R10_UINT => (BaseType::BaseTypeUInt, 4),
+        R8_SINT | R16_SINT | R32_SINT => (BaseType::BaseTypeInt, 1),
+        R8G8_SINT | R16G16_SINT | R32G32_SINT => (BaseType::BaseTypeInt, 2),
+        R8G8B8_SINT | B8G8R8_SINT | R16G16B16_SINT | R32G32B32_SINT => (BaseType::BaseTypeInt, 3),
+        R8G8B8A8_SINT | B8G8R8A8_SINT | R16G16B16A16_SINT | R32G32B32A32_SINT
+        | A2R10G10B10_SINT | A2B10G10R10_SINT => (BaseType::BaseTypeInt, 4),
+        R64_UINT => (BaseType::BaseTypeUInt64, 1),
+        R64G64_UINT => (BaseType::BaseTypeUInt64, 2),
+        R64G64B64_UINT => (BaseType::BaseTypeUInt64, 3),
+        R64G64B64A64_UINT => (BaseType::BaseTypeUInt64, 4),
+        R64_SINT => (BaseType::BaseTypeInt64, 1),
+        R64G64_SINT => (BaseType::BaseTypeInt64, 2),
+        R64G64B64_SINT => (BaseType::BaseTypeInt64, 3),
+        R64G64B64A64_SINT => (BaseType::BaseTypeInt64, 4),
+        R64_SFLOAT => (BaseType::BaseTypeDouble, 1),
+        R64G64_SFLOAT => (BaseType::BaseTypeDouble, 2),
+        R64G64B64_SFLOAT => (BaseType::BaseTypeDouble, 3),
+        R64G64B64A64_SFLOAT => (BaseType::BaseTypeDouble, 4),
+        _ => return None,
+    };
+    Some(components)
+}
+
+// whether a vertex format read as `format_type` can feed an input of `input_type`, 8 and 16 bit
+// inputs take the 32 bit values converted
+fn vertex_types_compatible(input_type: BaseType, format_type: BaseType) -> bool {
+    let widen = |base_type: BaseType| match base_type {
+        BaseType::BaseTypeHalf => BaseType::BaseTypeFloat,
+        BaseType::BaseTypeInt8 | BaseType::BaseTypeInt16 => BaseType::BaseTypeInt,
+        BaseType::BaseTypeUInt8 | BaseType::BaseTypeUInt16 => BaseType::BaseTypeUInt,
+        _ => base_type,
+    };
+    widen(input_type) == format_type
+}
+
+/// Builds the vertex layout of a vertex shader from the attributes in `buffer_layout`.
+///
+/// Inputs are matched with the attribute of the same name, or the unnamed attribute at their
+/// location, inputs that take several locations always match by location. The result keeps the
+/// bindings of `buffer_layout` and has one attribute per input location, attributes the shader
+/// doesn't read are left out. Formats with fewer components than the input are allowed, vulkan
+/// fills in 0 for missing components and 1 for alpha.
+pub fn match_vertex_layout(
+    reflection: &ShaderReflection,
+    buffer_layout: &VertexLayout,
+    max_vertex_input_bindings: u32,
+) -> Result<VertexLayout, Vec<VertexLayoutError>> {
+    let mut errors = vec![];
+    if buffer_layout.bindings.len() as u32 > max_vertex_input_bindings {
+        errors.push(VertexLayoutError::TooManyBindings {
+            count: buffer_layout.bindings.len() as u32,
+            max: max_vertex_input_bindings,
+        });
+    }
+
+    let mut layout = VertexLayout {
+        bindings: buffer_layout.bindings.clone(),
+        attribs: vec![],
+    };
+    for input in &reflection.vertex_inputs {
+        for location in input.location..input.location + input.location_count {
+            let attrib = buffer_layout.attribs.iter().find(|attrib| {
+                if attrib.name.as_bytes().is_empty() {
+                    attrib.location == location
+                } else {
+                    input.location_count == 1 && attrib.name.as_bytes() == input.name.as_bytes()
+                }
+            });
+            let attrib = match attrib {
+                Some(attrib) => attrib,
+                None => {
+                    errors.push(VertexLayoutError::MissingAttrib {
+                        name: input.name.clone(),
+                        location,
+                    });
+                    continue;
+                }
+            };
+
+            match vertex_format_components(attrib.format) {
+                None => errors.push(VertexLayoutError::UnsupportedFormat {
+                    location,
+                    format: attrib.format,
+                }),
+                Some((format_type, _))
+                    if !vertex_types_compatible(input.base_type, format_type) =>
+                {
+                    errors.push(VertexLayoutError::ComponentTypeMismatch {
+                        location,
+                        base_type: input.base_type,
+                        format: attrib.format,
+                    })
+                }
+                Some(_) => {}
+            }
+            match buffer_layout.bindings.get(attrib.binding as usize) {
+                None => errors.push(VertexLayoutError::InvalidBinding {
+                    location,
+                    binding: attrib.binding,
+                }),
+                Some(binding) => {
+                    let size = attrib.format.bit_size_of_block() / 8;
+                    if binding.stride != 0 && attrib.offset + size > binding.stride {
+                        errors.push(VertexLayoutError::OutsideStride {
+                            location,
+                            offset: attrib.offset,
+                            size,
+                            stride: binding.stride,
+                        });
+                    }
+                }
+            }
+            layout.attribs.push(VertexAttrib {
+                location,
+                ..attrib.clone()
+            });
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(layout)
+}
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+const SPIRV_HEADER_WORDS: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_CONSTANT_COMPOSITE: u32 = 44;
+const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_SPEC_CONSTANT_COMPOSITE: u32 = 51;
+const OP_FUNCTION: u32 = 54;
+const OP_FUNCTION_END: u32 = 56;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_EXECUTION_MODE_ID: u32 = 331;
+const OP_TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+const EXECUTION_MODE_LOCAL_SIZE_ID: u32 = 38;
+
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BUILT_IN: u32 = 11;
+
+fn invalid_spirv(reason: &str) -> ErrorCode {
+    ErrorCode::CompilationError(format!("invalid SPIR-V: {}", reason))
+}
+
+// opcode and operands of every instruction after the header
+fn spirv_instructions(spirv: &[u32]) -> Result<Vec<(u32, &[u32])>, ErrorCode> {
+    if spirv.len() < SPIRV_HEADER_WORDS || spirv[0] != SPIRV_MAGIC {
+        return Err(invalid_spirv("missing header"));
+    }
+    let mut instructions = vec![];
+    let mut offset = SPIRV_HEADER_WORDS;
+    while offset < spirv.len() {
+        let word_count = (spirv[offset] >> 16) as usize;
+        if word_count == 0 || offset + word_count > spirv.len() {
+            return Err(invalid_spirv("truncated instruction"));
+        }
+        instructions.push((
+            spirv[offset] & 0xffff,
+            &spirv[offset + 1..offset + word_count],
+        ));
+        offset += word_count;
+    }
+    Ok(instructions)
+}
+
+// nul terminated UTF-8 literal packed four bytes per word
+fn spirv_literal_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|byte| *byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reflects the entry points `spirv` declares with their workgroup sizes. A size is taken from
+/// the `WorkgroupSize` built-in when the module has one, otherwise from the `LocalSize` or
+/// `LocalSizeId` execution mode of the entry point.
+pub fn reflect_entry_points(spirv: &[u32]) -> Result<Vec<SprivEntryPoint>, ErrorCode> {
+    entry_points(&spirv_instructions(spirv)?)
+}
+
+fn entry_points(instructions: &[(u32, &[u32])]) -> Result<Vec<SprivEntryPoint>, ErrorCode> {
+    // entry function, execution model and name
+    let mut entry_points = vec![];
+    // entry function to the size literals or constant ids
+    let mut local_sizes: HashMap<u32, [u32; 3]> = HashMap::new();
+    let mut local_size_ids: HashMap<u32, [u32; 3]> = HashMap::new();
+    let mut workgroup_size_builtin = None;
+    let mut spec_ids: HashMap<u32, u32> = HashMap::new();
+    let mut scalar_constants: HashMap<u32, u32> = HashMap::new();
+    let mut composite_constants: HashMap<u32, &[u32]> = HashMap::new();
+    for &(opcode, operands) in instructions {
+        match (opcode, operands.len()) {
+            (OP_ENTRY_POINT, 3..) => {
+                entry_points.push((
+                    operands[1],
+                    operands[0],
+                    spirv_literal_string(&operands[2..]),
+                ));
+            }
+            (OP_EXECUTION_MODE, 5..) if operands[1] == EXECUTION_MODE_LOCAL_SIZE => {
+                local_sizes.insert(operands[0], [operands[2], operands[3], operands[4]]);
+            }
+            (OP_EXECUTION_MODE_ID, 5..) if operands[1] == EXECUTION_MODE_LOCAL_SIZE_ID => {
+                local_size_ids.insert(operands[0], [operands[2], operands[3], operands[4]]);
+            }
+            (OP_DECORATE, 3..) if operands[1] == DECORATION_SPEC_ID => {
+                spec_ids.insert(operands[0], operands[2]);
+            }
+            (OP_DECORATE, 3..)
+                if operands[1] == DECORATION_BUILT_IN && operands[2] == BUILT_IN_WORKGROUP_SIZE =>
+            {
+                workgroup_size_builtin = Some(operands[0]);
+            }
+            (OP_CONSTANT | OP_SPEC_CONSTANT, 3..) => {
+                scalar_constants.insert(operands[1], operands[2]);
+            }
+            (OP_CONSTANT_COMPOSITE | OP_SPEC_CONSTANT_COMPOSITE, 2..) => {
+                composite_constants.insert(operands[1], &operands[2..]);
+            }
+            _ => {}
+        }
+    }
+
+    // size of the constants `ids`, sizes computed by specialization constant operations can't
+    // be reflected
+    let constant_size = |ids: &[u32]| -> Result<SprivWorkgroupSize, ErrorCode> {
+        if ids.len() != 3 {
+            return Err(invalid_spirv("workgroup size is not a 3 component vector"));
+        }
+        let mut workgroup_size = SprivWorkgroupSize {
+            size: [1; 3],
+            spec_ids: [None; 3],
+        };
+        for (axis, id) in ids.iter().enumerate() {
+            workgroup_size.size[axis] = *scalar_constants
+                .get(id)
+                .ok_or_else(|| invalid_spirv("workgroup size is not a constant"))?;
+            workgroup_size.spec_ids[axis] = spec_ids.get(id).copied();
+        }
+        Ok(workgroup_size)
+    };
+    let builtin_size = match workgroup_size_builtin {
+        Some(id) => {
+            let ids = composite_constants
+                .get(&id)
+                .ok_or_else(|| invalid_spirv("workgroup size is not a constant"))?;
+            Some(constant_size(ids)?)
+        }
+        None => None,
+    };
+
+    let mut result = vec![];
+    for (function, execution_model, name) in entry_points {
+        let execution_model = ExecutionModel::from_spirv(execution_model);
+        let workgroup_size = if !execution_model.has_workgroup() {
+            None
+        } else if builtin_size.is_some() {
+            builtin_size
+        } else if let Some(ids) = local_size_ids.get(&function) {
+            Some(constant_size(ids)?)
+        } else {
+            local_sizes.get(&function).map(|size| SprivWorkgroupSize {
+                size: *size,
+                spec_ids: [None; 3],
+            })
+        };
+        result.push(SprivEntryPoint {
+            name,
+            execution_model,
+            workgroup_size,
+        });
+    }
+    Ok(result)
+}
+
+// specialization constants decorated with a SpecId
+fn reflect_spec_constants(instructions: &[(u32, &[u32])]) -> Vec<SprivSpecConstant> {
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let mut spec_ids: HashMap<u32, u32> = HashMap::new();
+    let mut scalar_types: HashMap<u32, BaseType> = HashMap::new();
+    // result id, type id and default value
+    let mut constants = vec![];
+    for &(opcode, operands) in instructions {
+        match (opcode, operands.len()) {
+            (OP_NAME, 2..) => {
+                names.insert(operands[0], spirv_literal_string(&operands[1..]));
+            }
+            (OP_DECORATE, 3..) if operands[1] == DECORATION_SPEC_ID => {
+                spec_ids.insert(operands[0], operands[2]);
+            }
+            (OP_TYPE_BOOL, 1..) => {
+                scalar_types.insert(operands[0], BaseType::BaseTypeBool);
+            }
+            (OP_TYPE_INT, 3..) => {
+                let base_type = match (operands[1], operands[2] != 0) {
+                    (8, true) => BaseType::BaseTypeInt8,
+                    (8, false) => BaseType::BaseTypeUInt8,
+                    (16, true) => BaseType::BaseTypeInt16,
+                    (16, false) => BaseType::BaseTypeUInt16,
+                    (32, true) => BaseType::BaseTypeInt,
+                    (32, false) => BaseType::BaseTypeUInt,
+                    (64, true) => BaseType::BaseTypeInt64,
+                    (64, false) => BaseType::BaseTypeUInt64,
+                    _ => BaseType::BaseTypeUnknown,
+                };
+                scalar_types.insert(operands[0], base_type);
+            }
+            (OP_TYPE_FLOAT, 2..) => {
+                let base_type = match operands[1] {
+                    16 => BaseType::BaseTypeHalf,
+                    32 => BaseType::BaseTypeFloat,
+                    64 => BaseType::BaseTypeDouble,
+                    _ => BaseType::BaseTypeUnknown,
+                };
+                scalar_types.insert(operands[0], base_type);
+            }
+            (OP_SPEC_CONSTANT_TRUE, 2..) => constants.push((operands[1], operands[0], 1)),
+            (OP_SPEC_CONSTANT_FALSE, 2..) => constants.push((operands[1], operands[0], 0)),
+            (OP_SPEC_CONSTANT, 3..) => {
+                // the high word follows for 64 bit types
+                let high = operands.get(3).copied().unwrap_or(0) as u64;
+                constants.push((operands[1], operands[0], operands[2] as u64 | high << 32));
+            }
+            _ => {}
+        }
+    }
+
+    let mut spec_constants = vec![];
+    for (id, type_id, default_value) in constants {
+        let constant_id = match spec_ids.get(&id) {
+            Some(constant_id) => *constant_id,
+            None => continue,
+        };
+        spec_constants.push(SprivSpecConstant {
+            name: names.remove(&id).unwrap_or_default(),
+            constant_id,
+            base_type: scalar_types
+                .get(&type_id)
+                .copied()
+                .unwrap_or(BaseType::BaseTypeUnknown),
+            default_value,
+        });
+    }
+    spec_constants
+}
+
+// global variables referenced by the functions reachable from `entry_point`. Every operand of
+// an instruction in a function body is treated as an id, so a literal that happens to equal a
+// variable id marks it used, which errs on the safe side for descriptor updates.
+fn active_variables(
+    instructions: &[(u32, &[u32])],
+    entry_point: &str,
+) -> Result<HashSet<u32>, ErrorCode> {
+    let mut entry_function = None;
+    let mut global_variables = HashSet::new();
+    let mut references: HashMap<u32, HashSet<u32>> = HashMap::new();
+    let mut current_function = None;
+    for &(opcode, operands) in instructions {
+        match (opcode, current_function) {
+            (OP_ENTRY_POINT, _)
+                if operands.len() >= 3 && spirv_literal_string(&operands[2..]) == entry_point =>
+            {
+                entry_function = Some(operands[1]);
+            }
+            (OP_FUNCTION, _) if operands.len() >= 2 => {
+                current_function = Some(operands[1]);
+                references.entry(operands[1]).or_default();
+            }
+            (OP_FUNCTION_END, _) => current_function = None,
+            (OP_VARIABLE, None) if operands.len() >= 2 => {
+                global_variables.insert(operands[1]);
+            }
+            (_, Some(function)) => {
+                references.entry(function).or_default().extend(operands);
+            }
+            _ => {}
+        }
+    }
+    let entry_function = entry_function.ok_or_else(|| {
+        ErrorCode::CompilationError(format!("entry point {} not found", entry_point))
+    })?;
+
+    // follow function calls, any referenced id that's a function is called from it
+    let mut active = HashSet::new();
+    let mut visited = HashSet::from([entry_function]);
+    let mut pending = vec![entry_function];
+    while let Some(function) = pending.pop() {
+        for id in &references[&function] {
+            if references.contains_key(id) {
+                if visited.insert(*id) {
+                    pending.push(*id);
+                }
+            } else if global_variables.contains(id) {
+                active.insert(*id);
+            }
+        }
+    }
+    Ok(active)
+}
+
+// array type ids to their element type and length, 0 for runtime sized arrays
+fn array_types(instructions: &[(u32, &[u32])]) -> HashMap<u32, (u32, u32)> {
+    let mut array_types = HashMap::new();
+    let mut constants: HashMap<u32, u32> = HashMap::new();
+    for &(opcode, operands) in instructions {
+        match (opcode, operands.len()) {
+            (OP_CONSTANT, 3..) => {
+                constants.insert(operands[1], operands[2]);
+            }
+            (OP_TYPE_ARRAY, 3..) => {
+                let length = constants.get(&operands[2]).copied().unwrap_or(0);
+                array_types.insert(operands[0], (operands[1], length));
+            }
+            (OP_TYPE_RUNTIME_ARRAY, 2..) => {
+                array_types.insert(operands[0], (operands[1], 0));
+            }
+            _ => {}
+        }
+    }
+    array_types
+}
+
+// acceleration structure variables with their array sizes
+fn acceleration_structures(
+    instructions: &[(u32, &[u32])],
+    array_types: &HashMap<u32, (u32, u32)>,
+) -> Vec<(Resource, Vec<u32>)> {
+    let mut acceleration_structure_types = HashSet::new();
+    let mut pointer_types: HashMap<u32, u32> = HashMap::new();
+    for &(opcode, operands) in instructions {
+        match (opcode, operands.len()) {
+            (OP_TYPE_ACCELERATION_STRUCTURE, 1..) => {
+                acceleration_structure_types.insert(operands[0]);
+            }
+            (OP_TYPE_POINTER, 3..) if operands[1] == STORAGE_CLASS_UNIFORM_CONSTANT => {
+                pointer_types.insert(operands[0], operands[2]);
+            }
+            _ => {}
+        }
+    }
+    let mut result = vec![];
+    for &(opcode, operands) in instructions {
+        if opcode != OP_VARIABLE || operands.len() < 3 {
+            continue;
+        }
+        let type_id = match pointer_types.get(&operands[0]) {
+            Some(type_id) => *type_id,
+            None => continue,
+        };
+        let mut base_type_id = type_id;
+        let mut array_size = vec![];
+        while let Some((element, length)) = array_types.get(&base_type_id) {
+            array_size.push(*length);
+            base_type_id = *element;
+        }
+        if acceleration_structure_types.contains(&base_type_id) {
+            // same order as spirv_cross, innermost first
+            array_size.reverse();
+            let resource = Resource {
+                id: operands[1],
+                type_id,
+                base_type_id,
+                name: String::new(),
+            };
+            result.push((resource, array_size));
+        }
+    }
+    result
+}
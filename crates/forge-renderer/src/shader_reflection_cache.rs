@@ -0,0 +1,433 @@
+use crate::{
+    byte_io::{fnv1a, ByteReader, ByteWriter},
+    shader_reflection::{
+        get_shader_resource, BaseType, ExecutionModel, ResourceDim, ResourceType, ShaderReflection,
+        SprivEntryPoint, SprivResource, SprivSpecConstant, SprivStructMember, SprivVertexInput,
+        SprivWorkgroupSize,
+    },
+};
+use spirv_cross::{
+    glsl,
+    spirv::{Ast, Module, Resource},
+    ErrorCode,
+};
+
+const CACHE_MAGIC: &[u8; 4] = b"FSRC";
+// bump when the layout of the cache or what reflection produces changes
+const CACHE_VERSION: u32 = 1;
+// struct nesting deeper than this is treated as corrupt rather than recursed into
+const MAX_MEMBER_DEPTH: u32 = 64;
+
+const RESOURCE_TYPES: [ResourceType; 14] = [
+    ResourceType::TypeStageInputs,
+    ResourceType::TypeStageOutputs,
+    ResourceType::TypeUniformBuffers,
+    ResourceType::TypeStorageBuffers,
+    ResourceType::TypeImages,
+    ResourceType::TypeStorageImages,
+    ResourceType::TypeSamplers,
+    ResourceType::TypePushConstant,
+    ResourceType::TypeSubpassInputs,
+    ResourceType::TypeUniformTexelBuffers,
+    ResourceType::TypeStorageTexelBuffers,
+    ResourceType::TypeAccelerationStructures,
+    ResourceType::TypeCombinedSamplers,
+    ResourceType::TypeCount,
+];
+
+const RESOURCE_DIMS: [ResourceDim; 12] = [
+    ResourceDim::DimUndefined,
+    ResourceDim::DimBuffer,
+    ResourceDim::DimTexture1D,
+    ResourceDim::DimTexture1DArray,
+    ResourceDim::DimTexture2D,
+    ResourceDim::DimTexture2DArray,
+    ResourceDim::DimTexture2DMS,
+    ResourceDim::DimTexture2DMSArray,
+    ResourceDim::DimTexture3D,
+    ResourceDim::DimTextureCube,
+    ResourceDim::DimTextureCubeArray,
+    ResourceDim::DimCount,
+];
+
+const BASE_TYPES: [BaseType; 14] = [
+    BaseType::BaseTypeUnknown,
+    BaseType::BaseTypeBool,
+    BaseType::BaseTypeInt8,
+    BaseType::BaseTypeUInt8,
+    BaseType::BaseTypeInt16,
+    BaseType::BaseTypeUInt16,
+    BaseType::BaseTypeInt,
+    BaseType::BaseTypeUInt,
+    BaseType::BaseTypeInt64,
+    BaseType::BaseTypeUInt64,
+    BaseType::BaseTypeHalf,
+    BaseType::BaseTypeFloat,
+    BaseType::BaseTypeDouble,
+    BaseType::BaseTypeStruct,
+];
+
+const EXECUTION_MODELS: [ExecutionModel; 16] = [
+    ExecutionModel::ExecutionModelVertex,
+    ExecutionModel::ExecutionModelTessellationControl,
+    ExecutionModel::ExecutionModelTessellationEvaluation,
+    ExecutionModel::ExecutionModelGeometry,
+    ExecutionModel::ExecutionModelFragment,
+    ExecutionModel::ExecutionModelGLCompute,
+    ExecutionModel::ExecutionModelKernel,
+    ExecutionModel::ExecutionModelTask,
+    ExecutionModel::ExecutionModelMesh,
+    ExecutionModel::ExecutionModelRayGeneration,
+    ExecutionModel::ExecutionModelIntersection,
+    ExecutionModel::ExecutionModelAnyHit,
+    ExecutionModel::ExecutionModelClosestHit,
+    ExecutionModel::ExecutionModelMiss,
+    ExecutionModel::ExecutionModelCallable,
+    ExecutionModel::ExecutionModelUnknown,
+];
+
+/// 64 bit FNV-1a of the SPIR-V words, stable between runs and builds so it can key caches on
+/// disk.
+pub fn spirv_hash(spirv: &[u32]) -> u64 {
+    fnv1a(spirv.iter().flat_map(|word| word.to_le_bytes()))
+}
+
+impl ByteWriter {
+    fn members(&mut self, members: &[SprivStructMember]) {
+        self.u32(members.len() as u32);
+        for member in members {
+            self.string(&member.name);
+            self.u32(member.base_type as u32);
+            self.u32(member.vec_size);
+            self.u32(member.columns);
+            self.u32(member.offset);
+            self.u32(member.size);
+            self.u32_array(&member.array_size);
+            self.u32(member.array_stride);
+            self.u32(member.matrix_stride);
+            self.u8(member.row_major as u8);
+            self.members(&member.members);
+        }
+    }
+}
+
+impl ByteReader<'_> {
+    fn members(&mut self, depth: u32) -> Option<Vec<SprivStructMember>> {
+        let depth = depth.checked_sub(1)?;
+        let count = self.count(4)?;
+        let mut members = Vec::with_capacity(count);
+        for _ in 0..count {
+            members.push(SprivStructMember {
+                name: self.string()?,
+                base_type: self.enum_value(&BASE_TYPES)?,
+                vec_size: self.u32()?,
+                columns: self.u32()?,
+                offset: self.u32()?,
+                size: self.u32()?,
+                array_size: self.u32_array()?,
+                array_stride: self.u32()?,
+                matrix_stride: self.u32()?,
+                row_major: self.bool()?,
+                members: self.members(depth)?,
+            });
+        }
+        Some(members)
+    }
+}
+
+/// Compact binary form of `reflection` keyed by the hash of the SPIR-V it was reflected from.
+pub fn serialize_reflection(reflection: &ShaderReflection, spirv_hash: u64) -> Vec<u8> {
+    let mut writer = ByteWriter { bytes: vec![] };
+    writer.bytes.extend_from_slice(CACHE_MAGIC);
+    writer.u32(CACHE_VERSION);
+    writer.u64(spirv_hash);
+
+    let entry_point = &reflection.entry_point;
+    writer.string(&entry_point.name);
+    writer.u32(entry_point.execution_model as u32);
+    match &entry_point.workgroup_size {
+        Some(workgroup_size) => {
+            writer.u8(1);
+            for (size, spec_id) in workgroup_size.size.iter().zip(workgroup_size.spec_ids) {
+                writer.u32(*size);
+                // u32::MAX for none, SpecId values are 31 bit at most
+                writer.u32(spec_id.unwrap_or(u32::MAX));
+            }
+        }
+        None => writer.u8(0),
+    }
+
+    writer.u32(reflection.resources.len() as u32);
+    for resource in &reflection.resources {
+        writer.string(&resource.name);
+        writer.u32(resource.code.id);
+        writer.u32(resource.code.type_id);
+        writer.u32(resource.code.base_type_id);
+        writer.string(&resource.code.name);
+        writer.u32(resource.resource_type as u32);
+        writer.u32(resource.resource_dim as u32);
+        writer.u32(resource.set);
+        writer.u32(resource.binding);
+        writer.u32_array(&resource.array_size);
+        writer.u8(resource.is_used as u8);
+        writer.u32(resource.size);
+        writer.members(&resource.members);
+    }
+
+    writer.u32(reflection.spec_constants.len() as u32);
+    for spec_constant in &reflection.spec_constants {
+        writer.string(&spec_constant.name);
+        writer.u32(spec_constant.constant_id);
+        writer.u32(spec_constant.base_type as u32);
+        writer.u64(spec_constant.default_value);
+    }
+
+    writer.u32(reflection.vertex_inputs.len() as u32);
+    for vertex_input in &reflection.vertex_inputs {
+        writer.string(&vertex_input.name);
+        writer.u32(vertex_input.location);
+        writer.u32(vertex_input.base_type as u32);
+        writer.u32(vertex_input.vec_size);
+        writer.u32(vertex_input.location_count);
+    }
+
+    // checksum of everything before it, catches caches damaged on disk
+    let checksum = fnv1a(writer.bytes.iter().copied());
+    writer.u64(checksum);
+    writer.bytes
+}
+
+/// Reads a reflection written by `serialize_reflection`, none when `bytes` is corrupt, from
+/// another cache version, or doesn't match `spirv_hash` and `entry_point`.
+pub fn deserialize_reflection(
+    bytes: &[u8],
+    spirv_hash: u64,
+    entry_point: &str,
+) -> Option<ShaderReflection> {
+    let (bytes, checksum) = bytes.split_at(bytes.len().checked_sub(8)?);
+    if fnv1a(bytes.iter().copied()) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+        return None;
+    }
+    let mut reader = ByteReader { bytes, cursor: 0 };
+    if reader.take(4)? != CACHE_MAGIC || reader.u32()? != CACHE_VERSION {
+        return None;
+    }
+    if reader.u64()? != spirv_hash {
+        return None;
+    }
+
+    let name = reader.string()?;
+    if name != entry_point {
+        return None;
+    }
+    let execution_model = reader.enum_value(&EXECUTION_MODELS)?;
+    let workgroup_size = match reader.bool()? {
+        true => {
+            let mut workgroup_size = SprivWorkgroupSize {
+                size: [0; 3],
+                spec_ids: [None; 3],
+            };
+            for axis in 0..3 {
+                workgroup_size.size[axis] = reader.u32()?;
+                workgroup_size.spec_ids[axis] = match reader.u32()? {
+                    u32::MAX => None,
+                    spec_id => Some(spec_id),
+                };
+            }
+            Some(workgroup_size)
+        }
+        false => None,
+    };
+    let mut reflection = ShaderReflection {
+        entry_point: SprivEntryPoint {
+            name,
+            execution_model,
+            workgroup_size,
+        },
+        resources: vec![],
+        spec_constants: vec![],
+        vertex_inputs: vec![],
+    };
+
+    for _ in 0..reader.count(4)? {
+        let name = reader.string()?;
+        let code = Resource {
+            id: reader.u32()?,
+            type_id: reader.u32()?,
+            base_type_id: reader.u32()?,
+            name: reader.string()?,
+        };
+        reflection.resources.push(SprivResource {
+            name,
+            code,
+            resource_type: reader.enum_value(&RESOURCE_TYPES)?,
+            resource_dim: reader.enum_value(&RESOURCE_DIMS)?,
+            set: reader.u32()?,
+            binding: reader.u32()?,
+            array_size: reader.u32_array()?,
+            is_used: reader.bool()?,
+            size: reader.u32()?,
+            members: reader.members(MAX_MEMBER_DEPTH)?,
+        });
+    }
+
+    for _ in 0..reader.count(4)? {
+        reflection.spec_constants.push(SprivSpecConstant {
+            name: reader.string()?,
+            constant_id: reader.u32()?,
+            base_type: reader.enum_value(&BASE_TYPES)?,
+            default_value: reader.u64()?,
+        });
+    }
+
+    for _ in 0..reader.count(4)? {
+        reflection.vertex_inputs.push(SprivVertexInput {
+            name: reader.string()?,
+            location: reader.u32()?,
+            base_type: reader.enum_value(&BASE_TYPES)?,
+            vec_size: reader.u32()?,
+            location_count: reader.u32()?,
+        });
+    }
+
+    if reader.cursor != bytes.len() {
+        return None;
+    }
+    Some(reflection)
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn json_array<T>(values: &[T], to_json: impl Fn(&T) -> String) -> String {
+    let values: Vec<String> = values.iter().map(to_json).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_members(members: &[SprivStructMember]) -> String {
+    json_array(members, |member| {
+        format!(
+            "{{\"name\":{},\"base_type\":\"{:?}\",\"vec_size\":{},\"columns\":{},\"offset\":{},\
+             \"size\":{},\"array_size\":{:?},\"array_stride\":{},\"matrix_stride\":{},\
+             \"row_major\":{},\"members\":{}}}",
+            json_string(&member.name),
+            member.base_type,
+            member.vec_size,
+            member.columns,
+            member.offset,
+            member.size,
+            member.array_size,
+            member.array_stride,
+            member.matrix_stride,
+            member.row_major,
+            json_members(&member.members),
+        )
+    })
+}
+
+/// JSON form of `reflection` with the same content as the binary one, for tools and for
+/// reading the cache. The SPIR-V hash is written as a hex string since JSON numbers can't hold
+/// every 64 bit value.
+pub fn reflection_to_json(reflection: &ShaderReflection, spirv_hash: u64) -> String {
+    let entry_point = &reflection.entry_point;
+    let workgroup_size = match &entry_point.workgroup_size {
+        Some(workgroup_size) => format!(
+            "{{\"size\":{:?},\"spec_ids\":{}}}",
+            workgroup_size.size,
+            json_array(&workgroup_size.spec_ids, |spec_id| match spec_id {
+                Some(spec_id) => spec_id.to_string(),
+                None => "null".to_string(),
+            }),
+        ),
+        None => "null".to_string(),
+    };
+    let resources = json_array(&reflection.resources, |resource| {
+        format!(
+            "{{\"name\":{},\"id\":{},\"type_id\":{},\"base_type_id\":{},\"block_name\":{},\
+             \"resource_type\":\"{:?}\",\"resource_dim\":\"{:?}\",\"set\":{},\"binding\":{},\
+             \"array_size\":{:?},\"is_used\":{},\"size\":{},\"members\":{}}}",
+            json_string(&resource.name),
+            resource.code.id,
+            resource.code.type_id,
+            resource.code.base_type_id,
+            json_string(&resource.code.name),
+            resource.resource_type,
+            resource.resource_dim,
+            resource.set,
+            resource.binding,
+            resource.array_size,
+            resource.is_used,
+            resource.size,
+            json_members(&resource.members),
+        )
+    });
+    let spec_constants = json_array(&reflection.spec_constants, |spec_constant| {
+        format!(
+            "{{\"name\":{},\"constant_id\":{},\"base_type\":\"{:?}\",\"default_value\":\"{:#x}\"}}",
+            json_string(&spec_constant.name),
+            spec_constant.constant_id,
+            spec_constant.base_type,
+            spec_constant.default_value,
+        )
+    });
+    let vertex_inputs = json_array(&reflection.vertex_inputs, |vertex_input| {
+        format!(
+            "{{\"name\":{},\"location\":{},\"base_type\":\"{:?}\",\"vec_size\":{},\
+             \"location_count\":{}}}",
+            json_string(&vertex_input.name),
+            vertex_input.location,
+            vertex_input.base_type,
+            vertex_input.vec_size,
+            vertex_input.location_count,
+        )
+    });
+    format!(
+        "{{\"version\":{},\"spirv_hash\":\"{:016x}\",\"entry_point\":{{\"name\":{},\
+         \"execution_model\":\"{:?}\",\"workgroup_size\":{}}},\"resources\":{},\
+         \"spec_constants\":{},\"vertex_inputs\":{}}}",
+        CACHE_VERSION,
+        spirv_hash,
+        json_string(&entry_point.name),
+        entry_point.execution_model,
+        workgroup_size,
+        resources,
+        spec_constants,
+        vertex_inputs,
+    )
+}
+
+/// Reflects `entry_point` of `spirv`, from `cache` when it holds a valid reflection of it and
+/// otherwise through spirv_cross. A fresh cache is returned alongside when `cache` couldn't be
+/// used, for the caller to store.
+pub fn load_shader_reflection(
+    cache: Option<&[u8]>,
+    spirv: &[u32],
+    entry_point: &str,
+) -> Result<(ShaderReflection, Option<Vec<u8>>), ErrorCode> {
+    let hash = spirv_hash(spirv);
+    if let Some(reflection) =
+        cache.and_then(|cache| deserialize_reflection(cache, hash, entry_point))
+    {
+        return Ok((reflection, None));
+    }
+    let module = Module::from_words(spirv);
+    let mut ast = Ast::<glsl::Target>::parse(&module)?;
+    let reflection = get_shader_resource(&mut ast, spirv, entry_point)?;
+    let cache = serialize_reflection(&reflection, hash);
+    Ok((reflection, Some(cache)))
+}
@@ -0,0 +1,246 @@
+use crate::{
+    byte_io::{fnv1a, ByteReader, ByteWriter},
+    desc::{BinaryShaderDesc, BinaryShaderStageDesc, SpecConstantDesc},
+    error::{RendererError, RendererResult},
+    types::{ShaderStage, ShaderStageFlags},
+    Api, Renderer,
+};
+use std::{collections::HashMap, ffi::CString};
+
+const TABLE_MAGIC: &[u8; 4] = b"FSVT";
+const TABLE_VERSION: u32 = 1;
+
+const MAX_KEYWORDS: usize = 64;
+
+const SHADER_STAGES: [ShaderStage; 7] = [
+    ShaderStage::Vertex,
+    ShaderStage::TesselationControl,
+    ShaderStage::TesselationEvaluation,
+    ShaderStage::Geometry,
+    ShaderStage::Fragment,
+    ShaderStage::Compute,
+    ShaderStage::Raytracing,
+];
+
+/// Keywords enabled in a variant, bit `i` is the `i`th keyword of its `ShaderVariants`.
+pub type ShaderVariantKey = u64;
+
+#[derive(Clone)]
+pub struct ShaderVariantStage {
+    pub stage: ShaderStage,
+    pub entry_point_name: CString,
+    pub spirv: Vec<u32>,
+}
+
+// builds the stages of a variant from its enabled keywords
+pub type ShaderVariantCompiler = Box<dyn FnMut(&[&str]) -> Option<Vec<ShaderVariantStage>>>;
+
+/// The permutations of one shader over a set of keywords. Variants come from precompiled SPIR-V,
+/// added one by one or as a lookup table, or from a compiler callback for the ones missing, and
+/// their shader objects are created on first use and kept until `clear_shaders`.
+pub struct ShaderVariants<A: Api> {
+    keywords: Vec<String>,
+    // specialization constant values for every variant
+    constants: Vec<SpecConstantDesc>,
+    precompiled: HashMap<ShaderVariantKey, Vec<ShaderVariantStage>>,
+    compiler: Option<ShaderVariantCompiler>,
+    shaders: HashMap<ShaderVariantKey, A::Shader>,
+}
+
+impl<A: Api> ShaderVariants<A> {
+    /// At most 64 keywords.
+    pub fn new(keywords: &[&str]) -> ShaderVariants<A> {
+        assert!(keywords.len() <= MAX_KEYWORDS);
+        ShaderVariants {
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            constants: vec![],
+            precompiled: HashMap::new(),
+            compiler: None,
+            shaders: HashMap::new(),
+        }
+    }
+
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    pub fn set_constants(&mut self, constants: Vec<SpecConstantDesc>) {
+        self.constants = constants;
+    }
+
+    pub fn set_compiler(&mut self, compiler: ShaderVariantCompiler) {
+        self.compiler = Some(compiler);
+    }
+
+    /// Key of the variant with `keywords` enabled, in any order.
+    pub fn key(&self, keywords: &[&str]) -> RendererResult<ShaderVariantKey> {
+        let mut key = 0;
+        for keyword in keywords {
+            let index = self
+                .keywords
+                .iter()
+                .position(|declared| declared == keyword)
+                .ok_or_else(|| RendererError::UnknownShaderKeyword(keyword.to_string()))?;
+            key |= 1 << index;
+        }
+        Ok(key)
+    }
+
+    pub fn enabled_keywords(&self, key: ShaderVariantKey) -> Vec<&str> {
+        self.keywords
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| key & (1 << index) != 0)
+            .map(|(_, keyword)| keyword.as_str())
+            .collect()
+    }
+
+    pub fn add_precompiled(&mut self, key: ShaderVariantKey, stages: Vec<ShaderVariantStage>) {
+        self.precompiled.insert(key, stages);
+    }
+
+    pub fn has_variant(&self, key: ShaderVariantKey) -> bool {
+        self.precompiled.contains_key(&key)
+    }
+
+    /// Returns the shader of the variant with `keywords` enabled, creating it on first use.
+    pub unsafe fn get_shader(
+        &mut self,
+        renderer: &A::Renderer,
+        keywords: &[&str],
+    ) -> RendererResult<&A::Shader> {
+        let key = self.key(keywords)?;
+        if !self.shaders.contains_key(&key) {
+            if !self.precompiled.contains_key(&key) {
+                let stages = match self.compiler.take() {
+                    Some(mut compiler) => {
+                        let stages = compiler(&self.enabled_keywords(key));
+                        self.compiler = Some(compiler);
+                        stages
+                    }
+                    None => None,
+                };
+                let stages = stages.ok_or(RendererError::MissingShaderVariant(key))?;
+                self.precompiled.insert(key, stages);
+            }
+
+            let stages = &self.precompiled[&key];
+            let mut desc = BinaryShaderDesc {
+                stages: ShaderStageFlags::STAGE_NONE,
+                shaders: vec![],
+                constants: self.constants.clone(),
+            };
+            for stage in stages {
+                desc.stages |= ShaderStageFlags::from_bits_truncate(1 << stage.stage as u32);
+                desc.shaders.push(BinaryShaderStageDesc {
+                    byte_code: stage.spirv.as_ptr(),
+                    byte_code_size: (stage.spirv.len() * 4) as u32,
+                    entry_point_name: stage.entry_point_name.clone(),
+                });
+            }
+            let shader = renderer.add_shader_binary(&desc)?;
+            self.shaders.insert(key, shader);
+        }
+        Ok(&self.shaders[&key])
+    }
+
+    /// Drops every created shader, the SPIR-V is kept.
+    pub fn clear_shaders(&mut self) {
+        self.shaders.clear();
+    }
+
+    /// Lookup table of every variant with SPIR-V, including the ones the compiler built, for
+    /// `load_lookup_table`.
+    pub fn lookup_table(&self) -> Vec<u8> {
+        let mut writer = ByteWriter { bytes: vec![] };
+        writer.bytes.extend_from_slice(TABLE_MAGIC);
+        writer.u32(TABLE_VERSION);
+        writer.u32(self.keywords.len() as u32);
+        for keyword in &self.keywords {
+            writer.string(keyword);
+        }
+        let mut keys: Vec<&ShaderVariantKey> = self.precompiled.keys().collect();
+        keys.sort();
+        writer.u32(keys.len() as u32);
+        for key in keys {
+            let stages = &self.precompiled[key];
+            writer.u64(*key);
+            writer.u32(stages.len() as u32);
+            for stage in stages {
+                writer.u32(stage.stage as u32);
+                writer.string(&stage.entry_point_name.to_string_lossy());
+                writer.u32_array(&stage.spirv);
+            }
+        }
+        let checksum = fnv1a(writer.bytes.iter().copied());
+        writer.u64(checksum);
+        writer.bytes
+    }
+
+    /// Adds the variants of a table from `lookup_table`, keywords are matched by name so tables
+    /// written with another keyword order or a subset of the keywords load too.
+    pub fn load_lookup_table(&mut self, bytes: &[u8]) -> RendererResult<()> {
+        let variants = self
+            .parse_lookup_table(bytes)
+            .ok_or(RendererError::InvalidShaderVariantTable)??;
+        self.precompiled.extend(variants);
+        Ok(())
+    }
+
+    // none for a corrupt table, an error for keywords this set doesn't declare
+    #[allow(clippy::type_complexity)]
+    fn parse_lookup_table(
+        &self,
+        bytes: &[u8],
+    ) -> Option<RendererResult<Vec<(ShaderVariantKey, Vec<ShaderVariantStage>)>>> {
+        let (bytes, checksum) = bytes.split_at(bytes.len().checked_sub(8)?);
+        if fnv1a(bytes.iter().copied()) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+            return None;
+        }
+        let mut reader = ByteReader { bytes, cursor: 0 };
+        if reader.take(4)? != TABLE_MAGIC || reader.u32()? != TABLE_VERSION {
+            return None;
+        }
+        let keyword_count = reader.count(4)?;
+        if keyword_count > MAX_KEYWORDS {
+            return None;
+        }
+        // key bit of each table keyword in this set
+        let mut key_bits = vec![];
+        for _ in 0..keyword_count {
+            let keyword = reader.string()?;
+            match self.key(&[&keyword]) {
+                Ok(bit) => key_bits.push(bit),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        let mut variants = vec![];
+        for _ in 0..reader.count(12)? {
+            let table_key = reader.u64()?;
+            if keyword_count < MAX_KEYWORDS && table_key >> keyword_count != 0 {
+                return None;
+            }
+            let key = key_bits
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| table_key & (1 << index) != 0)
+                .fold(0, |key, (_, bit)| key | bit);
+            let mut stages = vec![];
+            for _ in 0..reader.count(12)? {
+                let stage = *SHADER_STAGES.get(reader.u32()? as usize)?;
+                let entry_point_name = CString::new(reader.string()?).ok()?;
+                stages.push(ShaderVariantStage {
+                    stage,
+                    entry_point_name,
+                    spirv: reader.u32_array()?,
+                });
+            }
+            variants.push((key, stages));
+        }
+        if reader.cursor != bytes.len() {
+            return None;
+        }
+        Some(Ok(variants))
+    }
+}
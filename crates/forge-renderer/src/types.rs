@@ -56,6 +56,39 @@ pub enum MipMapMode {
     Linear,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SpecConstantValue {
+    Bool(bool),
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int(i32),
+    UInt(u32),
+    // stored as a 16 bit float
+    Half(f32),
+    Float(f32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+}
+
+// versions are numbered like the spirv-cross command line, 450 for glsl 4.5, 310 for essl 3.1, 51
+// for shader model 5.1 and 20100 for msl 2.1
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CrossCompileTarget {
+    Glsl { version: u32 },
+    Essl { version: u32 },
+    Hlsl { shader_model: u32 },
+    Msl { version: u32, ios: bool },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VertexAttribRate {
+    Vertex,
+    Instance,
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum IndirectArgumentType {
     Draw,
@@ -77,7 +110,7 @@ pub enum IndirectArgumentType {
                         // } IndirectArgumentType;
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ShaderStage {
     Vertex,
     TesselationControl,